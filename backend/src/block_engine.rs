@@ -0,0 +1,102 @@
+//! Optional Jito block engine for bundle submission.
+//!
+//! With `BLOCK_ENGINE_URL` set (e.g. `https://mainnet.block-engine.jito.wtf/api/v1`), the
+//! `/submit-bundle` route forwards client-signed transactions to the block engine as one atomic
+//! bundle, and bundle mode requests are tipped to one of its tip accounts. `BLOCK_ENGINE_UUID` optionally
+//! sets the key of a rate limit increase.
+
+use {
+    crate::errors::AppError,
+    jito_sdk_rust::JitoJsonRpcSDK,
+    serde_json::{json, Value},
+    solana_sdk::{pubkey::Pubkey, transaction::VersionedTransaction},
+    std::str::FromStr,
+};
+
+/// Block engine the server submits bundles to
+pub struct BlockEngine {
+    sdk: JitoJsonRpcSDK,
+    uuid: Option<String>,
+}
+
+impl BlockEngine {
+    pub fn new(url: &str, uuid: Option<String>) -> Self {
+        Self {
+            sdk: JitoJsonRpcSDK::new(url, uuid.clone()),
+            uuid,
+        }
+    }
+
+    /// Configures the block engine from `BLOCK_ENGINE_URL`. Returns `None` if it is not set.
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("BLOCK_ENGINE_URL").ok()?;
+        println!("✅ Submitting bundles to {}", url);
+
+        Some(Self::new(&url, std::env::var("BLOCK_ENGINE_UUID").ok()))
+    }
+
+    /// The block engine's tip accounts
+    pub async fn tip_accounts(&self) -> Result<Vec<Pubkey>, AppError> {
+        let response = self
+            .sdk
+            .get_tip_accounts()
+            .await
+            .map_err(|e| AppError::BlockEngineError(e.to_string()))?;
+
+        response
+            .get("result")
+            .and_then(Value::as_array)
+            .ok_or_else(|| AppError::BlockEngineError(format!("no tip accounts in {}", response)))?
+            .iter()
+            .map(|tip_account| {
+                tip_account
+                    .as_str()
+                    .and_then(|tip_account| Pubkey::from_str(tip_account).ok())
+                    .ok_or_else(|| {
+                        AppError::BlockEngineError(format!("invalid tip account {}", tip_account))
+                    })
+            })
+            .collect()
+    }
+
+    /// One of the block engine's tip accounts, picked at random
+    pub async fn tip_account(&self) -> Result<Pubkey, AppError> {
+        let tip_account = self
+            .sdk
+            .get_random_tip_account()
+            .await
+            .map_err(|e| AppError::BlockEngineError(e.to_string()))?;
+        println!("✅ Fetched tip account: {}", tip_account);
+
+        Pubkey::from_str(&tip_account)
+            .map_err(|_| AppError::BlockEngineError(format!("invalid tip account {}", tip_account)))
+    }
+
+    /// Submits the transactions as one bundle, in order, and returns the bundle id
+    pub async fn send_bundle(
+        &self,
+        transactions: &[VersionedTransaction],
+    ) -> Result<String, AppError> {
+        let transactions = transactions
+            .iter()
+            .map(|transaction| Ok(bs58::encode(bincode::serialize(transaction)?).into_string()))
+            .collect::<Result<Vec<_>, AppError>>()?;
+
+        let response = self
+            .sdk
+            .send_bundle(Some(json!(transactions)), self.uuid.as_deref())
+            .await
+            .map_err(|e| AppError::BlockEngineError(e.to_string()))?;
+        if let Some(error) = response.get("error") {
+            return Err(AppError::BlockEngineError(error.to_string()));
+        }
+
+        let bundle_id = response
+            .get("result")
+            .and_then(Value::as_str)
+            .ok_or_else(|| AppError::BlockEngineError(format!("no bundle id in {}", response)))?;
+        println!("✅ Submitted bundle {}", bundle_id);
+
+        Ok(bundle_id.to_string())
+    }
+}
@@ -2,6 +2,7 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
 };
+use solana_client::client_error::ClientError;
 use solana_program::program_error::ProgramError;
 use solana_sdk::signature::SignerError;
 use solana_zk_sdk::errors::ElGamalError;
@@ -22,7 +23,6 @@ pub enum AppError {
     DecryptionError,
     #[allow(dead_code)]
     TransactionDataNotFound,
-    #[allow(dead_code)]
     InvalidPublicKey,
     #[allow(dead_code)]
     InvalidPrivateKey,
@@ -30,14 +30,56 @@ pub enum AppError {
     InvalidBlockhash,
     #[allow(dead_code)]
     InstructionCreationError,
+    MissingSupplySignatures,
+    NoCreditsConfigurationProvided,
+    MultisigNotProvided,
+    MultisigOwnerMismatch,
+    InvalidMultisigSigners,
+    MissingRequestField(&'static str),
+    NonceAccountCountMismatch {
+        expected: usize,
+        provided: usize,
+    },
+    InvalidNonceAccountCount,
+    NotEnoughNonceAccounts,
+    InvalidPriorityFee,
+    InvalidBundleSize,
+    UnsignedBundleTransaction,
+    InvalidTipAccount,
+    UnknownProofAccount,
+    InvalidBatchSize,
+    ConflictingAuditorUpdate,
     // 401/403 - Access errors
     InvalidAuditorSignature,
     AuditorAccessDenied,
+    ConfidentialTransferAuthorityMismatch,
+    SponsorshipDenied,
     // 404 - Not Found
     NoConfidentialTransferFound,
     // 422 - Unprocessable Entity
     AmountDecodeError,
     InvalidInstructionData,
+    MintAuthorityNotSet,
+    PendingBalanceNotEmpty,
+    AvailableBalanceNotEmpty,
+    NonConfidentialBalanceNotEmpty,
+    ConfidentialTransferAuthorityNotSet,
+    AccountAlreadyApproved,
+    RecipientNotConfidential,
+    RecipientNotApproved,
+    RecipientConfidentialCreditsDisabled,
+    RecipientPendingBalanceCreditLimitReached,
+    RecipientMemoRequired,
+    InvalidNonceAccount,
+    NonceAuthorityMismatch,
+    InvalidAddressLookupTable,
+    TransactionTooLarge,
+    StaleProofAccounts,
+    InsufficientConfidentialBalance {
+        available: u64,
+        requested: u64,
+        pending: u64,
+    },
     // Add variants for underlying errors
     TokenError(spl_token_2022::error::TokenError),
     BincodeError(bincode::Error),
@@ -48,6 +90,9 @@ pub enum AppError {
     ElGamalError(ElGamalError),
     CompileError(solana_message::CompileError),
     SignerError(SignerError),
+    RpcError(ClientError),
+    BlockEngineError(String),
+    BlockEngineNotConfigured,
 }
 
 // Implement Display for better error messages
@@ -57,11 +102,94 @@ impl fmt::Display for AppError {
             // 401/403 - Auditor access errors
             Self::InvalidAuditorSignature => write!(f, "Invalid auditor signature"),
             Self::AuditorAccessDenied => write!(f, "Auditor access denied"),
+            Self::ConfidentialTransferAuthorityMismatch => write!(
+                f,
+                "Caller is not the confidential transfer authority of the mint"
+            ),
+            Self::SponsorshipDenied => write!(
+                f,
+                "The server does not sponsor fees for this request, use another fee payer"
+            ),
             // 404 - Not Found
             Self::NoConfidentialTransferFound => write!(f, "No confidential transfer found"),
             // 422 - Unprocessable Entity
             Self::AmountDecodeError => write!(f, "Failed to decode amount"),
             Self::InvalidInstructionData => write!(f, "Invalid instruction data provided"),
+            Self::MintAuthorityNotSet => write!(f, "Mint has no mint authority"),
+            Self::PendingBalanceNotEmpty => write!(
+                f,
+                "Pending balance is not empty, apply the pending balance first"
+            ),
+            Self::AvailableBalanceNotEmpty => write!(
+                f,
+                "Confidential available balance is not empty, withdraw or transfer it first"
+            ),
+            Self::ConfidentialTransferAuthorityNotSet => {
+                write!(f, "Mint has no confidential transfer authority")
+            }
+            Self::AccountAlreadyApproved => {
+                write!(
+                    f,
+                    "Token account is already approved for confidential transfers"
+                )
+            }
+            Self::NonConfidentialBalanceNotEmpty => write!(
+                f,
+                "Non-confidential balance is not empty, the account cannot be closed"
+            ),
+            Self::RecipientNotConfidential => write!(
+                f,
+                "Recipient token account is not configured for confidential transfers"
+            ),
+            Self::RecipientNotApproved => write!(
+                f,
+                "Recipient token account is not approved for confidential transfers"
+            ),
+            Self::RecipientConfidentialCreditsDisabled => write!(
+                f,
+                "Recipient token account does not accept confidential credits"
+            ),
+            Self::RecipientPendingBalanceCreditLimitReached => write!(
+                f,
+                "Recipient pending balance credit counter is at its maximum, the recipient must apply their pending balance first"
+            ),
+            Self::RecipientMemoRequired => write!(
+                f,
+                "Recipient token account requires a memo on incoming transfers"
+            ),
+            Self::InvalidNonceAccount => write!(f, "Account is not an initialized nonce account"),
+            Self::NonceAuthorityMismatch => write!(
+                f,
+                "Nonce authority does not match the authority of the nonce account"
+            ),
+            Self::InvalidAddressLookupTable => write!(f, "Account is not an address lookup table"),
+            Self::TransactionTooLarge => write!(
+                f,
+                "Instructions do not fit in a single transaction, even with lookup tables"
+            ),
+            Self::StaleProofAccounts => write!(
+                f,
+                "Proof accounts do not match the flow and the current account balances, recover them and start over"
+            ),
+            Self::InsufficientConfidentialBalance {
+                available,
+                requested,
+                pending,
+            } => {
+                if available.saturating_add(*pending) >= *requested {
+                    write!(
+                        f,
+                        "Insufficient confidential available balance: requested {}, available {}. The pending balance of {} covers the difference, apply the pending balance first",
+                        requested, available, pending
+                    )
+                } else {
+                    write!(
+                        f,
+                        "Insufficient confidential balance: requested {}, available {} (pending {})",
+                        requested, available, pending
+                    )
+                }
+            }
             // Other errors
             Self::InvalidAddress => write!(f, "Invalid Solana account address"),
             Self::InvalidAmount => write!(f, "Invalid amount format"),
@@ -79,6 +207,65 @@ impl fmt::Display for AppError {
             Self::InvalidPrivateKey => write!(f, "Invalid ElGamal private key format"),
             Self::InvalidBlockhash => write!(f, "Invalid blockhash format"),
             Self::InstructionCreationError => write!(f, "Failed to create instruction"),
+            Self::NoCreditsConfigurationProvided => write!(
+                f,
+                "At least one of confidential_credits or non_confidential_credits is required"
+            ),
+            Self::MultisigNotProvided => {
+                write!(f, "Multisig signers were provided without a multisig account")
+            }
+            Self::MultisigOwnerMismatch => {
+                write!(f, "Multisig account is not the owner of the token account")
+            }
+            Self::InvalidMultisigSigners => write!(
+                f,
+                "A multisig requires between 1 and 11 signer addresses"
+            ),
+            Self::MissingRequestField(field) => write!(
+                f,
+                "{} is required when the server has no RPC endpoint configured",
+                field
+            ),
+            Self::NonceAccountCountMismatch { expected, provided } => write!(
+                f,
+                "Expected {} nonce accounts, one per transaction advancing a nonce, got {}",
+                expected, provided
+            ),
+            Self::InvalidNonceAccountCount => write!(
+                f,
+                "Between 1 and 5 nonce accounts can be created at a time"
+            ),
+            Self::NotEnoughNonceAccounts => write!(
+                f,
+                "Not enough nonce accounts for the durable transactions of the flow"
+            ),
+            Self::InvalidBundleSize => {
+                write!(f, "A bundle holds between 1 and 5 transactions")
+            }
+            Self::UnsignedBundleTransaction => {
+                write!(f, "Every bundle transaction must be fully signed")
+            }
+            Self::InvalidTipAccount => {
+                write!(f, "The tip account is not a tip account of the block engine")
+            }
+            Self::InvalidBatchSize => {
+                write!(f, "A batch holds between 1 and 100 transfers")
+            }
+            Self::ConflictingAuditorUpdate => write!(
+                f,
+                "Either set a new auditor ElGamal pubkey or remove the auditor, not both"
+            ),
+            Self::UnknownProofAccount => {
+                write!(f, "Account is not a proof account of the flow")
+            }
+            Self::InvalidPriorityFee => write!(
+                f,
+                "Priority fee needs exactly one of micro_lamports_per_compute_unit and total_lamports, as u64"
+            ),
+            Self::MissingSupplySignatures => write!(
+                f,
+                "ElGamal and AES signatures are required for confidential mint/burn"
+            ),
             Self::TokenError(e) => write!(f, "Token error: {}", e),
             Self::BincodeError(e) => write!(f, "Bincode error: {}", e),
             Self::Base64Error(e) => write!(f, "Base64 decoding error: {}", e),
@@ -88,6 +275,11 @@ impl fmt::Display for AppError {
             Self::ElGamalError(e) => write!(f, "ElGamal encryption error: {}", e),
             Self::CompileError(e) => write!(f, "Compile error: {}", e),
             Self::SignerError(e) => write!(f, "Signer error: {}", e),
+            Self::RpcError(e) => write!(f, "RPC error: {}", e),
+            Self::BlockEngineError(e) => write!(f, "Block engine error: {}", e),
+            Self::BlockEngineNotConfigured => {
+                write!(f, "The server has no block engine configured")
+            }
         }
     }
 }
@@ -103,20 +295,58 @@ impl IntoResponse for AppError {
             | AppError::InvalidTransactionHash
             | AppError::InvalidPublicKey
             | AppError::InvalidPrivateKey
-            | AppError::InvalidBlockhash => StatusCode::BAD_REQUEST,
+            | AppError::InvalidBlockhash
+            | AppError::MissingSupplySignatures
+            | AppError::NoCreditsConfigurationProvided
+            | AppError::MultisigNotProvided
+            | AppError::MultisigOwnerMismatch
+            | AppError::InvalidMultisigSigners
+            | AppError::MissingRequestField(_)
+            | AppError::NonceAccountCountMismatch { .. }
+            | AppError::InvalidNonceAccountCount
+            | AppError::NotEnoughNonceAccounts
+            | AppError::InvalidPriorityFee
+            | AppError::InvalidBundleSize
+            | AppError::UnsignedBundleTransaction
+            | AppError::InvalidTipAccount
+            | AppError::UnknownProofAccount
+            | AppError::InvalidBatchSize
+            | AppError::ConflictingAuditorUpdate => StatusCode::BAD_REQUEST,
             AppError::TransactionFetchError | AppError::TransactionDataNotFound => {
                 StatusCode::NOT_FOUND
             }
             // 422 -  Unprocessable Entity
-            AppError::AmountDecodeError | AppError::InvalidInstructionData => {
-                StatusCode::UNPROCESSABLE_ENTITY
-            }
+            AppError::AmountDecodeError
+            | AppError::InvalidInstructionData
+            | AppError::MintAuthorityNotSet
+            | AppError::PendingBalanceNotEmpty
+            | AppError::AvailableBalanceNotEmpty
+            | AppError::NonConfidentialBalanceNotEmpty
+            | AppError::ConfidentialTransferAuthorityNotSet
+            | AppError::AccountAlreadyApproved
+            | AppError::RecipientNotConfidential
+            | AppError::RecipientNotApproved
+            | AppError::RecipientConfidentialCreditsDisabled
+            | AppError::RecipientPendingBalanceCreditLimitReached
+            | AppError::RecipientMemoRequired
+            | AppError::InvalidNonceAccount
+            | AppError::NonceAuthorityMismatch
+            | AppError::InvalidAddressLookupTable
+            | AppError::TransactionTooLarge
+            | AppError::StaleProofAccounts
+            | AppError::InsufficientConfidentialBalance { .. } => StatusCode::UNPROCESSABLE_ENTITY,
             // 401 - Unauthorized
             AppError::InvalidAuditorSignature => StatusCode::UNAUTHORIZED,
             // 403 - Forbidden
-            AppError::AuditorAccessDenied => StatusCode::FORBIDDEN,
+            AppError::AuditorAccessDenied
+            | AppError::ConfidentialTransferAuthorityMismatch
+            | AppError::SponsorshipDenied => StatusCode::FORBIDDEN,
             // 404 - Not Found
             AppError::NoConfidentialTransferFound => StatusCode::NOT_FOUND,
+            // 502 - Bad Gateway
+            AppError::RpcError(_) | AppError::BlockEngineError(_) => StatusCode::BAD_GATEWAY,
+            // 503 - Service Unavailable
+            AppError::BlockEngineNotConfigured => StatusCode::SERVICE_UNAVAILABLE,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -179,6 +409,12 @@ impl From<SignerError> for AppError {
     }
 }
 
+impl From<ClientError> for AppError {
+    fn from(error: ClientError) -> Self {
+        Self::RpcError(error)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
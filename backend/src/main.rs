@@ -3,7 +3,7 @@ use axum::{
     routing::{get, post},
     Router,
 };
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc};
 use tower_http::{
     cors::{Any, CorsLayer},
     trace::TraceLayer,
@@ -11,24 +11,45 @@ use tower_http::{
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 // Import our modules
+mod block_engine;
 mod errors;
 mod models;
 mod routes;
+mod rpc;
+mod signer;
+mod state;
 
 // Use our route handlers
 #[rustfmt::skip]
 use routes::{
     apply_cb,
+    approve_account_cb,
     audit_transaction_cb,
+    batch_transfer_cb,
+    burn_cb,
+    check_recipient_cb,
+    configure_credits_cb,
     create_cb_ata,
+    create_lookup_table_cb,
     create_memo_transaction,
+    create_nonce_accounts_cb,
     create_test_token_cb,
     decrypt_cb,
     deposit_cb,
+    empty_cb,
     health_check,
+    inspect_account,
+    inspect_mint,
+    mint_cb,
+    recover_proof_accounts_cb,
     reveal_elgamal_pubkey_cb,
+    sponsor_info,
+    submit_bundle_cb,
     transfer_cb,
     transfer_cb_space,
+    transfer_with_fee_cb,
+    transfer_with_fee_cb_space,
+    update_mint_cb,
     version_check,
     withdraw_cb,
     withdraw_cb_space,
@@ -45,6 +66,23 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    // Load configuration from a .env file if there is one
+    dotenvy::dotenv().ok();
+
+    // Optional server-held fee payer key to sponsor transactions
+    let sponsor = signer::Sponsor::from_env()
+        .await
+        .expect("failed to configure the fee payer sponsor");
+    // Optional RPC node to fetch account data, rent and blockhashes that requests leave out
+    let rpc = rpc::Rpc::from_env();
+    // Optional Jito block engine to submit bundles to
+    let block_engine = block_engine::BlockEngine::from_env();
+    let state = state::AppState {
+        sponsor: sponsor.map(Arc::new),
+        rpc: rpc.map(Arc::new),
+        block_engine: block_engine.map(Arc::new),
+    };
+
     // Configure CORS for Solana Actions
     // According to the Dialect Blinks specification
     let cors = CorsLayer::new()
@@ -69,13 +107,31 @@ async fn main() {
         .route("/deposit-cb", post(deposit_cb))
         .route("/apply-cb", post(apply_cb))
         .route("/transfer-cb", post(transfer_cb))
+        .route("/batch-transfer-cb", post(batch_transfer_cb))
+        .route("/transfer-with-fee-cb", post(transfer_with_fee_cb))
         .route("/withdraw-cb", post(withdraw_cb))
+        .route("/empty-cb", post(empty_cb))
+        .route("/configure-credits-cb", post(configure_credits_cb))
+        .route("/approve-account-cb", post(approve_account_cb))
+        .route("/update-mint-cb", post(update_mint_cb))
+        .route("/mint-cb", post(mint_cb))
+        .route("/burn-cb", post(burn_cb))
         .route("/transfer-cb", get(transfer_cb_space))
+        .route("/transfer-with-fee-cb", get(transfer_with_fee_cb_space))
         .route("/withdraw-cb", get(withdraw_cb_space))
         .route("/decrypt-cb", post(decrypt_cb))
         .route("/create-test-token", post(create_test_token_cb))
         .route("/audit-transaction", post(audit_transaction_cb))
         .route("/reveal-elgamal-pubkey", post(reveal_elgamal_pubkey_cb))
+        .route("/inspect-account", post(inspect_account))
+        .route("/inspect-mint", post(inspect_mint))
+        .route("/check-recipient", post(check_recipient_cb))
+        .route("/sponsor", get(sponsor_info))
+        .route("/create-nonce-accounts", post(create_nonce_accounts_cb))
+        .route("/create-lookup-table", post(create_lookup_table_cb))
+        .route("/submit-bundle", post(submit_bundle_cb))
+        .route("/recover-proof-accounts", post(recover_proof_accounts_cb))
+        .with_state(state)
         .layer(cors)
         .layer(TraceLayer::new_for_http());
 
@@ -10,33 +10,83 @@ pub struct TransactionRequest {
 #[derive(Serialize)]
 pub struct TransactionResponse {
     pub transaction: String,
+    pub required_signers: Vec<String>, // Signers whose signatures are still missing
     pub message: String,
 }
 
+// Address lookup table sent with a transaction request
+#[derive(Deserialize)]
+pub struct AddressLookupTableRequest {
+    pub address: String,                // Lookup table address (base58)
+    pub addresses: Option<Vec<String>>, // Addresses stored in the table (fetched over RPC if omitted)
+}
+
+// Priority fee policy for the transactions of a flow, with exactly one of the fields set
+#[derive(Deserialize)]
+pub struct PriorityFeeRequest {
+    pub micro_lamports_per_compute_unit: Option<String>, // Compute unit price of every transaction, as u64
+    pub total_lamports: Option<String>, // Priority fee budget of the whole flow, as u64 (spread by compute units)
+}
+
+// Jito bundle mode of a multi-transaction request
+#[derive(Deserialize)]
+pub struct BundleRequest {
+    pub tip_lamports: String, // Tip paid by the fee payer (the token owner if sponsored) in the last transaction, as u64
+    pub tip_account: Option<String>, // One of the block engine's tip accounts (base58, picked at random if omitted)
+}
+
 // Response model for the multi-transaction endpoint
 #[derive(Serialize)]
 pub struct MultiTransactionResponse {
     pub transactions: Vec<String>,
+    pub required_signers: Vec<Vec<String>>, // Missing signers, per transaction
+    pub flow_nonce: Option<u64>, // Flow nonce of the proof accounts, needed to recover them once the available balance changes
+    pub flow_nonce_count: Option<u64>, // Number of flows using consecutive nonces from `flow_nonce`
     pub message: String,
 }
 
 // Request model for the create_cb_ata endpoint (cb = Confidential Balances)
 #[derive(Deserialize)]
 pub struct CreateCbAtaRequest {
-    pub mint: String,              // The mint address of the token
-    pub ata_authority: String,     // The authority address for the confidential balance
-    pub elgamal_signature: String, // ElGamal signature as base64 encoded bytes
-    pub aes_signature: String,     // AES signature as base64 encoded bytes
-    pub latest_blockhash: String,  // The latest blockhash
+    pub mint: String,                          // The mint address of the token
+    pub ata_authority: String,                 // The authority address for the confidential balance
+    pub elgamal_signature: String,             // ElGamal signature as base64 encoded bytes
+    pub aes_signature: String,                 // AES signature as base64 encoded bytes
+    pub multisig: Option<String>,              // SPL multisig owning the token account (base58)
+    pub multisig_signers: Option<Vec<String>>, // Multisig signers approving the transaction (base58)
+    pub fee_payer: Option<String>, // Account paying fees and the token account rent (base58, defaults to `ata_authority`, or the first multisig signer)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy (no compute budget instructions if omitted)
 }
 
 // Request model for the deposit_cb endpoint
 #[derive(Deserialize)]
 pub struct DepositCbRequest {
-    pub token_account_data: String, // BASE64 encoded account data
-    pub lamport_amount: String,     // The amount to deposit (as a string to handle large numbers)
-    pub mint_decimals: u8,          // The number of decimals for the mint
-    pub latest_blockhash: String,   // The latest blockhash
+    pub token_account_data: Option<String>, // BASE64 encoded account data (or use `token_account_address`)
+    pub token_account_address: Option<String>, // Token account address (base58), to fetch its data over RPC
+    pub lamport_amount: String, // The amount to deposit (as a string to handle large numbers)
+    pub mint_decimals: Option<u8>, // The number of decimals for the mint (fetched over RPC if omitted)
+    pub multisig: Option<String>,  // SPL multisig owning the token account (base58)
+    pub multisig_signers: Option<Vec<String>>, // Multisig signers approving the transaction (base58)
+    pub fee_payer: Option<String>, // Account paying fees (base58, defaults to the token account owner, or the first multisig signer)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy (no compute budget instructions if omitted)
+    pub memo: Option<String>, // SPL memo placed right before the token instruction (e.g. an invoice reference)
+}
+
+// Request model for the configure_credits_cb endpoint
+#[derive(Deserialize)]
+pub struct ConfigureCreditsCbRequest {
+    pub token_account_data: Option<String>, // BASE64 encoded account data (or use `token_account_address`)
+    pub token_account_address: Option<String>, // Token account address (base58), to fetch its data over RPC
+    pub confidential_credits: Option<bool>, // Enable (true) or disable (false) confidential credits
+    pub non_confidential_credits: Option<bool>, // Enable (true) or disable (false) non-confidential credits
+    pub fee_payer: Option<String>, // Account paying fees (base58, defaults to the token account owner)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy (no compute budget instructions if omitted)
 }
 
 // Request model for the apply_cb endpoint
@@ -45,8 +95,31 @@ pub struct ApplyCbRequest {
     pub ata_authority: String, // The authority address for the confidential balance
     pub elgamal_signature: String, // ElGamal signature as base64 encoded bytes
     pub aes_signature: String, // AES signature as base64 encoded bytes
-    pub token_account_data: String, // BASE64 encoded account data
-    pub latest_blockhash: String, // The latest blockhash
+    pub token_account_data: Option<String>, // BASE64 encoded account data (or use `token_account_address`)
+    pub token_account_address: Option<String>, // Token account address (base58), to fetch its data over RPC
+    pub multisig: Option<String>,              // SPL multisig owning the token account (base58)
+    pub multisig_signers: Option<Vec<String>>, // Multisig signers approving the transaction (base58)
+    pub fee_payer: Option<String>, // Account paying fees (base58, defaults to `ata_authority`, or the first multisig signer)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy (no compute budget instructions if omitted)
+    pub memo: Option<String>, // SPL memo placed right before the token instruction (e.g. an invoice reference)
+}
+
+// Request model for the empty_cb endpoint
+#[derive(Deserialize)]
+pub struct EmptyCbRequest {
+    pub elgamal_signature: String, // ElGamal signature as base64 encoded bytes
+    pub aes_signature: String,     // AES signature as base64 encoded bytes
+    pub token_account_data: Option<String>, // BASE64 encoded account data (or use `token_account_address`)
+    pub token_account_address: Option<String>, // Token account address (base58), to fetch its data over RPC
+    pub close_account: Option<bool>, // Optionally close the token account to reclaim its rent
+    pub multisig: Option<String>,    // SPL multisig owning the token account (base58)
+    pub multisig_signers: Option<Vec<String>>, // Multisig signers approving the transaction (base58)
+    pub fee_payer: Option<String>, // Account paying fees (base58, defaults to the token account owner, or the first multisig signer), the rent of a closed account goes to the owner
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy (no compute budget instructions if omitted)
 }
 
 // Request model for the transfer_cb endpoint
@@ -54,15 +127,119 @@ pub struct ApplyCbRequest {
 pub struct TransferCbRequest {
     pub elgamal_signature: String, //Sender's ElGamal signature as base64 encoded bytes
     pub aes_signature: String,     // Sender's AES signature as base64 encoded bytes
-    pub sender_token_account: String, // The sender's token account info
-    pub recipient_token_account: String, // The recipient's token account info
-    pub mint_token_account: String, // The mint token account info
-    pub amount: String,            // The transfer amount as u64
-    pub priority_fee: String,      // The priority fee as u64
-    pub latest_blockhash: String,  // The latest blockhash
-    pub equality_proof_rent: String, // Rent for equality proof context state account
-    pub ciphertext_validity_proof_rent: String, // Rent for ciphertext validity proof context state account
-    pub range_proof_rent: String,               // Rent for range proof context state account
+    pub sender_token_account: Option<String>, // The sender's token account info (or use `sender_token_account_address`)
+    pub sender_token_account_address: Option<String>, // Sender token account address (base58), to fetch its data over RPC
+    pub recipient_token_account: Option<String>, // The recipient's token account info (or use `recipient_token_account_address`)
+    pub recipient_token_account_address: Option<String>, // Recipient token account address (base58), to fetch its data over RPC
+    pub mint_token_account: Option<String>, // The mint token account info (fetched over RPC if omitted)
+    pub amount: String,                     // The transfer amount as u64
+    pub priority_fee: String, // Priority fee budget of the whole flow in lamports, as u64 (0 for none)
+    pub multisig: Option<String>, // SPL multisig owning the token account (base58)
+    pub multisig_signers: Option<Vec<String>>, // Multisig signers approving the transaction (base58)
+    pub fee_payer: Option<String>, // Account paying fees and proof account rent (base58, defaults to the authority)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub nonce_accounts: Option<Vec<String>>, // Durable nonce accounts (base58), one per transaction
+    pub nonce_account_data: Option<Vec<String>>, // BASE64 encoded nonce account data, in the same order (fetched over RPC if omitted)
+    pub nonce_authority: Option<String>, // Authority of the nonce accounts (defaults to the fee payer, must be the sponsor if sponsored)
+    pub equality_proof_rent: Option<String>, // Rent for equality proof context state account (computed by the server if omitted)
+    pub ciphertext_validity_proof_rent: Option<String>, // Rent for ciphertext validity proof context state account (computed by the server if omitted)
+    pub range_proof_rent: Option<String>, // Rent for range proof context state account (computed by the server if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee_policy: Option<PriorityFeeRequest>, // Priority fee policy, overriding `priority_fee`
+    pub bundle: Option<BundleRequest>, // Build the transactions as an atomic Jito bundle
+    pub flow_nonce: Option<u64>, // Nonce deriving the proof account addresses (defaults to one derived from the available balance, returned in the response)
+    pub resume: Option<ResumeRequest>, // Proof accounts of an interrupted attempt, to return only its missing transactions
+    pub memo: Option<String>, // SPL memo placed right before the token instruction (e.g. an invoice reference)
+}
+
+// A transfer of the batch_transfer_cb endpoint
+#[derive(Deserialize)]
+pub struct BatchTransfer {
+    pub recipient_token_account: Option<String>, // The recipient's token account info (or use `recipient_token_account_address`)
+    pub recipient_token_account_address: Option<String>, // Recipient token account address (base58), to fetch its data over RPC
+    pub amount: String,                                  // The transfer amount as u64
+    pub memo: Option<String>, // SPL memo placed right before the transfer instruction
+}
+
+// Request model for the batch_transfer_cb endpoint
+#[derive(Deserialize)]
+pub struct BatchTransferCbRequest {
+    pub elgamal_signature: String, // Sender's ElGamal signature as base64 encoded bytes
+    pub aes_signature: String,     // Sender's AES signature as base64 encoded bytes
+    pub sender_token_account: Option<String>, // The sender's token account info (or use `sender_token_account_address`)
+    pub sender_token_account_address: Option<String>, // Sender token account address (base58), to fetch its data over RPC
+    pub mint_token_account: Option<String>, // The mint token account info (fetched over RPC if omitted)
+    pub transfers: Vec<BatchTransfer>,      // Transfers, in the order they land
+    pub multisig: Option<String>,           // SPL multisig owning the token account (base58)
+    pub multisig_signers: Option<Vec<String>>, // Multisig signers approving the transaction (base58)
+    pub fee_payer: Option<String>, // Account paying fees and proof account rent (base58, defaults to the authority)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub nonce_accounts: Option<Vec<String>>, // Durable nonce accounts (base58), one per transaction
+    pub nonce_account_data: Option<Vec<String>>, // BASE64 encoded nonce account data, in the same order (fetched over RPC if omitted)
+    pub nonce_authority: Option<String>, // Authority of the nonce accounts (defaults to the fee payer, must be the sponsor if sponsored)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy of every transaction of the batch
+    pub flow_nonce: Option<u64>, // Nonce deriving the proof account addresses of the first transfer, the next ones use the following nonces
+}
+
+// Request model for the transfer_with_fee_cb endpoint
+#[derive(Deserialize)]
+pub struct TransferWithFeeCbRequest {
+    pub elgamal_signature: String, //Sender's ElGamal signature as base64 encoded bytes
+    pub aes_signature: String,     // Sender's AES signature as base64 encoded bytes
+    pub sender_token_account: Option<String>, // The sender's token account info (or use `sender_token_account_address`)
+    pub sender_token_account_address: Option<String>, // Sender token account address (base58), to fetch its data over RPC
+    pub recipient_token_account: Option<String>, // The recipient's token account info (or use `recipient_token_account_address`)
+    pub recipient_token_account_address: Option<String>, // Recipient token account address (base58), to fetch its data over RPC
+    pub mint_token_account: Option<String>, // The mint token account info (fetched over RPC if omitted)
+    pub amount: String,                     // The transfer amount as u64
+    pub epoch: Option<u64>, // The current epoch, used to select the active transfer fee (fetched over RPC if omitted)
+    pub priority_fee: String, // Priority fee budget of the whole flow in lamports, as u64 (0 for none)
+    pub fee_payer: Option<String>, // Account paying fees and proof account rent (base58, defaults to the authority)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub equality_proof_rent: Option<String>, // Rent for equality proof context state account (computed by the server if omitted)
+    pub transfer_amount_ciphertext_validity_proof_rent: Option<String>, // Rent for transfer amount ciphertext validity proof context state account (computed by the server if omitted)
+    pub fee_sigma_proof_rent: Option<String>, // Rent for fee sigma (percentage with cap) proof context state account (computed by the server if omitted)
+    pub fee_ciphertext_validity_proof_rent: Option<String>, // Rent for fee ciphertext validity proof context state account (computed by the server if omitted)
+    pub range_proof_rent: Option<String>, // Rent for range proof context state account (computed by the server if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee_policy: Option<PriorityFeeRequest>, // Priority fee policy, overriding `priority_fee`
+}
+
+// Request model for the mint_cb endpoint
+#[derive(Deserialize)]
+pub struct MintCbRequest {
+    pub elgamal_signature: String, // Mint authority's ElGamal signature (supply key) as base64 encoded bytes
+    pub aes_signature: String, // Mint authority's AES signature (supply key) as base64 encoded bytes
+    pub recipient_token_account: Option<String>, // The recipient's token account info (or use `recipient_token_account_address`)
+    pub recipient_token_account_address: Option<String>, // Recipient token account address (base58), to fetch its data over RPC
+    pub mint_token_account: Option<String>, // The mint token account info (fetched over RPC if omitted)
+    pub amount: String,                     // The mint amount as u64
+    pub fee_payer: Option<String>, // Account paying fees and proof account rent (base58, defaults to the authority)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub equality_proof_rent: Option<String>, // Rent for equality proof context state account (computed by the server if omitted)
+    pub ciphertext_validity_proof_rent: Option<String>, // Rent for ciphertext validity proof context state account (computed by the server if omitted)
+    pub range_proof_rent: Option<String>, // Rent for range proof context state account (computed by the server if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy (no compute budget instructions if omitted)
+}
+
+// Request model for the burn_cb endpoint
+#[derive(Deserialize)]
+pub struct BurnCbRequest {
+    pub elgamal_signature: String, // Token account owner's ElGamal signature as base64 encoded bytes
+    pub aes_signature: String,     // Token account owner's AES signature as base64 encoded bytes
+    pub token_account: Option<String>, // The token account info to burn from (or use `token_account_address`)
+    pub token_account_address: Option<String>, // Token account address (base58), to fetch its data over RPC
+    pub mint_token_account: Option<String>, // The mint token account info (fetched over RPC if omitted)
+    pub amount: String,                     // The burn amount as u64
+    pub fee_payer: Option<String>, // Account paying fees and proof account rent (base58, defaults to the authority)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub equality_proof_rent: Option<String>, // Rent for equality proof context state account (computed by the server if omitted)
+    pub ciphertext_validity_proof_rent: Option<String>, // Rent for ciphertext validity proof context state account (computed by the server if omitted)
+    pub range_proof_rent: Option<String>, // Rent for range proof context state account (computed by the server if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy (no compute budget instructions if omitted)
 }
 
 // Request model for the withdraw_cb endpoint
@@ -70,42 +247,82 @@ pub struct TransferCbRequest {
 pub struct WithdrawCbRequest {
     pub elgamal_signature: String, // ElGamal signature as base64 encoded bytes
     pub aes_signature: String,     // AES signature as base64 encoded bytes
-    pub recipient_token_account: String, // The recipient's token account info
-    pub mint_account_info: String, // The mint account info
-    pub withdraw_amount_lamports: String, // The amount to withdraw as u64
-    pub latest_blockhash: String,  // The latest blockhash
-    pub equality_proof_rent: String, // Rent for equality proof context state account
-    pub range_proof_rent: String,  // Rent for range proof context state account
+    pub recipient_token_account: Option<String>, // The recipient's token account info (or use `recipient_token_account_address`)
+    pub recipient_token_account_address: Option<String>, // Recipient token account address (base58), to fetch its data over RPC
+    pub mint_account_info: Option<String>, // The mint account info (fetched over RPC if omitted)
+    pub withdraw_amount_lamports: String,  // The amount to withdraw as u64
+    pub multisig: Option<String>,          // SPL multisig owning the token account (base58)
+    pub multisig_signers: Option<Vec<String>>, // Multisig signers approving the transaction (base58)
+    pub fee_payer: Option<String>, // Account paying fees and proof account rent (base58, defaults to the authority)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub nonce_accounts: Option<Vec<String>>, // Durable nonce accounts (base58), one per transaction
+    pub nonce_account_data: Option<Vec<String>>, // BASE64 encoded nonce account data, in the same order (fetched over RPC if omitted)
+    pub nonce_authority: Option<String>, // Authority of the nonce accounts (defaults to the fee payer, must be the sponsor if sponsored)
+    pub equality_proof_rent: Option<String>, // Rent for equality proof context state account (computed by the server if omitted)
+    pub range_proof_rent: Option<String>, // Rent for range proof context state account (computed by the server if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy (no compute budget instructions if omitted)
+    pub bundle: Option<BundleRequest>,            // Build the transactions as an atomic Jito bundle
+    pub flow_nonce: Option<u64>, // Nonce deriving the proof account addresses (defaults to one derived from the available balance, returned in the response)
+    pub resume: Option<ResumeRequest>, // Proof accounts of an interrupted attempt, to return only its missing transactions
+    pub memo: Option<String>, // SPL memo placed right before the token instruction (e.g. an invoice reference)
 }
 
-// Response model for the transfer-cb GET endpoint providing space requirements
+// Response model for the transfer-cb GET endpoint providing space and rent requirements
 #[derive(Serialize)]
 pub struct TransferCbSpaceResponse {
     pub equality_proof_space: usize,
     pub ciphertext_validity_proof_space: usize,
     pub range_proof_space: usize,
+    pub equality_proof_rent: u64, // Rent-exempt minimums in lamports
+    pub ciphertext_validity_proof_rent: u64,
+    pub range_proof_rent: u64,
     pub message: String,
 }
 
-// Response model for the withdraw-cb GET endpoint providing space requirements
+// Response model for the transfer-with-fee-cb GET endpoint providing space and rent requirements
+#[derive(Serialize)]
+pub struct TransferWithFeeCbSpaceResponse {
+    pub equality_proof_space: usize,
+    pub transfer_amount_ciphertext_validity_proof_space: usize,
+    pub fee_sigma_proof_space: usize,
+    pub fee_ciphertext_validity_proof_space: usize,
+    pub range_proof_space: usize,
+    pub equality_proof_rent: u64, // Rent-exempt minimums in lamports
+    pub transfer_amount_ciphertext_validity_proof_rent: u64,
+    pub fee_sigma_proof_rent: u64,
+    pub fee_ciphertext_validity_proof_rent: u64,
+    pub range_proof_rent: u64,
+    pub message: String,
+}
+
+// Response model for the withdraw-cb GET endpoint providing space and rent requirements
 #[derive(Serialize)]
 pub struct WithdrawCbSpaceResponse {
     pub equality_proof_space: usize,
     pub range_proof_space: usize,
+    pub equality_proof_rent: u64, // Rent-exempt minimums in lamports
+    pub range_proof_rent: u64,
     pub message: String,
 }
 
 // Request model for the decrypt_cb endpoint
 #[derive(Deserialize)]
 pub struct DecryptCbRequest {
-    pub aes_signature: String,      // AES signature as base64 encoded bytes
-    pub token_account_data: String, // BASE64 encoded account data
+    pub aes_signature: String, // AES signature as base64 encoded bytes
+    pub elgamal_signature: Option<String>, // ElGamal signature, needed to decrypt the pending balance
+    pub token_account_data: Option<String>, // BASE64 encoded account data (or use `token_account_address`)
+    pub token_account_address: Option<String>, // Token account address (base58), to fetch its data over RPC
 }
 
 // Response model for the decrypt_cb endpoint
 #[derive(Serialize)]
 pub struct DecryptCbResponse {
-    pub amount: String,
+    pub amount: String,           // Available balance (kept for existing clients)
+    pub available_amount: String, // Available balance
+    pub pending_amount: Option<String>, // Pending balance (only with an ElGamal signature)
+    pub total_amount: Option<String>, // Available + pending balance (only with an ElGamal signature)
+    pub pending_balance_credit_counter: String,
     pub message: String,
 }
 
@@ -114,9 +331,40 @@ pub struct DecryptCbResponse {
 pub struct CreateTestTokenTransactionRequest {
     pub account: String,
     pub mint: String,
-    pub latest_blockhash: String,               // The latest blockhash
-    pub mint_rent: Option<u64>,                 // Optional rent amount for mint account
+    pub fee_payer: Option<String>, // Account paying fees and the mint rent (base58, defaults to `account`)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub mint_rent: Option<u64>,    // Rent for the mint account (computed by the server if omitted)
     pub auditor_elgamal_pubkey: Option<String>, // Optional ElGamal key for auditor
+    pub auto_approve_new_accounts: Option<bool>, // Whether new accounts are approved automatically (defaults to true)
+    pub confidential_mint_burn: Option<bool>, // Optionally add the ConfidentialMintBurn extension
+    pub elgamal_signature: Option<String>, // Mint authority's ElGamal signature (required for ConfidentialMintBurn)
+    pub aes_signature: Option<String>, // Mint authority's AES signature (required for ConfidentialMintBurn)
+}
+
+// Request model for the approve_account_cb endpoint
+#[derive(Deserialize)]
+pub struct ApproveAccountCbRequest {
+    pub token_account_data: Option<String>, // BASE64 encoded data of the token account to approve (or use `token_account_address`)
+    pub token_account_address: Option<String>, // Token account address (base58), to fetch its data over RPC
+    pub mint_token_account: Option<String>, // BASE64 encoded mint account data (fetched over RPC if omitted)
+    pub fee_payer: Option<String>, // Account paying fees (base58, defaults to the confidential transfer authority of the mint)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+}
+
+// Request model for the update_mint_cb endpoint
+#[derive(Deserialize)]
+pub struct UpdateMintCbRequest {
+    pub mint: String,                            // The mint address (base58)
+    pub authority: String, // The confidential transfer authority address (base58)
+    pub mint_token_account: Option<String>, // BASE64 encoded mint account data (fetched over RPC if omitted)
+    pub auditor_elgamal_pubkey: Option<String>, // New auditor ElGamal key (defaults to the current one)
+    pub remove_auditor: Option<bool>,           // Removes the auditor, the only way to clear it
+    pub auto_approve_new_accounts: Option<bool>, // New auto-approve setting (defaults to the current one)
+    pub fee_payer: Option<String>, // Account paying fees (base58, defaults to `authority`)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+    pub address_lookup_tables: Option<Vec<AddressLookupTableRequest>>, // Lookup tables to compile the transactions with
+    pub priority_fee: Option<PriorityFeeRequest>, // Priority fee policy (no compute budget instructions if omitted)
 }
 
 // Request model for auditing a transaction
@@ -149,3 +397,195 @@ pub struct RevealElGamalPubkeyResponse {
     pub pubkey: String, // ElGamal public key as string
     pub message: String,
 }
+
+// Request model for the inspect_account endpoint
+#[derive(Deserialize)]
+pub struct InspectAccountRequest {
+    pub token_account_data: Option<String>, // BASE64 encoded account data (or use `token_account_address`)
+    pub token_account_address: Option<String>, // Token account address (base58), to fetch its data over RPC
+}
+
+// Confidential transfer state of a token account
+#[derive(Serialize)]
+pub struct ConfidentialTransferAccountDetails {
+    pub approved: bool,
+    pub elgamal_pubkey: String,
+    pub allow_confidential_credits: bool,
+    pub allow_non_confidential_credits: bool,
+    pub pending_balance_credit_counter: String,
+    pub maximum_pending_balance_credit_counter: String,
+    pub expected_pending_balance_credit_counter: String,
+    pub actual_pending_balance_credit_counter: String,
+}
+
+// Response model for the inspect_account endpoint
+#[derive(Serialize)]
+pub struct InspectAccountResponse {
+    pub mint: String,
+    pub owner: String,
+    pub amount: String, // Public (non-confidential) balance
+    pub state: String,
+    pub delegate: Option<String>,
+    pub delegated_amount: String,
+    pub close_authority: Option<String>,
+    pub is_native: bool,
+    pub has_confidential_transfer_account: bool,
+    pub confidential_transfer_account: Option<ConfidentialTransferAccountDetails>,
+    pub extensions: Vec<String>, // Every extension present on the account
+    pub message: String,
+}
+
+// Request model for the inspect_mint endpoint
+#[derive(Deserialize)]
+pub struct InspectMintRequest {
+    pub mint_token_account: Option<String>, // BASE64 encoded mint account data (or use `mint`)
+    pub mint: Option<String>,               // Mint address (base58), to fetch its data over RPC
+}
+
+// Confidential transfer configuration of a mint
+#[derive(Serialize)]
+pub struct ConfidentialTransferMintDetails {
+    pub authority: Option<String>,
+    pub auto_approve_new_accounts: bool,
+    pub auditor_elgamal_pubkey: Option<String>,
+}
+
+// Transfer fee configuration of a mint (newer fee, which applies from its epoch onwards)
+#[derive(Serialize)]
+pub struct TransferFeeDetails {
+    pub epoch: String,
+    pub transfer_fee_basis_points: u16,
+    pub maximum_fee: String,
+    pub confidential_transfer_fee_enabled: bool, // Whether the ConfidentialTransferFeeConfig extension is present
+    pub withdraw_withheld_authority_elgamal_pubkey: Option<String>,
+}
+
+// Response model for the inspect_mint endpoint
+#[derive(Serialize)]
+pub struct InspectMintResponse {
+    pub mint_authority: Option<String>,
+    pub freeze_authority: Option<String>,
+    pub supply: String,
+    pub decimals: u8,
+    pub confidential_transfer_mint: Option<ConfidentialTransferMintDetails>,
+    pub transfer_fee: Option<TransferFeeDetails>,
+    pub confidential_mint_burn: bool,
+    pub blocking_extensions: Vec<String>, // Extensions preventing confidential transfers
+    pub supports_confidential_transfers: bool,
+    pub supports_confidential_mint_burn: bool,
+    pub extensions: Vec<String>, // Every extension present on the mint
+    pub message: String,
+}
+
+// Request model for the check_recipient endpoint
+#[derive(Deserialize)]
+pub struct CheckRecipientRequest {
+    pub recipient_token_account: Option<String>, // BASE64 encoded recipient token account data (or use `recipient_token_account_address`)
+    pub recipient_token_account_address: Option<String>, // Recipient token account address (base58), to fetch its data over RPC
+}
+
+// Response model for the check_recipient endpoint
+#[derive(Serialize)]
+pub struct CheckRecipientResponse {
+    pub ready: bool,
+    pub message: String,
+}
+
+// Response model for the sponsor endpoint
+#[derive(Serialize)]
+pub struct SponsorInfoResponse {
+    pub fee_payer: Option<String>, // Server fee payer to set as `fee_payer`, if sponsoring is enabled
+    pub routes: Vec<String>,       // Routes the server sponsors
+    pub max_amount: Option<String>, // Maximum sponsored token amount
+    pub max_lamports: Option<String>, // Maximum lamports the sponsor spends per request
+    pub message: String,
+}
+
+// Request model for the create_nonce_accounts endpoint
+#[derive(Deserialize)]
+pub struct CreateNonceAccountsRequest {
+    pub fee_payer: String,                // Pays for the nonce accounts (base58)
+    pub nonce_authority: Option<String>, // Authority of the nonce accounts (defaults to the fee payer, must be the sponsor if sponsored)
+    pub count: Option<usize>, // Number of nonce accounts to create, 1 to 600, 5 per transaction (defaults to 1)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+}
+
+// Response model for the create_nonce_accounts endpoint
+#[derive(Serialize)]
+pub struct CreateNonceAccountsResponse {
+    pub transactions: Vec<String>,
+    pub required_signers: Vec<Vec<String>>, // Missing signers, per transaction
+    pub nonce_accounts: Vec<String>,        // Addresses of the new nonce accounts
+    pub message: String,
+}
+
+// Request model for the create_lookup_table endpoint
+#[derive(Deserialize)]
+pub struct CreateLookupTableRequest {
+    pub owner: String, // Token account owner and lookup table authority, unless sponsored (base58)
+    pub mint: String,  // Mint of the owner's confidential token account (base58)
+    pub fee_payer: Option<String>, // Account paying fees and table rent, the authority if it is the sponsor (base58, defaults to the owner)
+    pub lookup_table_address: Option<String>, // Existing lookup table to extend instead of creating one
+    pub additional_addresses: Option<Vec<String>>, // Other recurring accounts, e.g. recipients or nonce accounts (base58)
+    pub recent_slot: Option<u64>, // Recent slot deriving the new table address (fetched over RPC if omitted)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+}
+
+// Response model for the create_lookup_table endpoint
+#[derive(Serialize)]
+pub struct CreateLookupTableResponse {
+    pub transactions: Vec<String>,
+    pub required_signers: Vec<Vec<String>>, // Missing signers, per transaction
+    pub lookup_table_address: String,
+    pub addresses: Vec<String>, // Addresses added to the table
+    pub message: String,
+}
+
+// Request model for the submit_bundle endpoint
+#[derive(Deserialize)]
+pub struct SubmitBundleRequest {
+    pub transactions: Vec<String>, // Signed transactions as base64, in bundle order
+}
+
+// Response model for the submit_bundle endpoint
+#[derive(Serialize)]
+pub struct SubmitBundleResponse {
+    pub bundle_id: String,
+    pub message: String,
+}
+
+// Proof accounts left behind by an interrupted transfer or withdraw, to resume it from
+#[derive(Clone, Deserialize)]
+pub struct ResumeRequest {
+    pub proof_accounts: Vec<ProofAccountRequest>,
+}
+
+// A proof context state account and its on-chain state
+#[derive(Clone, Deserialize)]
+pub struct ProofAccountRequest {
+    pub address: String,      // Proof account address (base58)
+    pub data: Option<String>, // BASE64 encoded account data (fetched over RPC if omitted)
+}
+
+// Request model for the recover_proof_accounts endpoint
+#[derive(Deserialize)]
+pub struct RecoverProofAccountsRequest {
+    pub elgamal_signature: String, // Token account owner's ElGamal signature as base64 encoded bytes
+    pub token_account_data: Option<String>, // BASE64 encoded account data (or use `token_account_address`)
+    pub token_account_address: Option<String>, // Token account address (base58), to fetch its data over RPC
+    pub flow_nonces: Option<Vec<u64>>, // Flow nonces of the abandoned flows, as returned with their transactions (defaults to the one derived from the available balance)
+    pub proof_accounts: Option<Vec<ProofAccountRequest>>, // Leftover proof accounts among the derived ones and their state (looked up over RPC if omitted)
+    pub multisig: Option<String>, // SPL multisig owning the token account (base58)
+    pub multisig_signers: Option<Vec<String>>, // Multisig signers of the abandoned flows (base58)
+    pub fee_payer: Option<String>, // Fee payer of the abandoned flows, which is the proof account authority (base58, defaults to the owner, or the first multisig signer)
+    pub latest_blockhash: Option<String>, // The latest blockhash (fetched over RPC if omitted)
+}
+
+// Response model for the recover_proof_accounts endpoint
+#[derive(Serialize)]
+pub struct RecoverProofAccountsResponse {
+    pub transactions: Vec<String>,
+    pub required_signers: Vec<Vec<String>>, // Missing signers, per transaction
+    pub proof_accounts: Vec<String>,        // Leftover proof accounts closed by the transactions
+    pub message: String,
+}
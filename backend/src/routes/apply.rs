@@ -2,9 +2,16 @@ use {
     crate::{
         errors::AppError,
         models::{ApplyCbRequest, TransactionResponse},
-        routes::util::{parse_base64_base58_pubkey, parse_latest_blockhash},
+        routes::{
+            priority_fee::{with_compute_budget, PriorityFee},
+            util::{
+                missing_signers, parse_base64_base58_pubkey, parse_fee_payer,
+                parse_token_account_authority, with_memo,
+            },
+        },
+        state::AppState,
     },
-    axum::extract::Json,
+    axum::extract::{Json, State},
     base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
     bincode,
     solana_sdk::{
@@ -28,6 +35,7 @@ use {
 
 /// Handler to apply pending amount of token to confidential balance
 pub async fn apply_cb(
+    State(state): State<AppState>,
     Json(request): Json<ApplyCbRequest>,
 ) -> Result<Json<TransactionResponse>, AppError> {
     println!("🔄 Processing apply_cb request");
@@ -39,8 +47,14 @@ pub async fn apply_cb(
     // Deserialize the account data
     println!("📦 Decoding token account data from request");
     let token_account_info = {
-        // Decode token account data from request instead of fetching it
-        let token_account_data = BASE64_STANDARD.decode(&request.token_account_data)?;
+        // Use the account data from the request, or fetch it over RPC
+        let token_account_data = state
+            .account_data(
+                request.token_account_data.as_ref(),
+                request.token_account_address.as_ref(),
+                "token_account_data",
+            )
+            .await?;
         StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(token_account_data)?
     };
     println!(
@@ -48,6 +62,14 @@ pub async fn apply_cb(
         token_account_info.base.owner.to_string()
     );
 
+    // With a multisig owner, `ata_authority` is the multisig and its first signer pays the fees
+    let authority = parse_token_account_authority(
+        &ata_authority,
+        request.multisig.as_ref(),
+        request.multisig_signers.as_ref(),
+    )?;
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), authority.fee_payer())?;
+
     // Parse the mint address
     let mint_pubkey = token_account_info.base.mint;
 
@@ -121,7 +143,7 @@ pub async fn apply_cb(
         expected_pending_balance_credit_counter, // Expected number of times the pending balance has been credited
         &new_decryptable_available_balance.into(), // Cipher text of the new decryptable available balance
         &ata_authority,                            // Token account owner
-        &[&ata_authority],                         // Additional signers
+        &authority.signers(),                      // Multisig signers
     )
     .map_err(|e| {
         println!(
@@ -132,15 +154,27 @@ pub async fn apply_cb(
     })?;
     println!("✅ Successfully created apply_pending_balance instruction");
 
-    // Parse the provided blockhash from the request
-    let client_blockhash = parse_latest_blockhash(&request.latest_blockhash)?;
+    // Use the blockhash from the request, or fetch the latest one over RPC
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+    let priority_fee = PriorityFee::from_request(request.priority_fee.as_ref())?;
 
     // Create a V0 message with the dummy blockhash
     println!("📝 Creating V0 message");
     let v0_message = v0::Message::try_compile(
-        &ata_authority,
-        &[apply_pending_balance_instruction],
-        &[],
+        &fee_payer,
+        &with_compute_budget(
+            priority_fee.as_ref(),
+            with_memo(
+                request.memo.as_ref(),
+                vec![apply_pending_balance_instruction],
+            ),
+        ),
+        &lookup_tables,
         client_blockhash,
     )
     .map_err(|_| AppError::SerializationError)?;
@@ -166,11 +200,20 @@ pub async fn apply_cb(
         signatures.push(solana_sdk::signature::Signature::default());
     }
 
-    let versioned_transaction = VersionedTransaction {
+    let mut versioned_transaction = VersionedTransaction {
         signatures,
         message: versioned_message,
     };
 
+    // Sign as fee payer if the transaction is sponsored by the server
+    state
+        .co_sign(
+            "apply-cb",
+            0,
+            std::slice::from_mut(&mut versioned_transaction),
+        )
+        .await?;
+
     // Serialize the transaction to base64
     println!("🔄 Serializing transaction");
     let serialized_transaction = match bincode::serialize(&versioned_transaction) {
@@ -182,6 +225,7 @@ pub async fn apply_cb(
     // Return the transaction
     Ok(Json(TransactionResponse {
         transaction: serialized_transaction,
+        required_signers: missing_signers(&versioned_transaction),
         message: format!(
             "Created apply_cb transaction for mint: {} using client-provided account data",
             mint_pubkey
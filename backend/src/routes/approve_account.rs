@@ -0,0 +1,274 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{ApproveAccountCbRequest, TransactionResponse},
+        routes::util::{missing_signers, parse_fee_payer},
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    bincode,
+    solana_sdk::{
+        message::{v0, VersionedMessage},
+        pubkey::Pubkey,
+        signature::Signature,
+        transaction::VersionedTransaction,
+    },
+    spl_associated_token_account::get_associated_token_address_with_program_id,
+    spl_token_2022::extension::{
+        confidential_transfer::{
+            instruction::approve_account, ConfidentialTransferAccount, ConfidentialTransferMint,
+        },
+        BaseStateWithExtensions, StateWithExtensionsOwned,
+    },
+};
+
+/// Handler for approving a pending Confidential Balances account
+///
+/// Only needed for mints created with `auto_approve_new_accounts = false`. The transaction must be
+/// signed by the confidential transfer authority of the mint.
+pub async fn approve_account_cb(
+    State(state): State<AppState>,
+    Json(request): Json<ApproveAccountCbRequest>,
+) -> Result<Json<TransactionResponse>, AppError> {
+    println!("🚀 Starting approve_account_cb handler");
+
+    // Deserialize the token account data
+    println!("📦 Decoding token account data from request");
+    let token_account_info = {
+        let token_account_data = state
+            .account_data(
+                request.token_account_data.as_ref(),
+                request.token_account_address.as_ref(),
+                "token_account_data",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(token_account_data)?
+    };
+
+    let mint = token_account_info.base.mint;
+
+    // Deserialize the mint data
+    println!("📦 Decoding mint account data from request");
+    let mint_account_info = {
+        let mint_account_data = state
+            .mint_data(
+                request.mint_token_account.as_ref(),
+                &mint,
+                "mint_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(mint_account_data)?
+    };
+
+    // Only the confidential transfer authority of the mint can approve accounts
+    let confidential_transfer_authority = Option::<Pubkey>::from(
+        mint_account_info
+            .get_extension::<ConfidentialTransferMint>()?
+            .authority,
+    )
+    .ok_or_else(|| {
+        println!("⛔️ Mint {} has no confidential transfer authority", mint);
+        AppError::ConfidentialTransferAuthorityNotSet
+    })?;
+    println!(
+        "✅ Confidential transfer authority: {}",
+        confidential_transfer_authority
+    );
+
+    let confidential_transfer_account =
+        token_account_info.get_extension::<ConfidentialTransferAccount>()?;
+    if bool::from(confidential_transfer_account.approved) {
+        println!("⛔️ Token account is already approved");
+        return Err(AppError::AccountAlreadyApproved);
+    }
+
+    let token_account = get_associated_token_address_with_program_id(
+        &token_account_info.base.owner, // Token account owner
+        &mint,                          // Mint
+        &spl_token_2022::id(),
+    );
+    println!("✅ Calculated token account address: {}", token_account);
+
+    let approve_account_instruction = approve_account(
+        &spl_token_2022::id(),
+        &token_account,                   // Token account to approve
+        &mint,                            // Mint
+        &confidential_transfer_authority, // Confidential transfer authority
+        &[],                              // Additional signers
+    )?;
+    println!("✅ Approve account instruction created successfully");
+
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), confidential_transfer_authority)?;
+
+    // Use the blockhash from the request, or fetch the latest one over RPC
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+
+    // Create a V0 message with the provided blockhash
+    let v0_message = v0::Message::try_compile(
+        &fee_payer,
+        &[approve_account_instruction],
+        &lookup_tables,
+        client_blockhash,
+    )
+    .map_err(|_| AppError::SerializationError)?;
+
+    // Get the number of required signatures before moving v0_message
+    let num_required_signatures = v0_message.header.num_required_signatures as usize;
+
+    // Create a versioned transaction with placeholder signatures for required signers
+    // (will be replaced by the wallet)
+    let versioned_transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); num_required_signatures],
+        message: VersionedMessage::V0(v0_message),
+    };
+
+    // Serialize the transaction to base64
+    let serialized_transaction = match bincode::serialize(&versioned_transaction) {
+        Ok(bytes) => BASE64_STANDARD.encode(bytes),
+        Err(_) => return Err(AppError::SerializationError),
+    };
+
+    println!("✅ Transaction created successfully");
+
+    Ok(Json(TransactionResponse {
+        transaction: serialized_transaction,
+        required_signers: missing_signers(&versioned_transaction),
+        message: format!(
+            "Approve account transaction created for token account: {}",
+            token_account
+        ),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::routes::test_util,
+        spl_token_2022::{
+            extension::{
+                confidential_transfer::instruction::ConfidentialTransferInstruction,
+                BaseStateWithExtensionsMut, StateWithExtensionsMut,
+            },
+            instruction::decode_instruction_type,
+            state::{Account, Mint},
+        },
+    };
+
+    // Builds data of a token account the confidential transfer authority has yet to approve
+    fn pending_token_account_data(owner: &Pubkey, mint: &Pubkey) -> String {
+        let data = test_util::confidential_token_account_data(owner, mint, 0, 0, 0);
+        let mut data = BASE64_STANDARD.decode(data).unwrap();
+        let mut state = StateWithExtensionsMut::<Account>::unpack(&mut data).unwrap();
+        state
+            .get_extension_mut::<ConfidentialTransferAccount>()
+            .unwrap()
+            .approved = false.into();
+        BASE64_STANDARD.encode(data)
+    }
+
+    fn approve_account_request(
+        token_account_data: String,
+        mint_token_account: String,
+    ) -> ApproveAccountCbRequest {
+        ApproveAccountCbRequest {
+            token_account_data: Some(token_account_data),
+            token_account_address: None,
+            mint_token_account: Some(mint_token_account),
+            fee_payer: None,
+            latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+            address_lookup_tables: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_approve_account_is_signed_by_the_mint_authority() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let Json(response) = approve_account_cb(
+            State(AppState::default()),
+            Json(approve_account_request(
+                pending_token_account_data(&owner, &mint),
+                test_util::confidential_mint_data(&authority, false),
+            )),
+        )
+        .await
+        .unwrap();
+
+        let instructions = test_util::decode_transaction(&response.transaction);
+        assert_eq!(instructions.len(), 1);
+        let (program_id, data) = &instructions[0];
+        assert_eq!(*program_id, spl_token_2022::id());
+        assert!(matches!(
+            decode_instruction_type::<ConfidentialTransferInstruction>(&data[1..]),
+            Ok(ConfidentialTransferInstruction::ApproveAccount)
+        ));
+
+        // The owner's account of the mint is approved, and only the authority signs
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&BASE64_STANDARD.decode(&response.transaction).unwrap()).unwrap();
+        let account_keys = transaction.message.static_account_keys();
+        let accounts = transaction.message.instructions()[0]
+            .accounts
+            .iter()
+            .map(|index| account_keys[*index as usize])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            accounts,
+            vec![
+                get_associated_token_address_with_program_id(&owner, &mint, &spl_token_2022::id()),
+                mint,
+                authority,
+            ]
+        );
+        assert_eq!(response.required_signers, vec![authority.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_approve_account_rejects_approved_accounts_and_mints_without_authority() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let result = approve_account_cb(
+            State(AppState::default()),
+            Json(approve_account_request(
+                test_util::confidential_token_account_data(&owner, &mint, 0, 0, 0),
+                test_util::confidential_mint_data(&authority, false),
+            )),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::AccountAlreadyApproved)));
+
+        let mint_without_authority = {
+            let data = test_util::confidential_mint_data(&authority, false);
+            let mut data = BASE64_STANDARD.decode(data).unwrap();
+            let mut state = StateWithExtensionsMut::<Mint>::unpack(&mut data).unwrap();
+            state
+                .get_extension_mut::<ConfidentialTransferMint>()
+                .unwrap()
+                .authority = None.try_into().unwrap();
+            BASE64_STANDARD.encode(data)
+        };
+        let result = approve_account_cb(
+            State(AppState::default()),
+            Json(approve_account_request(
+                pending_token_account_data(&owner, &mint),
+                mint_without_authority,
+            )),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(AppError::ConfidentialTransferAuthorityNotSet)
+        ));
+    }
+}
@@ -0,0 +1,712 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{BatchTransferCbRequest, MultiTransactionResponse},
+        routes::{
+            check_recipient::check_recipient_readiness,
+            nonce::TransactionLifetime,
+            packer::{fits_in_transaction, pack_transactions},
+            priority_fee::PriorityFee,
+            proof_accounts::{flow_nonce, ProofAccount},
+            util::{
+                check_sender_balance, get_zk_proof_context_state_account_creation_instructions,
+                get_zk_proof_record_verification_instructions, missing_signers, parse_fee_payer,
+                parse_token_account_authority, with_memo,
+            },
+        },
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+        signer::Signer,
+    },
+    solana_zk_sdk::{
+        encryption::{elgamal::ElGamalPubkey, pod::elgamal::PodElGamalPubkey},
+        zk_elgamal_proof_program::{
+            instruction::{close_context_state, ContextStateInfo},
+            proof_data::{
+                BatchedGroupedCiphertext3HandlesValidityProofContext, BatchedRangeProofContext,
+                BatchedRangeProofU128Data, CiphertextCommitmentEqualityProofContext, ZkProofData,
+            },
+            state::ProofContextState,
+        },
+    },
+    spl_associated_token_account::get_associated_token_address_with_program_id,
+    spl_record::state::RecordData,
+    spl_token_2022::{
+        error::TokenError,
+        extension::{
+            confidential_transfer::{
+                account_info::TransferAccountInfo, instruction::transfer,
+                ConfidentialTransferAccount, ConfidentialTransferMint,
+            },
+            memo_transfer::memo_required,
+            BaseStateWithExtensions, StateWithExtensionsOwned,
+        },
+        solana_zk_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
+        state::{Account, Mint},
+    },
+    spl_token_confidential_transfer_proof_extraction::instruction::{ProofData, ProofLocation},
+    spl_token_confidential_transfer_proof_generation::transfer::TransferProofData,
+    std::{collections::HashMap, mem::size_of, num::NonZeroI8},
+};
+
+/// Maximum number of transfers in a batch
+pub const MAX_BATCH_TRANSFERS: usize = 100;
+
+/// Handler for the batch-transfer-cb endpoint
+///
+/// Transfers from one sender to many recipients in a single ordered flow. The proofs of each
+/// transfer are generated against the balance the previous transfers leave, so every transfer
+/// stays valid once the earlier ones land, and all proof accounts are closed at the end.
+pub async fn batch_transfer_cb(
+    State(state): State<AppState>,
+    Json(request): Json<BatchTransferCbRequest>,
+) -> Result<Json<MultiTransactionResponse>, AppError> {
+    println!("🚀 Starting batch_transfer_cb handler");
+
+    if request.transfers.is_empty() || request.transfers.len() > MAX_BATCH_TRANSFERS {
+        println!("⛔️ Batch of {} transfers", request.transfers.len());
+        return Err(AppError::InvalidBatchSize);
+    }
+    let amounts = request
+        .transfers
+        .iter()
+        .map(|transfer| {
+            transfer
+                .amount
+                .parse::<u64>()
+                .map_err(|_| AppError::InvalidAmount)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let total_amount = amounts
+        .iter()
+        .try_fold(0u64, |total, amount| total.checked_add(*amount))
+        .ok_or(AppError::InvalidAmount)?;
+    println!(
+        "✅ Batch of {} transfers, {} in total",
+        amounts.len(),
+        total_amount
+    );
+
+    let sender_token_account_info = {
+        let sender_token_account_data = state
+            .account_data(
+                request.sender_token_account.as_ref(),
+                request.sender_token_account_address.as_ref(),
+                "sender_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<Account>::unpack(sender_token_account_data)?
+    };
+    let sender_account =
+        sender_token_account_info.get_extension::<ConfidentialTransferAccount>()?;
+    let mint = sender_token_account_info.base.mint;
+
+    // The sender, or the first signer of a multisig sender, pays for every transaction
+    let sender_ata_authority = sender_token_account_info.base.owner;
+    let sender_authority = parse_token_account_authority(
+        &sender_ata_authority,
+        request.multisig.as_ref(),
+        request.multisig_signers.as_ref(),
+    )?;
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), sender_authority.fee_payer())?;
+    let context_state_authority = &fee_payer;
+    let sender_token_account = get_associated_token_address_with_program_id(
+        &sender_ata_authority,
+        &mint,
+        &spl_token_2022::id(),
+    );
+
+    let elgamal_signature = Signature::try_from(
+        BASE64_STANDARD
+            .decode(&request.elgamal_signature)?
+            .as_slice(),
+    )
+    .map_err(|_| AppError::SerializationError)?;
+    let sender_elgamal_keypair = ElGamalKeypair::new_from_signature(&elgamal_signature)
+        .map_err(|_| AppError::SerializationError)?;
+    let sender_aes_key = {
+        let aes_signature =
+            Signature::try_from(BASE64_STANDARD.decode(&request.aes_signature)?.as_slice())
+                .map_err(|_| AppError::SerializationError)?;
+        AeKey::new_from_signature(&aes_signature).map_err(|_| AppError::SerializationError)?
+    };
+    println!("✅ ElGamal keypair and AES key created successfully");
+
+    // The available balance must cover the whole batch
+    check_sender_balance(
+        sender_account,
+        &sender_elgamal_keypair,
+        &sender_aes_key,
+        total_amount,
+    )?;
+
+    let auditor_elgamal_pubkey = {
+        let mint_account_data = state
+            .mint_data(
+                request.mint_token_account.as_ref(),
+                &mint,
+                "mint_token_account",
+            )
+            .await?;
+        Option::<PodElGamalPubkey>::from(
+            StateWithExtensionsOwned::<Mint>::unpack(mint_account_data)?
+                .get_extension::<ConfidentialTransferMint>()?
+                .auditor_elgamal_pubkey,
+        )
+        .map(ElGamalPubkey::try_from)
+        .transpose()?
+    };
+
+    let mut lifetime = TransactionLifetime::from_request(
+        &state,
+        request.latest_blockhash.as_ref(),
+        request.nonce_accounts.as_ref(),
+        request.nonce_account_data.as_ref(),
+        request.nonce_authority.as_ref(),
+        fee_payer,
+    )
+    .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+    let priority_fee = PriorityFee::from_request(request.priority_fee.as_ref())?;
+
+    // The proof accounts of every transfer have the same sizes, so their rents are looked up once
+    let range_proof_rent = state
+        .rent(
+            None,
+            size_of::<ProofContextState<BatchedRangeProofContext>>(),
+        )
+        .await?;
+    let equality_proof_rent = state
+        .rent(
+            None,
+            size_of::<ProofContextState<CiphertextCommitmentEqualityProofContext>>(),
+        )
+        .await?;
+    let validity_proof_rent = state
+        .rent(
+            None,
+            size_of::<ProofContextState<BatchedGroupedCiphertext3HandlesValidityProofContext>>(),
+        )
+        .await?;
+    let range_proof_record_rent = if lifetime.is_durable() {
+        Some(
+            state
+                .rent(
+                    None,
+                    RecordData::WRITABLE_START_INDEX + size_of::<BatchedRangeProofU128Data>(),
+                )
+                .await?,
+        )
+    } else {
+        None
+    };
+
+    // Every transfer starts from the balance the previous one leaves, and derives its proof
+    // accounts from the next flow nonce
+    let first_flow_nonce = flow_nonce(request.flow_nonce, sender_account);
+    let mut sender_transfer_account_info = TransferAccountInfo::new(sender_account);
+    let mut proof_account_keypairs = Vec::<Keypair>::new();
+    let mut record_keypairs = Vec::<Keypair>::new();
+    let mut groups = Vec::new();
+    let mut recipient_token_account_infos = Vec::with_capacity(request.transfers.len());
+    for batch_transfer in &request.transfers {
+        let recipient_token_account_data = state
+            .account_data(
+                batch_transfer.recipient_token_account.as_ref(),
+                batch_transfer.recipient_token_account_address.as_ref(),
+                "transfers.recipient_token_account",
+            )
+            .await?;
+        recipient_token_account_infos.push(StateWithExtensionsOwned::<Account>::unpack(
+            recipient_token_account_data,
+        )?);
+    }
+    // Each transfer to a recipient is one more pending balance credit on its account
+    let mut recipient_credits = HashMap::<Pubkey, u64>::new();
+    for recipient_token_account_info in &recipient_token_account_infos {
+        *recipient_credits
+            .entry(recipient_token_account_info.base.owner)
+            .or_default() += 1;
+    }
+    for (index, ((batch_transfer, amount), recipient_token_account_info)) in request
+        .transfers
+        .iter()
+        .zip(amounts)
+        .zip(recipient_token_account_infos)
+        .enumerate()
+    {
+        let recipient_owner = recipient_token_account_info.base.owner;
+        if recipient_token_account_info.base.mint != mint {
+            println!(
+                "⛔️ Recipient account of {} has another mint",
+                recipient_owner
+            );
+            return Err(AppError::MintMismatch);
+        }
+        check_recipient_readiness(
+            &recipient_token_account_info,
+            recipient_credits[&recipient_owner],
+        )?;
+        if memo_required(&recipient_token_account_info) && batch_transfer.memo.is_none() {
+            println!(
+                "⛔️ Recipient account of {} requires a memo",
+                recipient_owner
+            );
+            return Err(AppError::RecipientMemoRequired);
+        }
+        let recipient_token_account = get_associated_token_address_with_program_id(
+            &recipient_owner,
+            &mint,
+            &spl_token_2022::id(),
+        );
+        let recipient_elgamal_pubkey: ElGamalPubkey = recipient_token_account_info
+            .get_extension::<ConfidentialTransferAccount>()?
+            .elgamal_pubkey
+            .try_into()?;
+
+        let TransferProofData {
+            equality_proof_data,
+            ciphertext_validity_proof_data_with_ciphertext,
+            range_proof_data,
+        } = sender_transfer_account_info.generate_split_transfer_proof_data(
+            amount,
+            &sender_elgamal_keypair,
+            &sender_aes_key,
+            &recipient_elgamal_pubkey,
+            auditor_elgamal_pubkey.as_ref(),
+        )?;
+        let new_decryptable_available_balance = sender_transfer_account_info
+            .new_decryptable_available_balance(amount, &sender_aes_key)
+            .map_err(|_| TokenError::AccountDecryption)?
+            .into();
+        println!(
+            "✅ Generated proofs of transfer {} of {}",
+            index + 1,
+            amount
+        );
+
+        let flow_nonce = first_flow_nonce.wrapping_add(index as u64);
+        let proof_account_keypair = |proof_account: ProofAccount| {
+            proof_account.keypair(&elgamal_signature, &sender_token_account, flow_nonce)
+        };
+        let equality_proof_account = proof_account_keypair(ProofAccount::TransferEquality);
+        let ciphertext_validity_proof_account =
+            proof_account_keypair(ProofAccount::TransferCiphertextValidity);
+        let range_proof_account = proof_account_keypair(ProofAccount::TransferRange);
+        let equality_proof_address = equality_proof_account.pubkey();
+        let validity_proof_address = ciphertext_validity_proof_account.pubkey();
+        let range_proof_address = range_proof_account.pubkey();
+
+        // As in a single transfer, the equality and ciphertext validity proofs are verified in the
+        // transfer transaction if they fit there
+        let transfer_instructions = |inline_equality_proof: bool, inline_validity_proof: bool| {
+            let mut next_offset = 1;
+            let mut next_instruction_offset = || {
+                let offset = NonZeroI8::new(next_offset).unwrap();
+                next_offset += 1;
+                offset
+            };
+            let equality_proof_location = if inline_equality_proof {
+                ProofLocation::InstructionOffset(
+                    next_instruction_offset(),
+                    ProofData::InstructionData(&equality_proof_data),
+                )
+            } else {
+                ProofLocation::ContextStateAccount(&equality_proof_address)
+            };
+            let validity_proof_location = if inline_validity_proof {
+                ProofLocation::InstructionOffset(
+                    next_instruction_offset(),
+                    ProofData::InstructionData(
+                        &ciphertext_validity_proof_data_with_ciphertext.proof_data,
+                    ),
+                )
+            } else {
+                ProofLocation::ContextStateAccount(&validity_proof_address)
+            };
+
+            transfer(
+                &spl_token_2022::id(),
+                &sender_token_account,
+                &mint,
+                &recipient_token_account,
+                &new_decryptable_available_balance,
+                &ciphertext_validity_proof_data_with_ciphertext.ciphertext_lo,
+                &ciphertext_validity_proof_data_with_ciphertext.ciphertext_hi,
+                &sender_ata_authority,
+                &sender_authority.signers(),
+                equality_proof_location,
+                validity_proof_location,
+                ProofLocation::ContextStateAccount(&range_proof_address),
+            )
+            .map(|instructions| with_memo(batch_transfer.memo.as_ref(), instructions))
+        };
+        let inline_equality_proof = fits_in_transaction(
+            &fee_payer,
+            &transfer_instructions(true, false)?,
+            &lifetime,
+            &lookup_tables,
+            priority_fee.as_ref(),
+        )?;
+        let inline_validity_proof = fits_in_transaction(
+            &fee_payer,
+            &transfer_instructions(inline_equality_proof, true)?,
+            &lifetime,
+            &lookup_tables,
+            priority_fee.as_ref(),
+        )?;
+
+        // Verify the other proofs into context state accounts, then transfer
+        let mut context_state_proofs =
+            vec![get_zk_proof_context_state_account_creation_instructions(
+                &fee_payer,
+                &range_proof_account.pubkey(),
+                context_state_authority,
+                &range_proof_data,
+                range_proof_rent,
+            )?];
+        let mut keypairs = vec![range_proof_account];
+        if !inline_equality_proof {
+            context_state_proofs.push(get_zk_proof_context_state_account_creation_instructions(
+                &fee_payer,
+                &equality_proof_address,
+                context_state_authority,
+                &equality_proof_data,
+                equality_proof_rent,
+            )?);
+            keypairs.push(equality_proof_account);
+        }
+        if !inline_validity_proof {
+            context_state_proofs.push(get_zk_proof_context_state_account_creation_instructions(
+                &fee_payer,
+                &validity_proof_address,
+                context_state_authority,
+                &ciphertext_validity_proof_data_with_ciphertext.proof_data,
+                validity_proof_rent,
+            )?);
+            keypairs.push(ciphertext_validity_proof_account);
+        }
+        groups.extend(
+            context_state_proofs
+                .iter()
+                .map(|(create_ix, _)| vec![create_ix.clone()]),
+        );
+        // With durable nonces, the range proof is verified from a record account instead
+        if lifetime.is_durable() {
+            let range_proof_record = proof_account_keypair(ProofAccount::TransferRangeRecord);
+            groups.extend(get_zk_proof_record_verification_instructions(
+                &fee_payer,
+                &range_proof_record.pubkey(),
+                &range_proof_address,
+                context_state_authority,
+                &range_proof_data,
+                range_proof_record_rent,
+            )?);
+            record_keypairs.push(range_proof_record);
+        } else {
+            groups.push(vec![context_state_proofs[0].1.clone()]);
+        }
+        groups.extend(
+            context_state_proofs
+                .iter()
+                .skip(1)
+                .map(|(_, verify_ix)| vec![verify_ix.clone()]),
+        );
+        groups.push(transfer_instructions(
+            inline_equality_proof,
+            inline_validity_proof,
+        )?);
+        proof_account_keypairs.extend(keypairs);
+
+        // The next transfer spends from the balance this one leaves
+        sender_transfer_account_info = TransferAccountInfo {
+            available_balance: equality_proof_data.context_data().ciphertext,
+            decryptable_available_balance: new_decryptable_available_balance,
+        };
+    }
+
+    // Close every proof account once all transfers are done
+    groups.extend(proof_account_keypairs.iter().map(|keypair| {
+        vec![close_context_state(
+            ContextStateInfo {
+                context_state_account: &keypair.pubkey(),
+                context_state_authority,
+            },
+            &fee_payer,
+        )]
+    }));
+
+    let mut transactions = pack_transactions(
+        &fee_payer,
+        groups,
+        &proof_account_keypairs
+            .iter()
+            .chain(&record_keypairs)
+            .collect::<Vec<_>>(),
+        &mut lifetime,
+        &lookup_tables,
+        priority_fee.as_ref(),
+    )?;
+
+    // Sign as fee payer if the transactions are sponsored by the server
+    state
+        .co_sign("batch-transfer-cb", total_amount, &mut transactions)
+        .await?;
+
+    let required_signers = transactions.iter().map(missing_signers).collect();
+    let transactions = transactions
+        .iter()
+        .map(|transaction| Ok(BASE64_STANDARD.encode(bincode::serialize(transaction)?)))
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    Ok(Json(MultiTransactionResponse {
+        message: format!(
+            "MultiTransaction for {} confidential transfers created successfully",
+            request.transfers.len()
+        ),
+        transactions,
+        required_signers,
+        flow_nonce: Some(first_flow_nonce),
+        flow_nonce_count: Some(request.transfers.len() as u64),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{models::BatchTransfer, routes::test_util},
+        solana_sdk::transaction::VersionedTransaction,
+        solana_zk_sdk::{
+            encryption::auth_encryption::AeCiphertext,
+            zk_elgamal_proof_program::instruction::ProofInstruction,
+        },
+        spl_token_2022::{
+            extension::confidential_transfer::instruction::TransferInstructionData,
+            instruction::decode_instruction_data,
+        },
+    };
+
+    fn batch_transfer_request(
+        sender: &Pubkey,
+        mint: &Pubkey,
+        recipients: &[Pubkey],
+        amounts: &[&str],
+    ) -> BatchTransferCbRequest {
+        BatchTransferCbRequest {
+            elgamal_signature: test_util::elgamal_signature(),
+            aes_signature: test_util::aes_signature(),
+            sender_token_account: Some(test_util::confidential_token_account_data(
+                sender, mint, 1_000, 0, 0,
+            )),
+            sender_token_account_address: None,
+            mint_token_account: Some(test_util::confidential_mint_data(sender, false)),
+            transfers: recipients
+                .iter()
+                .zip(amounts)
+                .map(|(recipient, amount)| BatchTransfer {
+                    recipient_token_account: Some(test_util::confidential_token_account_data(
+                        recipient, mint, 0, 0, 0,
+                    )),
+                    recipient_token_account_address: None,
+                    amount: amount.to_string(),
+                    memo: None,
+                })
+                .collect(),
+            multisig: None,
+            multisig_signers: None,
+            fee_payer: None,
+            latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+            nonce_accounts: None,
+            nonce_account_data: None,
+            nonce_authority: None,
+            address_lookup_tables: None,
+            priority_fee: None,
+            flow_nonce: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_transfer_spends_from_the_running_balance() {
+        let sender = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        let Json(response) = batch_transfer_cb(
+            State(AppState::default()),
+            Json(batch_transfer_request(
+                &sender,
+                &mint,
+                &recipients,
+                &["100", "200"],
+            )),
+        )
+        .await
+        .unwrap();
+
+        // Every transfer leaves the balance the next one spends from, and the proof accounts are
+        // only closed once all transfers are done
+        let mut decryptable_balances = Vec::new();
+        let mut last_transfer = None;
+        let mut first_close = None;
+        let instructions = response.transactions.iter().flat_map(|transaction| {
+            let transaction: VersionedTransaction =
+                bincode::deserialize(&BASE64_STANDARD.decode(transaction).unwrap()).unwrap();
+            let account_keys = transaction.message.static_account_keys().to_vec();
+            transaction
+                .message
+                .instructions()
+                .iter()
+                .map(|instruction| {
+                    (
+                        account_keys[instruction.program_id_index as usize],
+                        instruction.data.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+        for (position, (program_id, data)) in instructions.enumerate() {
+            if program_id == spl_token_2022::id() {
+                let transfer_data =
+                    decode_instruction_data::<TransferInstructionData>(&data[1..]).unwrap();
+                let decryptable_balance =
+                    AeCiphertext::try_from(transfer_data.new_source_decryptable_available_balance)
+                        .unwrap();
+                decryptable_balances.push(test_util::aes_key().decrypt(&decryptable_balance));
+                last_transfer = Some(position);
+            }
+            if program_id == solana_zk_sdk::zk_elgamal_proof_program::id()
+                && ProofInstruction::instruction_type(&data)
+                    == Some(ProofInstruction::CloseContextState)
+            {
+                first_close.get_or_insert(position);
+            }
+        }
+        assert_eq!(decryptable_balances, vec![Some(900), Some(700)]);
+        assert!(last_transfer.unwrap() < first_close.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_batch_transfer_returns_its_flow_nonces() {
+        let sender = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        let Json(response) = batch_transfer_cb(
+            State(AppState::default()),
+            Json(batch_transfer_request(
+                &sender,
+                &mint,
+                &recipients,
+                &["100", "200"],
+            )),
+        )
+        .await
+        .unwrap();
+
+        // The returned nonces derive the proof accounts of every transfer, so that they can be
+        // recovered once the available balance has changed
+        assert_eq!(response.flow_nonce_count, Some(2));
+        let elgamal_signature = Signature::try_from(
+            BASE64_STANDARD
+                .decode(test_util::elgamal_signature())
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        let sender_token_account =
+            get_associated_token_address_with_program_id(&sender, &mint, &spl_token_2022::id());
+        let account_keys = response
+            .transactions
+            .iter()
+            .flat_map(|transaction| {
+                let transaction: VersionedTransaction =
+                    bincode::deserialize(&BASE64_STANDARD.decode(transaction).unwrap()).unwrap();
+                transaction.message.static_account_keys().to_vec()
+            })
+            .collect::<Vec<_>>();
+        for index in 0..2 {
+            let range_proof_account = ProofAccount::TransferRange.keypair(
+                &elgamal_signature,
+                &sender_token_account,
+                response.flow_nonce.unwrap() + index,
+            );
+            assert!(account_keys.contains(&range_proof_account.pubkey()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_transfer_checks_the_balance_against_the_whole_batch() {
+        let sender = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        // Each transfer fits in the balance, but not both
+        let result = batch_transfer_cb(
+            State(AppState::default()),
+            Json(batch_transfer_request(
+                &sender,
+                &mint,
+                &recipients,
+                &["600", "600"],
+            )),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(AppError::InsufficientConfidentialBalance {
+                available: 1_000,
+                requested: 1_200,
+                ..
+            })
+        ));
+
+        let result = batch_transfer_cb(
+            State(AppState::default()),
+            Json(batch_transfer_request(&sender, &mint, &[], &[])),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::InvalidBatchSize)));
+    }
+
+    #[tokio::test]
+    async fn test_batch_transfer_counts_the_credits_of_repeated_recipients() {
+        let sender = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let batch = |recipients: &[Pubkey], amounts: &[&str]| {
+            let mut request = batch_transfer_request(&sender, &mint, recipients, amounts);
+            for transfer in &mut request.transfers {
+                transfer.recipient_token_account = Some(
+                    test_util::confidential_token_account_data(&recipient, &mint, 0, 0, 65_535),
+                );
+            }
+            request
+        };
+
+        // The recipient has room for one more pending balance credit
+        assert!(batch_transfer_cb(
+            State(AppState::default()),
+            Json(batch(&[recipient], &["100"]))
+        )
+        .await
+        .is_ok());
+
+        let result = batch_transfer_cb(
+            State(AppState::default()),
+            Json(batch(&[recipient, recipient], &["100", "200"])),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(AppError::RecipientPendingBalanceCreditLimitReached)
+        ));
+    }
+}
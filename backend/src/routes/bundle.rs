@@ -0,0 +1,196 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{BundleRequest, SubmitBundleRequest, SubmitBundleResponse},
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    solana_sdk::{
+        instruction::Instruction, pubkey::Pubkey, system_instruction,
+        transaction::VersionedTransaction,
+    },
+};
+
+/// Maximum number of transactions in a Jito bundle
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+
+/// Instruction tipping the block engine in bundle mode, to add after every other instruction of
+/// the flow so that it lands in the last transaction
+///
+/// The fee payer pays the tip, unless it is the server's sponsor: the tip is then paid by
+/// `owner_payer`, the paying signer of the token owner, so the sponsor never funds a tip.
+pub async fn tip_instruction(
+    state: &AppState,
+    fee_payer: &Pubkey,
+    owner_payer: &Pubkey,
+    bundle: Option<&BundleRequest>,
+) -> Result<Option<Instruction>, AppError> {
+    let Some(bundle) = bundle else {
+        return Ok(None);
+    };
+
+    let tip_lamports = bundle
+        .tip_lamports
+        .parse::<u64>()
+        .map_err(|_| AppError::InvalidAmount)?;
+    let tip_account = state.tip_account(bundle.tip_account.as_ref()).await?;
+    let tip_payer = if state.is_sponsor(fee_payer) {
+        owner_payer
+    } else {
+        fee_payer
+    };
+    println!(
+        "✅ Tipping {} lamports from {} to {}",
+        tip_lamports, tip_payer, tip_account
+    );
+
+    Ok(Some(system_instruction::transfer(
+        tip_payer,
+        &tip_account,
+        tip_lamports,
+    )))
+}
+
+/// Checks that a flow fits in a single bundle
+pub fn check_bundle_size(transaction_count: usize) -> Result<(), AppError> {
+    if transaction_count == 0 || transaction_count > MAX_BUNDLE_TRANSACTIONS {
+        println!("⛔️ Bundle of {} transactions", transaction_count);
+        return Err(AppError::InvalidBundleSize);
+    }
+
+    Ok(())
+}
+
+/// Handler forwarding client-signed transactions to the block engine as one atomic bundle
+///
+/// The transactions land in the given order, in the same block, or not at all.
+pub async fn submit_bundle_cb(
+    State(state): State<AppState>,
+    Json(request): Json<SubmitBundleRequest>,
+) -> Result<Json<SubmitBundleResponse>, AppError> {
+    println!("🚀 Starting submit_bundle_cb handler");
+
+    let block_engine = state.block_engine()?;
+    check_bundle_size(request.transactions.len())?;
+
+    let transactions = request
+        .transactions
+        .iter()
+        .map(|transaction| {
+            let transaction: VersionedTransaction =
+                bincode::deserialize(&BASE64_STANDARD.decode(transaction)?)?;
+
+            // The block engine drops the whole bundle for a single bad signature
+            if !transaction
+                .verify_with_results()
+                .into_iter()
+                .all(|valid| valid)
+            {
+                println!("⛔️ Bundle transaction is not fully signed");
+                return Err(AppError::UnsignedBundleTransaction);
+            }
+
+            Ok(transaction)
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    let bundle_id = block_engine.send_bundle(&transactions).await?;
+
+    Ok(Json(SubmitBundleResponse {
+        message: format!(
+            "Bundle {} of {} transactions submitted successfully",
+            bundle_id,
+            transactions.len()
+        ),
+        bundle_id,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::routes::test_util,
+        solana_sdk::{
+            hash::Hash,
+            message::{v0, VersionedMessage},
+            signature::{Keypair, Signature},
+            signer::Signer,
+        },
+    };
+
+    fn transfer_transaction(payer: &Keypair, signed: bool) -> String {
+        let message = v0::Message::try_compile(
+            &payer.pubkey(),
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &Pubkey::new_unique(),
+                1,
+            )],
+            &[],
+            Hash::new_unique(),
+        )
+        .unwrap();
+        let transaction = if signed {
+            VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer]).unwrap()
+        } else {
+            VersionedTransaction {
+                signatures: vec![Signature::default()],
+                message: VersionedMessage::V0(message),
+            }
+        };
+
+        BASE64_STANDARD.encode(bincode::serialize(&transaction).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_submit_bundle_forwards_transactions_in_order() {
+        let payer = Keypair::new();
+        let transactions = vec![
+            transfer_transaction(&payer, true),
+            transfer_transaction(&payer, true),
+        ];
+        let (state, bundles) = test_util::mock_block_engine_state().await;
+
+        let Json(response) = submit_bundle_cb(
+            State(state),
+            Json(SubmitBundleRequest {
+                transactions: transactions.clone(),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.bundle_id, test_util::MOCK_BUNDLE_ID);
+
+        // The block engine receives base58 encoded transactions
+        let bundles = bundles.lock().unwrap();
+        let expected = transactions
+            .iter()
+            .map(|transaction| {
+                bs58::encode(BASE64_STANDARD.decode(transaction).unwrap()).into_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(*bundles, vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn test_submit_bundle_rejects_unsigned_transactions() {
+        let payer = Keypair::new();
+        let (state, bundles) = test_util::mock_block_engine_state().await;
+
+        let result = submit_bundle_cb(
+            State(state),
+            Json(SubmitBundleRequest {
+                transactions: vec![
+                    transfer_transaction(&payer, true),
+                    transfer_transaction(&payer, false),
+                ],
+            }),
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::UnsignedBundleTransaction)));
+        assert!(bundles.lock().unwrap().is_empty());
+    }
+}
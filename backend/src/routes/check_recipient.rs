@@ -0,0 +1,181 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{CheckRecipientRequest, CheckRecipientResponse},
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    spl_token_2022::{
+        extension::{
+            confidential_transfer::ConfidentialTransferAccount, BaseStateWithExtensions,
+            StateWithExtensionsOwned,
+        },
+        state::Account,
+    },
+};
+
+/// Verifies that a token account can receive `credits` confidential transfers
+///
+/// The transfer instruction fails on chain for any of these conditions, so checking them up
+/// front avoids generating proofs and funding proof accounts for a transfer that can't land.
+/// Every transfer is a pending balance credit, so a recipient listed several times in a flow
+/// must be checked for all of its transfers at once.
+pub fn check_recipient_readiness(
+    recipient_token_account_info: &StateWithExtensionsOwned<Account>,
+    credits: u64,
+) -> Result<(), AppError> {
+    let recipient_owner = recipient_token_account_info.base.owner;
+
+    let confidential_transfer_account = recipient_token_account_info
+        .get_extension::<ConfidentialTransferAccount>()
+        .map_err(|_| {
+            println!(
+                "⛔️ Recipient account of {} has no ConfidentialTransferAccount extension",
+                recipient_owner
+            );
+            AppError::RecipientNotConfidential
+        })?;
+
+    if !bool::from(confidential_transfer_account.approved) {
+        println!(
+            "⛔️ Recipient account of {} is not approved",
+            recipient_owner
+        );
+        return Err(AppError::RecipientNotApproved);
+    }
+
+    if !bool::from(confidential_transfer_account.allow_confidential_credits) {
+        println!(
+            "⛔️ Recipient account of {} does not allow confidential credits",
+            recipient_owner
+        );
+        return Err(AppError::RecipientConfidentialCreditsDisabled);
+    }
+
+    let pending_balance_credit_counter =
+        u64::from(confidential_transfer_account.pending_balance_credit_counter);
+    let maximum_pending_balance_credit_counter =
+        u64::from(confidential_transfer_account.maximum_pending_balance_credit_counter);
+    if pending_balance_credit_counter.saturating_add(credits)
+        > maximum_pending_balance_credit_counter
+    {
+        println!(
+            "⛔️ Recipient account of {} can't take {} more pending balance credits ({}/{})",
+            recipient_owner,
+            credits,
+            pending_balance_credit_counter,
+            maximum_pending_balance_credit_counter
+        );
+        return Err(AppError::RecipientPendingBalanceCreditLimitReached);
+    }
+
+    println!(
+        "✅ Recipient account of {} can receive confidential transfers",
+        recipient_owner
+    );
+    Ok(())
+}
+
+/// Handler for the check-recipient endpoint
+///
+/// Runs the same recipient checks as transfer-cb, returning the specific error if the recipient
+/// can't receive a confidential transfer
+pub async fn check_recipient_cb(
+    State(state): State<AppState>,
+    Json(request): Json<CheckRecipientRequest>,
+) -> Result<Json<CheckRecipientResponse>, AppError> {
+    println!("🔍 Starting check_recipient_cb handler");
+
+    let recipient_token_account_info = {
+        let recipient_token_account_data = state
+            .account_data(
+                request.recipient_token_account.as_ref(),
+                request.recipient_token_account_address.as_ref(),
+                "recipient_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<Account>::unpack(recipient_token_account_data)?
+    };
+
+    check_recipient_readiness(&recipient_token_account_info, 1)?;
+
+    Ok(Json(CheckRecipientResponse {
+        ready: true,
+        message: "Recipient can receive confidential transfers".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::routes::test_util,
+        base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+        solana_sdk::pubkey::Pubkey,
+        spl_token_2022::extension::{BaseStateWithExtensionsMut, StateWithExtensionsMut},
+    };
+
+    // Builds recipient account data and lets the test tweak its confidential transfer state
+    fn recipient_account_data(update: impl FnOnce(&mut ConfidentialTransferAccount)) -> String {
+        let data = test_util::confidential_token_account_data(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            0,
+            0,
+            0,
+        );
+        let mut data = BASE64_STANDARD.decode(data).unwrap();
+        let mut state = StateWithExtensionsMut::<Account>::unpack(&mut data).unwrap();
+        update(
+            state
+                .get_extension_mut::<ConfidentialTransferAccount>()
+                .unwrap(),
+        );
+        BASE64_STANDARD.encode(data)
+    }
+
+    async fn check(recipient_token_account: String) -> Result<bool, AppError> {
+        check_recipient_cb(
+            State(AppState::default()),
+            Json(CheckRecipientRequest {
+                recipient_token_account: Some(recipient_token_account),
+                recipient_token_account_address: None,
+            }),
+        )
+        .await
+        .map(|Json(response)| response.ready)
+    }
+
+    #[tokio::test]
+    async fn test_ready_recipient() {
+        assert!(check(recipient_account_data(|_| {})).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unready_recipients() {
+        let not_approved = recipient_account_data(|extension| {
+            extension.approved = false.into();
+        });
+        assert!(matches!(
+            check(not_approved).await,
+            Err(AppError::RecipientNotApproved)
+        ));
+
+        let credits_disabled = recipient_account_data(|extension| {
+            extension.allow_confidential_credits = false.into();
+        });
+        assert!(matches!(
+            check(credits_disabled).await,
+            Err(AppError::RecipientConfidentialCreditsDisabled)
+        ));
+
+        let credit_limit_reached = recipient_account_data(|extension| {
+            extension.pending_balance_credit_counter =
+                extension.maximum_pending_balance_credit_counter;
+        });
+        assert!(matches!(
+            check(credit_limit_reached).await,
+            Err(AppError::RecipientPendingBalanceCreditLimitReached)
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{ConfigureCreditsCbRequest, TransactionResponse},
+        routes::{
+            priority_fee::{with_compute_budget, PriorityFee},
+            util::{missing_signers, parse_fee_payer},
+        },
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    bincode,
+    solana_sdk::{
+        message::{v0, VersionedMessage},
+        signature::Signature,
+        transaction::VersionedTransaction,
+    },
+    spl_associated_token_account::get_associated_token_address_with_program_id,
+    spl_token_2022::extension::{
+        confidential_transfer::instruction::{
+            disable_confidential_credits, disable_non_confidential_credits,
+            enable_confidential_credits, enable_non_confidential_credits,
+        },
+        StateWithExtensionsOwned,
+    },
+};
+
+/// Handler for enabling/disabling confidential and non-confidential credits of a token account
+pub async fn configure_credits_cb(
+    State(state): State<AppState>,
+    Json(request): Json<ConfigureCreditsCbRequest>,
+) -> Result<Json<TransactionResponse>, AppError> {
+    println!("🚀 Starting configure_credits_cb handler");
+
+    // Deserialize the account data
+    println!("📦 Decoding token account data from request");
+    let token_account_info = {
+        // Use the account data from the request, or fetch it over RPC
+        let token_account_data = state
+            .account_data(
+                request.token_account_data.as_ref(),
+                request.token_account_address.as_ref(),
+                "token_account_data",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(token_account_data)?
+    };
+
+    let mint = token_account_info.base.mint;
+    let token_account_authority = token_account_info.base.owner;
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), token_account_authority)?;
+
+    let token_account = get_associated_token_address_with_program_id(
+        &token_account_authority, // Token account owner
+        &mint,                    // Mint
+        &spl_token_2022::id(),
+    );
+
+    let mut instructions = Vec::new();
+
+    if let Some(enable) = request.confidential_credits {
+        println!(
+            "📋 Creating {} confidential credits instruction",
+            if enable { "enable" } else { "disable" }
+        );
+        let configure_instruction = if enable {
+            enable_confidential_credits
+        } else {
+            disable_confidential_credits
+        };
+        instructions.push(configure_instruction(
+            &spl_token_2022::id(),
+            &token_account,           // Token account
+            &token_account_authority, // Token account owner
+            &[],                      // Additional signers
+        )?);
+    }
+
+    if let Some(enable) = request.non_confidential_credits {
+        println!(
+            "📋 Creating {} non-confidential credits instruction",
+            if enable { "enable" } else { "disable" }
+        );
+        let configure_instruction = if enable {
+            enable_non_confidential_credits
+        } else {
+            disable_non_confidential_credits
+        };
+        instructions.push(configure_instruction(
+            &spl_token_2022::id(),
+            &token_account,           // Token account
+            &token_account_authority, // Token account owner
+            &[],                      // Additional signers
+        )?);
+    }
+
+    if instructions.is_empty() {
+        println!("⛔️ No credits configuration provided");
+        return Err(AppError::NoCreditsConfigurationProvided);
+    }
+    println!(
+        "✅ Created {} configure credits instructions",
+        instructions.len()
+    );
+
+    // Use the blockhash from the request, or fetch the latest one over RPC
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+    let priority_fee = PriorityFee::from_request(request.priority_fee.as_ref())?;
+
+    // Create a V0 message with the provided blockhash
+    let v0_message = v0::Message::try_compile(
+        &fee_payer,
+        &with_compute_budget(priority_fee.as_ref(), instructions),
+        &lookup_tables,
+        client_blockhash,
+    )
+    .map_err(|_| AppError::SerializationError)?;
+
+    // Get the number of required signatures before moving v0_message
+    let num_required_signatures = v0_message.header.num_required_signatures as usize;
+
+    // Create a versioned transaction with placeholder signatures for required signers
+    // (will be replaced by the wallet)
+    let mut versioned_transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); num_required_signatures],
+        message: VersionedMessage::V0(v0_message),
+    };
+
+    // Sign as fee payer if the transaction is sponsored by the server
+    state
+        .co_sign(
+            "configure-credits-cb",
+            0,
+            std::slice::from_mut(&mut versioned_transaction),
+        )
+        .await?;
+
+    // Serialize the transaction to base64
+    let serialized_transaction = match bincode::serialize(&versioned_transaction) {
+        Ok(bytes) => BASE64_STANDARD.encode(bytes),
+        Err(_) => return Err(AppError::SerializationError),
+    };
+
+    println!("✅ Transaction created successfully");
+
+    Ok(Json(TransactionResponse {
+        transaction: serialized_transaction,
+        required_signers: missing_signers(&versioned_transaction),
+        message: "Configure credits CB transaction created successfully".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::routes::test_util,
+        solana_sdk::pubkey::Pubkey,
+        spl_token_2022::{
+            extension::confidential_transfer::instruction::ConfidentialTransferInstruction,
+            instruction::decode_instruction_type,
+        },
+    };
+
+    fn configure_credits_request(
+        owner: &Pubkey,
+        mint: &Pubkey,
+        confidential_credits: Option<bool>,
+        non_confidential_credits: Option<bool>,
+    ) -> ConfigureCreditsCbRequest {
+        ConfigureCreditsCbRequest {
+            token_account_data: Some(test_util::confidential_token_account_data(
+                owner, mint, 0, 0, 0,
+            )),
+            token_account_address: None,
+            confidential_credits,
+            non_confidential_credits,
+            fee_payer: None,
+            latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+            address_lookup_tables: None,
+            priority_fee: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configure_credits_to_accept_only_confidential_inflows() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let Json(response) = configure_credits_cb(
+            State(AppState::default()),
+            Json(configure_credits_request(
+                &owner,
+                &mint,
+                Some(true),
+                Some(false),
+            )),
+        )
+        .await
+        .unwrap();
+
+        let instructions = test_util::decode_transaction(&response.transaction)
+            .into_iter()
+            .map(|(program_id, data)| {
+                assert_eq!(program_id, spl_token_2022::id());
+                decode_instruction_type::<ConfidentialTransferInstruction>(&data[1..]).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            instructions.as_slice(),
+            [
+                ConfidentialTransferInstruction::EnableConfidentialCredits,
+                ConfidentialTransferInstruction::DisableNonConfidentialCredits,
+            ]
+        ));
+
+        // The owner of the token account signs, and pays the fees by default
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&BASE64_STANDARD.decode(&response.transaction).unwrap()).unwrap();
+        let account_keys = transaction.message.static_account_keys();
+        assert_eq!(account_keys[0], owner);
+        assert!(
+            account_keys.contains(&get_associated_token_address_with_program_id(
+                &owner,
+                &mint,
+                &spl_token_2022::id(),
+            ))
+        );
+        assert_eq!(response.required_signers, vec![owner.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_configure_credits_requires_a_setting() {
+        let result = configure_credits_cb(
+            State(AppState::default()),
+            Json(configure_credits_request(
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                None,
+                None,
+            )),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(AppError::NoCreditsConfigurationProvided)
+        ));
+    }
+}
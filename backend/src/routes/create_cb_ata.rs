@@ -2,9 +2,16 @@ use {
     crate::{
         errors::AppError,
         models::{CreateCbAtaRequest, TransactionResponse},
-        routes::util::{parse_base64_base58_pubkey, parse_latest_blockhash},
+        routes::{
+            priority_fee::{with_compute_budget, PriorityFee},
+            util::{
+                missing_signers, parse_base64_base58_pubkey, parse_fee_payer,
+                parse_token_account_authority,
+            },
+        },
+        state::AppState,
     },
-    axum::extract::Json,
+    axum::extract::{Json, State},
     base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
     bincode,
     solana_sdk::{
@@ -28,6 +35,7 @@ use {
 
 /// Handler for creating a Confidential Balances associated token account
 pub async fn create_cb_ata(
+    State(state): State<AppState>,
     Json(request): Json<CreateCbAtaRequest>,
 ) -> Result<Json<TransactionResponse>, AppError> {
     println!("🚀 Starting create_cb_ata handler");
@@ -44,6 +52,14 @@ pub async fn create_cb_ata(
         token_account_authority
     );
 
+    // The owner may be an SPL multisig, in which case its first signer funds the account
+    let authority = parse_token_account_authority(
+        &token_account_authority,
+        request.multisig.as_ref(),
+        request.multisig_signers.as_ref(),
+    )?;
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), authority.fee_payer())?;
+
     // Parse the mint address
     println!("🪙 Parsing mint address");
     let mint = parse_base64_base58_pubkey(&request.mint)?;
@@ -64,7 +80,7 @@ pub async fn create_cb_ata(
     // Instruction to create associated token account
     println!("📋 Creating instruction to create associated token account");
     let create_associated_token_account_instruction = create_associated_token_account(
-        &token_account_authority, // Funding account
+        &fee_payer,               // Funding account
         &token_account_authority, // Token account owner
         &mint,                    // Mint
         &spl_token_2022::id(),
@@ -76,9 +92,9 @@ pub async fn create_cb_ata(
     let reallocate_instruction = reallocate(
         &spl_token_2022::id(),
         &token_account_pubkey,                         // Token account
-        &token_account_authority,                      // Payer
+        &fee_payer,                                    // Payer
         &token_account_authority,                      // Token account owner
-        &[&token_account_authority],                   // Signers
+        &authority.signers(),                          // Multisig signers
         &[ExtensionType::ConfidentialTransferAccount], // Extension to reallocate space for
     )?;
     println!("✅ Reallocate instruction created");
@@ -154,7 +170,7 @@ pub async fn create_cb_ata(
         &decryptable_balance.into(),            // Initial balance
         maximum_pending_balance_credit_counter, // Maximum pending balance credit counter
         &token_account_authority,               // Token Account Owner
-        &[],                                    // Additional signers
+        &authority.signers(),                   // Multisig signers
         proof_location,                         // Proof location
     )?;
     println!("✅ Configure account instructions created successfully");
@@ -167,17 +183,20 @@ pub async fn create_cb_ata(
     instructions.extend(configure_account_instruction);
     println!("✅ Combined {} instructions", instructions.len());
 
-    // Parse the provided blockhash from the request
-    let client_blockhash = parse_latest_blockhash(&request.latest_blockhash)?;
+    // Use the blockhash from the request, or fetch the latest one over RPC
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+    let priority_fee = PriorityFee::from_request(request.priority_fee.as_ref())?;
+    let instructions = with_compute_budget(priority_fee.as_ref(), instructions);
 
     println!("📝 Creating V0 message");
-    let v0_message = v0::Message::try_compile(
-        &token_account_authority,
-        &instructions,
-        &[],
-        client_blockhash,
-    )
-    .map_err(|_| AppError::SerializationError)?;
+    let v0_message =
+        v0::Message::try_compile(&fee_payer, &instructions, &lookup_tables, client_blockhash)
+            .map_err(|_| AppError::SerializationError)?;
     println!("✅ V0 message created successfully");
 
     let num_required_signatures = v0_message.header.num_required_signatures as usize;
@@ -198,11 +217,20 @@ pub async fn create_cb_ata(
         signatures.push(solana_sdk::signature::Signature::default());
     }
 
-    let versioned_transaction = VersionedTransaction {
+    let mut versioned_transaction = VersionedTransaction {
         signatures,
         message: versioned_message,
     };
 
+    // Sign as fee payer if the transaction is sponsored by the server
+    state
+        .co_sign(
+            "create-cb-ata",
+            0,
+            std::slice::from_mut(&mut versioned_transaction),
+        )
+        .await?;
+
     // Serialize the transaction to base64
     println!("🔄 Serializing transaction to base64");
     let serialized_transaction = match bincode::serialize(&versioned_transaction) {
@@ -217,6 +245,7 @@ pub async fn create_cb_ata(
     println!("🎉 Transaction creation completed successfully");
     Ok(Json(TransactionResponse {
         transaction: serialized_transaction,
+        required_signers: missing_signers(&versioned_transaction),
         message: "Confidential Balances ATA transaction created successfully".to_string(),
     }))
 }
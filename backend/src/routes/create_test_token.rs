@@ -2,30 +2,36 @@ use {
     crate::{
         errors::AppError,
         models::{CreateTestTokenTransactionRequest, TransactionResponse},
-        routes::util::{parse_base58_pubkey, parse_latest_blockhash},
+        routes::util::{missing_signers, parse_base58_pubkey, parse_fee_payer},
+        state::AppState,
     },
-    axum::extract::Json,
+    axum::extract::{Json, State},
     base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
     bincode,
     solana_sdk::{
         message::{v0, VersionedMessage},
+        signature::Signature,
         system_instruction,
         transaction::VersionedTransaction,
     },
     solana_zk_sdk::encryption::pod::elgamal::PodElGamalPubkey,
     spl_token_2022::{
         extension::{
+            confidential_mint_burn::instruction::initialize_mint as initialize_confidential_mint_burn,
             confidential_transfer::instruction::initialize_mint as initialize_confidential_transfer_mint,
             ExtensionType,
         },
         instruction::{initialize_mint, initialize_mint_close_authority},
+        solana_zk_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
         state::Mint,
     },
     std::str::FromStr,
 };
 
 /// Handler for creating a test token mint with confidential transfers and close mint support
+/// (optionally with confidential mint/burn)
 pub async fn create_test_token_cb(
+    State(state): State<AppState>,
     Json(request): Json<CreateTestTokenTransactionRequest>,
 ) -> Result<Json<TransactionResponse>, AppError> {
     // Parse the account address from base58 to use as mint authority and freeze authority
@@ -38,16 +44,23 @@ pub async fn create_test_token_cb(
         request.account, request.mint,
     );
 
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), authority_pubkey)?;
+
     // Validate that mint address is different from authority
     if mint_address == authority_pubkey {
         return Err(AppError::InvalidAddress);
     }
 
+    let confidential_mint_burn = request.confidential_mint_burn.unwrap_or(false);
+
     // Calculate space required for mint account with extensions
-    let extensions = vec![
+    let mut extensions = vec![
         ExtensionType::ConfidentialTransferMint,
         ExtensionType::MintCloseAuthority,
     ];
+    if confidential_mint_burn {
+        extensions.push(ExtensionType::ConfidentialMintBurn);
+    }
     let mint_space = ExtensionType::try_calculate_account_len::<Mint>(&extensions)
         .map_err(|_| AppError::SerializationError)?;
 
@@ -56,30 +69,19 @@ pub async fn create_test_token_cb(
         mint_space.to_string()
     );
 
-    // Use rent provided by client or calculate a reasonable default
-    // For Token-2022 mints with extensions, rent is typically higher than regular mints
+    // Use the rent-exempt minimum for the mint length, unless the client overrides it
     let mint_rent = match request.mint_rent {
-        Some(rent) => rent,
-        None => {
-            // Calculate minimum rent based on space + buffer for extensions
-            // Solana rent is approximately 0.00000348 SOL per byte
-            let base_rent = mint_space as u64 * 3500; // ~3500 lamports per byte
-            let extension_buffer = 5_000_000; // 0.005 SOL buffer for extensions
-            base_rent + extension_buffer
-        }
-    };
-
-    match request.mint_rent {
-        Some(_) => {
+        Some(rent) => {
             println!("💰 Using rent amount from request!");
+            rent
         }
-        None => (),
-    }
+        None => state.rent(None, mint_space).await?,
+    };
     println!("💰 Rent amount: {} lamports", mint_rent);
 
     // Create the mint account instruction
     let create_mint_account_instruction = system_instruction::create_account(
-        &authority_pubkey,     // Fee payer
+        &fee_payer,            // Fee payer
         &mint_address,         // New account (mint)
         mint_rent,             // Lamports for rent
         mint_space as u64,     // Space required
@@ -113,13 +115,20 @@ pub async fn create_test_token_cb(
         None => None,
     };
 
+    // New accounts are approved automatically unless the client asks for a permissioned mint
+    let auto_approve_new_accounts = request.auto_approve_new_accounts.unwrap_or(true);
+    println!(
+        "✅ Auto approve new accounts: {}",
+        auto_approve_new_accounts
+    );
+
     // Initialize ConfidentialTransferMint extension
     let initialize_confidential_transfer_mint_instruction = initialize_confidential_transfer_mint(
-        &spl_token_2022::id(),  // Program ID
-        &mint_address,          // Mint account
-        Some(authority_pubkey), // Authority that can modify confidential transfer settings
-        true,                   // Auto approve new accounts
-        auditor_elgamal_pk,     // Optional auditor ElGamal key
+        &spl_token_2022::id(),     // Program ID
+        &mint_address,             // Mint account
+        Some(authority_pubkey),    // Authority that can modify confidential transfer settings
+        auto_approve_new_accounts, // Auto approve new accounts
+        auditor_elgamal_pk,        // Optional auditor ElGamal key
     )
     .map_err(|_| AppError::SerializationError)?;
 
@@ -131,6 +140,50 @@ pub async fn create_test_token_cb(
     )
     .map_err(|_| AppError::SerializationError)?;
 
+    // Initialize ConfidentialMintBurn extension (optional)
+    // The supply is encrypted under the mint authority's keys derived from its signatures
+    let initialize_confidential_mint_burn_instruction = if confidential_mint_burn {
+        let (Some(elgamal_signature), Some(aes_signature)) =
+            (&request.elgamal_signature, &request.aes_signature)
+        else {
+            println!("⛔️ ConfidentialMintBurn requested without ElGamal and AES signatures");
+            return Err(AppError::MissingSupplySignatures);
+        };
+
+        let supply_elgamal_keypair = {
+            let decoded_elgamal_signature = BASE64_STANDARD.decode(elgamal_signature)?;
+            let elgamal_signature = Signature::try_from(decoded_elgamal_signature.as_slice())
+                .map_err(|_| AppError::SerializationError)?;
+
+            ElGamalKeypair::new_from_signature(&elgamal_signature)
+                .map_err(|_| AppError::SerializationError)?
+        };
+
+        let supply_aes_key = {
+            let decoded_aes_signature = BASE64_STANDARD.decode(aes_signature)?;
+            let aes_signature = Signature::try_from(decoded_aes_signature.as_slice())
+                .map_err(|_| AppError::SerializationError)?;
+
+            AeKey::new_from_signature(&aes_signature).map_err(|_| AppError::SerializationError)?
+        };
+        println!("✅ Supply ElGamal keypair and AES key created successfully");
+
+        // Initial supply is 0
+        let decryptable_supply = supply_aes_key.encrypt(0);
+
+        Some(
+            initialize_confidential_mint_burn(
+                &spl_token_2022::id(),                      // Program ID
+                &mint_address,                              // Mint account
+                &(*supply_elgamal_keypair.pubkey()).into(), // Supply ElGamal key
+                &decryptable_supply.into(),                 // Initial decryptable supply
+            )
+            .map_err(|_| AppError::SerializationError)?,
+        )
+    } else {
+        None
+    };
+
     // Initialize the mint itself with 9 decimals
     let initialize_mint_instruction = initialize_mint(
         &spl_token_2022::id(),
@@ -142,20 +195,22 @@ pub async fn create_test_token_cb(
     .map_err(|_| AppError::SerializationError)?;
 
     // Combine all instructions in the correct order
-    let instructions = vec![
+    let mut instructions = vec![
         create_mint_account_instruction,
         initialize_confidential_transfer_mint_instruction,
-        initialize_mint_close_authority_instruction,
-        initialize_mint_instruction,
     ];
+    instructions.extend(initialize_confidential_mint_burn_instruction);
+    instructions.push(initialize_mint_close_authority_instruction);
+    instructions.push(initialize_mint_instruction);
 
-    // Use blockhash from client or generate a placeholder
-    let client_blockhash = parse_latest_blockhash(&request.latest_blockhash)?;
+    // Use the blockhash from the request, or fetch the latest one over RPC
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
 
     println!("📝 Creating V0 message");
-    let v0_message =
-        v0::Message::try_compile(&authority_pubkey, &instructions, &[], client_blockhash)
-            .map_err(|_| AppError::SerializationError)?;
+    let v0_message = v0::Message::try_compile(&fee_payer, &instructions, &[], client_blockhash)
+        .map_err(|_| AppError::SerializationError)?;
     println!("✅ V0 message created successfully");
 
     // Get the number of required signatures
@@ -201,6 +256,7 @@ pub async fn create_test_token_cb(
     println!("🎉 Transaction creation completed successfully");
     Ok(Json(TransactionResponse {
         transaction: serialized_transaction,
+        required_signers: missing_signers(&versioned_transaction),
         message: response_message,
     }))
 }
@@ -2,8 +2,10 @@ use {
     crate::{
         errors::AppError,
         models::{DecryptCbRequest, DecryptCbResponse},
+        routes::util::decrypt_pending_balance,
+        state::AppState,
     },
-    axum::extract::Json,
+    axum::extract::{Json, State},
     base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
     solana_sdk::signature::Signature,
     solana_zk_sdk::encryption::auth_encryption::AeCiphertext,
@@ -12,12 +14,13 @@ use {
             confidential_transfer::ConfidentialTransferAccount, BaseStateWithExtensions,
             StateWithExtensionsOwned,
         },
-        solana_zk_sdk::encryption::auth_encryption::AeKey,
+        solana_zk_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
     },
 };
 
 /// Handler for decrypting a Confidential Balance
 pub async fn decrypt_cb(
+    State(state): State<AppState>,
     Json(request): Json<DecryptCbRequest>,
 ) -> Result<Json<DecryptCbResponse>, AppError> {
     println!("🔐 Starting decrypt_cb handler");
@@ -35,8 +38,14 @@ pub async fn decrypt_cb(
 
     // Get the token account info
     let token_account_info = {
-        // Decode token account data from request instead of fetching it
-        let token_account_data = BASE64_STANDARD.decode(&request.token_account_data)?;
+        // Use the account data from the request, or fetch it over RPC
+        let token_account_data = state
+            .account_data(
+                request.token_account_data.as_ref(),
+                request.token_account_address.as_ref(),
+                "token_account_data",
+            )
+            .await?;
         StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(token_account_data)?
     };
     println!("🧳 Unpacked token account info");
@@ -53,9 +62,112 @@ pub async fn decrypt_cb(
         .decrypt(&available_balance)
         .ok_or(AppError::SerializationError)?;
 
+    let pending_balance_credit_counter =
+        u64::from(confidential_transfer_account.pending_balance_credit_counter);
+
+    // The pending balance is only encrypted under the ElGamal key, so it can only be decrypted
+    // (via discrete log) when the client provides the ElGamal signature
+    let decrypted_pending_balance = match &request.elgamal_signature {
+        Some(elgamal_signature) => {
+            let elgamal_keypair = {
+                let decoded_elgamal_signature = BASE64_STANDARD.decode(elgamal_signature)?;
+
+                let elgamal_signature = Signature::try_from(decoded_elgamal_signature.as_slice())
+                    .map_err(|_| AppError::SerializationError)?;
+
+                ElGamalKeypair::new_from_signature(&elgamal_signature)
+                    .map_err(|_| AppError::SerializationError)?
+            };
+            println!("✅ ElGamal keypair created successfully");
+
+            let pending_balance =
+                decrypt_pending_balance(confidential_transfer_account, &elgamal_keypair)?;
+            println!("✅ Decrypted pending balance: {}", pending_balance);
+
+            Some(pending_balance)
+        }
+        None => {
+            println!("⚠️ No ElGamal signature provided, skipping pending balance decryption");
+            None
+        }
+    };
+
+    let total_balance = decrypted_pending_balance
+        .map(|pending_balance| {
+            decrypted_balance
+                .checked_add(pending_balance)
+                .ok_or(AppError::DecryptionError)
+        })
+        .transpose()?;
+
     println!("✅ Returning decrypted balance");
     Ok(Json(DecryptCbResponse {
         amount: decrypted_balance.to_string(),
+        available_amount: decrypted_balance.to_string(),
+        pending_amount: decrypted_pending_balance.map(|amount| amount.to_string()),
+        total_amount: total_balance.map(|amount| amount.to_string()),
+        pending_balance_credit_counter: pending_balance_credit_counter.to_string(),
         message: "Decryption successful".to_string(),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::routes::test_util, solana_sdk::pubkey::Pubkey};
+
+    #[tokio::test]
+    async fn test_decrypt_available_and_pending_balance() {
+        let token_account_data = test_util::confidential_token_account_data(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            1_000,
+            70_000,
+            2,
+        );
+
+        let Json(response) = decrypt_cb(
+            State(AppState::default()),
+            Json(DecryptCbRequest {
+                aes_signature: test_util::aes_signature(),
+                elgamal_signature: Some(test_util::elgamal_signature()),
+                token_account_data: Some(token_account_data),
+                token_account_address: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.amount, "1000");
+        assert_eq!(response.available_amount, "1000");
+        assert_eq!(response.pending_amount.as_deref(), Some("70000"));
+        assert_eq!(response.total_amount.as_deref(), Some("71000"));
+        assert_eq!(response.pending_balance_credit_counter, "2");
+    }
+
+    #[tokio::test]
+    async fn test_decrypt_without_elgamal_signature_skips_pending_balance() {
+        let token_account_data = test_util::confidential_token_account_data(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            1_000,
+            70_000,
+            2,
+        );
+
+        let Json(response) = decrypt_cb(
+            State(AppState::default()),
+            Json(DecryptCbRequest {
+                aes_signature: test_util::aes_signature(),
+                elgamal_signature: None,
+                token_account_data: Some(token_account_data),
+                token_account_address: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.available_amount, "1000");
+        assert!(response.pending_amount.is_none());
+        assert!(response.total_amount.is_none());
+    }
+}
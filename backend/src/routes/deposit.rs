@@ -2,9 +2,13 @@ use {
     crate::{
         errors::AppError,
         models::{DepositCbRequest, TransactionResponse},
-        routes::util::parse_latest_blockhash,
+        routes::{
+            priority_fee::{with_compute_budget, PriorityFee},
+            util::{missing_signers, parse_fee_payer, parse_token_account_authority, with_memo},
+        },
+        state::AppState,
     },
-    axum::extract::Json,
+    axum::extract::{Json, State},
     base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
     bincode,
     solana_sdk::{
@@ -19,6 +23,7 @@ use {
 
 /// Handler for depositing to a Confidential Balances account
 pub async fn deposit_cb(
+    State(state): State<AppState>,
     Json(request): Json<DepositCbRequest>,
 ) -> Result<Json<TransactionResponse>, AppError> {
     println!("🚀 Starting deposit_cb handler");
@@ -26,8 +31,14 @@ pub async fn deposit_cb(
     // Deserialize the account data
     println!("📦 Decoding token account data from request");
     let token_account_info = {
-        // Decode token account data from request instead of fetching it
-        let token_account_data = BASE64_STANDARD.decode(&request.token_account_data)?;
+        // Use the account data from the request, or fetch it over RPC
+        let token_account_data = state
+            .account_data(
+                request.token_account_data.as_ref(),
+                request.token_account_address.as_ref(),
+                "token_account_data",
+            )
+            .await?;
         StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(token_account_data)?
     };
 
@@ -46,6 +57,12 @@ pub async fn deposit_cb(
 
     let mint = token_account_info.base.mint;
     let token_account_authority = token_account_info.base.owner;
+    let authority = parse_token_account_authority(
+        &token_account_authority,
+        request.multisig.as_ref(),
+        request.multisig_signers.as_ref(),
+    )?;
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), authority.fee_payer())?;
 
     let depositor_token_account = get_associated_token_address_with_program_id(
         &token_account_authority, // Token account owner
@@ -53,25 +70,37 @@ pub async fn deposit_cb(
         &spl_token_2022::id(),
     );
 
+    // Use the mint decimals from the request, or read them from the mint over RPC
+    let mint_decimals = state.mint_decimals(request.mint_decimals, &mint).await?;
+
     let deposit_instruction = deposit(
         &spl_token_2022::id(),
-        &depositor_token_account,    // Token account
-        &mint,                       // Mint
-        deposit_amount,              // Amount to deposit
-        request.mint_decimals,       // Mint decimals
-        &token_account_authority,    // Token account owner
-        &[&token_account_authority], // Signers
+        &depositor_token_account, // Token account
+        &mint,                    // Mint
+        deposit_amount,           // Amount to deposit
+        mint_decimals,            // Mint decimals
+        &token_account_authority, // Token account owner
+        &authority.signers(),     // Multisig signers
     )?;
     println!("✅ Deposit instruction created successfully");
 
-    // Parse the provided blockhash from the request
-    let client_blockhash = parse_latest_blockhash(&request.latest_blockhash)?;
+    // Use the blockhash from the request, or fetch the latest one over RPC
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+    let priority_fee = PriorityFee::from_request(request.priority_fee.as_ref())?;
 
     // Create a V0 message with the provided blockhash
     let v0_message = v0::Message::try_compile(
-        &token_account_authority,
-        &[deposit_instruction],
-        &[],
+        &fee_payer,
+        &with_compute_budget(
+            priority_fee.as_ref(),
+            with_memo(request.memo.as_ref(), vec![deposit_instruction]),
+        ),
+        &lookup_tables,
         client_blockhash,
     )
     .map_err(|_| AppError::SerializationError)?;
@@ -90,11 +119,20 @@ pub async fn deposit_cb(
         signatures.push(solana_sdk::signature::Signature::default());
     }
 
-    let versioned_transaction = VersionedTransaction {
+    let mut versioned_transaction = VersionedTransaction {
         signatures,
         message: versioned_message,
     };
 
+    // Sign as fee payer if the transaction is sponsored by the server
+    state
+        .co_sign(
+            "deposit-cb",
+            deposit_amount,
+            std::slice::from_mut(&mut versioned_transaction),
+        )
+        .await?;
+
     // Serialize the transaction to base64
     let serialized_transaction = match bincode::serialize(&versioned_transaction) {
         Ok(bytes) => BASE64_STANDARD.encode(bytes),
@@ -105,6 +143,157 @@ pub async fn deposit_cb(
 
     Ok(Json(TransactionResponse {
         transaction: serialized_transaction,
+        required_signers: missing_signers(&versioned_transaction),
         message: "Deposit CB transaction created successfully".to_string(),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::routes::test_util, solana_sdk::pubkey::Pubkey};
+
+    #[tokio::test]
+    async fn test_deposit_from_multisig_account() {
+        let multisig = Pubkey::new_unique();
+        let signers = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        let Json(response) = deposit_cb(
+            State(AppState::default()),
+            Json(DepositCbRequest {
+                token_account_data: Some(test_util::confidential_token_account_data(
+                    &multisig,
+                    &Pubkey::new_unique(),
+                    0,
+                    0,
+                    0,
+                )),
+                token_account_address: None,
+                lamport_amount: "100".to_string(),
+                mint_decimals: Some(2),
+                multisig: Some(multisig.to_string()),
+                multisig_signers: Some(signers.iter().map(|signer| signer.to_string()).collect()),
+                fee_payer: None,
+                latest_blockhash: Some(solana_sdk::hash::Hash::default().to_string()),
+                address_lookup_tables: None,
+                priority_fee: None,
+                memo: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        // The multisig itself never signs, the first signer also pays the fees
+        assert_eq!(
+            response.required_signers,
+            vec![signers[0].to_string(), signers[1].to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deposit_with_sponsored_fee_payer() {
+        let owner = Pubkey::new_unique();
+        let sponsor = Pubkey::new_unique();
+
+        let Json(response) = deposit_cb(
+            State(AppState::default()),
+            Json(DepositCbRequest {
+                token_account_data: Some(test_util::confidential_token_account_data(
+                    &owner,
+                    &Pubkey::new_unique(),
+                    0,
+                    0,
+                    0,
+                )),
+                token_account_address: None,
+                lamport_amount: "100".to_string(),
+                mint_decimals: Some(2),
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: Some(sponsor.to_string()),
+                latest_blockhash: Some(solana_sdk::hash::Hash::default().to_string()),
+                address_lookup_tables: None,
+                priority_fee: None,
+                memo: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        // The sponsor pays the fees, the owner still authorizes the deposit
+        assert_eq!(
+            response.required_signers,
+            vec![sponsor.to_string(), owner.to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deposit_fetches_account_data_decimals_and_blockhash_over_rpc() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let state = test_util::mock_rpc_state(vec![
+            (
+                token_account,
+                test_util::confidential_token_account_data(&owner, &mint, 0, 0, 0),
+            ),
+            (mint, test_util::confidential_mint_data(&owner, false)),
+        ])
+        .await;
+
+        let Json(response) = deposit_cb(
+            State(state),
+            Json(DepositCbRequest {
+                token_account_data: None,
+                token_account_address: Some(token_account.to_string()),
+                lamport_amount: "100".to_string(),
+                mint_decimals: None,
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: None,
+                latest_blockhash: None,
+                address_lookup_tables: None,
+                priority_fee: None,
+                memo: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&BASE64_STANDARD.decode(response.transaction).unwrap()).unwrap();
+        assert_eq!(
+            *transaction.message.recent_blockhash(),
+            test_util::mock_blockhash()
+        );
+        // The deposit instruction data ends with the mint decimals
+        let deposit_instruction = &transaction.message.instructions()[0];
+        assert_eq!(deposit_instruction.data.last(), Some(&2));
+        assert_eq!(response.required_signers, vec![owner.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_deposit_without_account_data_or_rpc() {
+        let result = deposit_cb(
+            State(AppState::default()),
+            Json(DepositCbRequest {
+                token_account_data: None,
+                token_account_address: Some(Pubkey::new_unique().to_string()),
+                lamport_amount: "100".to_string(),
+                mint_decimals: Some(2),
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: None,
+                latest_blockhash: Some(solana_sdk::hash::Hash::default().to_string()),
+                address_lookup_tables: None,
+                priority_fee: None,
+                memo: None,
+            }),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(AppError::MissingRequestField("token_account_data"))
+        ));
+    }
+}
@@ -0,0 +1,372 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{EmptyCbRequest, TransactionResponse},
+        routes::{
+            priority_fee::{with_compute_budget, PriorityFee},
+            util::{missing_signers, parse_fee_payer, parse_token_account_authority},
+        },
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    bincode,
+    bytemuck::Zeroable,
+    solana_sdk::{
+        message::{v0, VersionedMessage},
+        signature::Signature,
+        transaction::VersionedTransaction,
+    },
+    solana_zk_sdk::encryption::auth_encryption::AeCiphertext,
+    spl_associated_token_account::get_associated_token_address_with_program_id,
+    spl_token_2022::{
+        extension::{
+            confidential_transfer::{
+                account_info::EmptyAccountAccountInfo, instruction::empty_account,
+                ConfidentialTransferAccount, EncryptedBalance,
+            },
+            BaseStateWithExtensions, StateWithExtensionsOwned,
+        },
+        instruction::close_account,
+        solana_zk_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
+    },
+    spl_token_confidential_transfer_proof_extraction::instruction::{ProofData, ProofLocation},
+};
+
+/// Handler for emptying (and optionally closing) a Confidential Balances account
+pub async fn empty_cb(
+    State(state): State<AppState>,
+    Json(request): Json<EmptyCbRequest>,
+) -> Result<Json<TransactionResponse>, AppError> {
+    println!("🚀 Starting empty_cb handler");
+
+    // Deserialize the account data
+    println!("📦 Decoding token account data from request");
+    let token_account_info = {
+        let token_account_data = state
+            .account_data(
+                request.token_account_data.as_ref(),
+                request.token_account_address.as_ref(),
+                "token_account_data",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(token_account_data)?
+    };
+
+    let mint = token_account_info.base.mint;
+    let token_account_authority = token_account_info.base.owner;
+    let close = request.close_account.unwrap_or(false);
+    let authority = parse_token_account_authority(
+        &token_account_authority,
+        request.multisig.as_ref(),
+        request.multisig_signers.as_ref(),
+    )?;
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), authority.fee_payer())?;
+
+    let token_account = get_associated_token_address_with_program_id(
+        &token_account_authority, // Token account owner
+        &mint,                    // Mint
+        &spl_token_2022::id(),
+    );
+    println!("✅ Calculated token account address: {}", token_account);
+
+    let confidential_transfer_account =
+        token_account_info.get_extension::<ConfidentialTransferAccount>()?;
+
+    // The account can only be emptied once every pending credit has been applied
+    let pending_balance_credit_counter =
+        u64::from(confidential_transfer_account.pending_balance_credit_counter);
+    if pending_balance_credit_counter != 0
+        || confidential_transfer_account.pending_balance_lo != EncryptedBalance::zeroed()
+        || confidential_transfer_account.pending_balance_hi != EncryptedBalance::zeroed()
+    {
+        println!(
+            "⛔️ Pending balance is not empty: credit counter is {}",
+            pending_balance_credit_counter
+        );
+        return Err(AppError::PendingBalanceNotEmpty);
+    }
+
+    // Closing also requires the non-confidential balance to be zero
+    if close && token_account_info.base.amount != 0 {
+        println!(
+            "⛔️ Non-confidential balance is not empty: {}",
+            token_account_info.base.amount
+        );
+        return Err(AppError::NonConfidentialBalanceNotEmpty);
+    }
+
+    // Create the ElGamal keypair
+    let elgamal_keypair = {
+        let decoded_elgamal_signature = BASE64_STANDARD.decode(&request.elgamal_signature)?;
+        let elgamal_signature = Signature::try_from(decoded_elgamal_signature.as_slice())
+            .map_err(|_| AppError::SerializationError)?;
+
+        ElGamalKeypair::new_from_signature(&elgamal_signature)
+            .map_err(|_| AppError::SerializationError)?
+    };
+    println!("✅ ElGamal keypair created successfully");
+
+    // Create the AES key
+    let aes_key = {
+        let decoded_aes_signature = BASE64_STANDARD.decode(&request.aes_signature)?;
+        let aes_signature = Signature::try_from(decoded_aes_signature.as_slice())
+            .map_err(|_| AppError::SerializationError)?;
+
+        AeKey::new_from_signature(&aes_signature).map_err(|_| AppError::SerializationError)?
+    };
+    println!("✅ AES key created successfully");
+
+    // The zero ciphertext proof only verifies if the available balance is actually zero
+    let available_balance = {
+        let decryptable_available_balance =
+            AeCiphertext::try_from(confidential_transfer_account.decryptable_available_balance)
+                .map_err(|_| AppError::SerializationError)?;
+        aes_key
+            .decrypt(&decryptable_available_balance)
+            .ok_or(AppError::DecryptionError)?
+    };
+    if available_balance != 0 {
+        println!("⛔️ Available balance is not empty: {}", available_balance);
+        return Err(AppError::AvailableBalanceNotEmpty);
+    }
+
+    // The instruction data that is needed for the `ProofInstruction::VerifyZeroCiphertext` instruction.
+    println!("🧩 Generating zero ciphertext proof data");
+    let proof_data = EmptyAccountAccountInfo::new(confidential_transfer_account)
+        .generate_proof_data(&elgamal_keypair)?;
+    println!("✅ Zero ciphertext proof data generated successfully");
+
+    // `InstructionOffset` indicates that proof is included in the same transaction
+    let proof_location = ProofLocation::InstructionOffset(
+        1.try_into().unwrap(),
+        ProofData::InstructionData(&proof_data),
+    );
+
+    let mut instructions = empty_account(
+        &spl_token_2022::id(),    // Program ID
+        &token_account,           // Token account
+        &token_account_authority, // Token account owner
+        &authority.signers(),     // Multisig signers
+        proof_location,           // Proof location
+    )?;
+    println!("✅ Empty account instructions created successfully");
+
+    // Return the rent of the token account to its owner, whoever pays the fees
+    if close {
+        instructions.push(close_account(
+            &spl_token_2022::id(),
+            &token_account,           // Account to close
+            &token_account_authority, // Rent destination
+            &token_account_authority, // Token account owner
+            &authority.signers(),     // Multisig signers
+        )?);
+        println!("✅ Close account instruction created successfully");
+    }
+
+    // Use the blockhash from the request, or fetch the latest one over RPC
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+    let priority_fee = PriorityFee::from_request(request.priority_fee.as_ref())?;
+
+    // Create a V0 message with the provided blockhash
+    let v0_message = v0::Message::try_compile(
+        &fee_payer,
+        &with_compute_budget(priority_fee.as_ref(), instructions),
+        &lookup_tables,
+        client_blockhash,
+    )
+    .map_err(|_| AppError::SerializationError)?;
+
+    // Get the number of required signatures before moving v0_message
+    let num_required_signatures = v0_message.header.num_required_signatures as usize;
+
+    // Create a versioned transaction with placeholder signatures for required signers
+    // (will be replaced by the wallet)
+    let mut versioned_transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); num_required_signatures],
+        message: VersionedMessage::V0(v0_message),
+    };
+
+    // Sign as fee payer if the transaction is sponsored by the server
+    state
+        .co_sign(
+            "empty-cb",
+            0,
+            std::slice::from_mut(&mut versioned_transaction),
+        )
+        .await?;
+
+    // Serialize the transaction to base64
+    let serialized_transaction = match bincode::serialize(&versioned_transaction) {
+        Ok(bytes) => BASE64_STANDARD.encode(bytes),
+        Err(_) => return Err(AppError::SerializationError),
+    };
+
+    println!("✅ Transaction created successfully");
+
+    Ok(Json(TransactionResponse {
+        transaction: serialized_transaction,
+        required_signers: missing_signers(&versioned_transaction),
+        message: if close {
+            "Empty and close CB transaction created successfully".to_string()
+        } else {
+            "Empty CB transaction created successfully".to_string()
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::routes::test_util,
+        solana_sdk::{pubkey::Pubkey, signer::Signer},
+        spl_token_2022::{
+            extension::{
+                confidential_transfer::instruction::ConfidentialTransferInstruction,
+                BaseStateWithExtensionsMut, StateWithExtensionsMut,
+            },
+            instruction::{decode_instruction_type, TokenInstruction},
+            state::Account,
+        },
+    };
+
+    // Builds token account data whose pending balance was applied, holding `amount`
+    // non-confidential tokens, and lets the test tweak its confidential transfer state
+    fn token_account_data(
+        owner: &Pubkey,
+        available_balance: u64,
+        amount: u64,
+        update: impl FnOnce(&mut ConfidentialTransferAccount),
+    ) -> String {
+        let data = test_util::confidential_token_account_data(
+            owner,
+            &Pubkey::new_unique(),
+            available_balance,
+            0,
+            0,
+        );
+        let mut data = BASE64_STANDARD.decode(data).unwrap();
+        let mut state = StateWithExtensionsMut::<Account>::unpack(&mut data).unwrap();
+        state.base.amount = amount;
+        state.pack_base();
+        let extension = state
+            .get_extension_mut::<ConfidentialTransferAccount>()
+            .unwrap();
+        extension.pending_balance_lo = EncryptedBalance::zeroed();
+        extension.pending_balance_hi = EncryptedBalance::zeroed();
+        update(extension);
+        BASE64_STANDARD.encode(data)
+    }
+
+    fn empty_request(token_account_data: String, close_account: bool) -> EmptyCbRequest {
+        EmptyCbRequest {
+            elgamal_signature: test_util::elgamal_signature(),
+            aes_signature: test_util::aes_signature(),
+            token_account_data: Some(token_account_data),
+            token_account_address: None,
+            close_account: Some(close_account),
+            multisig: None,
+            multisig_signers: None,
+            fee_payer: None,
+            latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+            address_lookup_tables: None,
+            priority_fee: None,
+        }
+    }
+
+    async fn empty(request: EmptyCbRequest) -> Result<TransactionResponse, AppError> {
+        empty_cb(State(AppState::default()), Json(request))
+            .await
+            .map(|Json(response)| response)
+    }
+
+    #[tokio::test]
+    async fn test_empty_rejects_unapplied_pending_balance() {
+        let owner = Pubkey::new_unique();
+
+        let pending_credits = token_account_data(&owner, 0, 0, |extension| {
+            extension.pending_balance_credit_counter = 1.into();
+        });
+        assert!(matches!(
+            empty(empty_request(pending_credits, false)).await,
+            Err(AppError::PendingBalanceNotEmpty)
+        ));
+
+        // A pending balance of zero still has to be applied before the account is emptied
+        let pending_ciphertext =
+            test_util::confidential_token_account_data(&owner, &Pubkey::new_unique(), 0, 0, 0);
+        assert!(matches!(
+            empty(empty_request(pending_ciphertext, false)).await,
+            Err(AppError::PendingBalanceNotEmpty)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_empty_rejects_remaining_balances() {
+        let owner = Pubkey::new_unique();
+
+        let available_balance = token_account_data(&owner, 100, 0, |_| {});
+        assert!(matches!(
+            empty(empty_request(available_balance, false)).await,
+            Err(AppError::AvailableBalanceNotEmpty)
+        ));
+
+        // Non-confidential tokens only prevent closing the account
+        let non_confidential_balance = token_account_data(&owner, 0, 5, |_| {});
+        assert!(matches!(
+            empty(empty_request(non_confidential_balance.clone(), true)).await,
+            Err(AppError::NonConfidentialBalanceNotEmpty)
+        ));
+        assert!(empty(empty_request(non_confidential_balance, false))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_empty_and_close_returns_the_rent_to_the_owner() {
+        let owner = Pubkey::new_unique();
+        let (state, sponsor) = test_util::sponsored_state(AppState::default(), &["empty-cb"]);
+
+        let mut request = empty_request(token_account_data(&owner, 0, 0, |_| {}), true);
+        request.fee_payer = Some(sponsor.pubkey().to_string());
+        let Json(response) = empty_cb(State(state), Json(request)).await.unwrap();
+
+        // The zero ciphertext proof directly follows the instruction reading it
+        let instructions = test_util::decode_transaction(&response.transaction);
+        let program_ids = instructions
+            .iter()
+            .map(|(program_id, _)| *program_id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            program_ids,
+            vec![
+                spl_token_2022::id(),
+                solana_zk_sdk::zk_elgamal_proof_program::id(),
+                spl_token_2022::id(),
+            ]
+        );
+        assert!(matches!(
+            decode_instruction_type::<ConfidentialTransferInstruction>(&instructions[0].1[1..]),
+            Ok(ConfidentialTransferInstruction::EmptyAccount)
+        ));
+        assert!(matches!(
+            TokenInstruction::unpack(&instructions[2].1),
+            Ok(TokenInstruction::CloseAccount)
+        ));
+
+        // The sponsor pays the fees, but the rent of the closed account goes to the owner
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&BASE64_STANDARD.decode(&response.transaction).unwrap()).unwrap();
+        let account_keys = transaction.message.static_account_keys();
+        let close_instruction = &transaction.message.instructions()[2];
+        assert_eq!(account_keys[0], sponsor.pubkey());
+        assert_eq!(account_keys[close_instruction.accounts[1] as usize], owner);
+        assert_eq!(response.required_signers, vec![owner.to_string()]);
+    }
+}
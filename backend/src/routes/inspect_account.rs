@@ -0,0 +1,139 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{
+            ConfidentialTransferAccountDetails, InspectAccountRequest, InspectAccountResponse,
+        },
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    solana_sdk::pubkey::Pubkey,
+    spl_token_2022::{
+        extension::{
+            confidential_transfer::ConfidentialTransferAccount, BaseStateWithExtensions,
+            StateWithExtensionsOwned,
+        },
+        state::AccountState,
+    },
+};
+
+/// Handler for inspecting a token account
+///
+/// Returns every field of the base account state and of the `ConfidentialTransferAccount`
+/// extension, plus the list of extensions present on the account
+pub async fn inspect_account(
+    State(state): State<AppState>,
+    Json(request): Json<InspectAccountRequest>,
+) -> Result<Json<InspectAccountResponse>, AppError> {
+    println!("🔍 Starting inspect_account handler");
+
+    // Deserialize the account data
+    let token_account_info = {
+        let token_account_data = state
+            .account_data(
+                request.token_account_data.as_ref(),
+                request.token_account_address.as_ref(),
+                "token_account_data",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(token_account_data)?
+    };
+    println!(
+        "🧳 Unpacked token account info from owner {}",
+        token_account_info.base.owner
+    );
+
+    let base = &token_account_info.base;
+    let state = match base.state {
+        AccountState::Uninitialized => "uninitialized",
+        AccountState::Initialized => "initialized",
+        AccountState::Frozen => "frozen",
+    };
+
+    let extensions = token_account_info
+        .get_extension_types()?
+        .into_iter()
+        .map(|extension_type| format!("{:?}", extension_type))
+        .collect::<Vec<_>>();
+    println!("✅ Found extensions: {:?}", extensions);
+
+    let confidential_transfer_account = token_account_info
+        .get_extension::<ConfidentialTransferAccount>()
+        .ok()
+        .map(|extension| ConfidentialTransferAccountDetails {
+            approved: bool::from(extension.approved),
+            elgamal_pubkey: extension.elgamal_pubkey.to_string(),
+            allow_confidential_credits: bool::from(extension.allow_confidential_credits),
+            allow_non_confidential_credits: bool::from(extension.allow_non_confidential_credits),
+            pending_balance_credit_counter: u64::from(extension.pending_balance_credit_counter)
+                .to_string(),
+            maximum_pending_balance_credit_counter: u64::from(
+                extension.maximum_pending_balance_credit_counter,
+            )
+            .to_string(),
+            expected_pending_balance_credit_counter: u64::from(
+                extension.expected_pending_balance_credit_counter,
+            )
+            .to_string(),
+            actual_pending_balance_credit_counter: u64::from(
+                extension.actual_pending_balance_credit_counter,
+            )
+            .to_string(),
+        });
+
+    println!("✅ Returning token account inspection");
+    Ok(Json(InspectAccountResponse {
+        mint: base.mint.to_string(),
+        owner: base.owner.to_string(),
+        amount: base.amount.to_string(),
+        state: state.to_string(),
+        delegate: Option::<Pubkey>::from(base.delegate).map(|delegate| delegate.to_string()),
+        delegated_amount: base.delegated_amount.to_string(),
+        close_authority: Option::<Pubkey>::from(base.close_authority)
+            .map(|close_authority| close_authority.to_string()),
+        is_native: base.is_native.is_some(),
+        has_confidential_transfer_account: confidential_transfer_account.is_some(),
+        confidential_transfer_account,
+        extensions,
+        message: "Token account inspected successfully".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::routes::test_util};
+
+    #[tokio::test]
+    async fn test_inspect_confidential_account() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account_data = test_util::confidential_token_account_data(&owner, &mint, 0, 0, 3);
+
+        let Json(response) = inspect_account(
+            State(AppState::default()),
+            Json(InspectAccountRequest {
+                token_account_data: Some(token_account_data),
+                token_account_address: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.mint, mint.to_string());
+        assert_eq!(response.owner, owner.to_string());
+        assert_eq!(response.state, "initialized");
+        assert!(response.has_confidential_transfer_account);
+        assert_eq!(response.extensions, vec!["ConfidentialTransferAccount"]);
+
+        let confidential_transfer_account = response.confidential_transfer_account.unwrap();
+        assert!(confidential_transfer_account.approved);
+        assert_eq!(
+            confidential_transfer_account.elgamal_pubkey,
+            test_util::elgamal_keypair().pubkey().to_string()
+        );
+        assert_eq!(
+            confidential_transfer_account.pending_balance_credit_counter,
+            "3"
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{
+            ConfidentialTransferMintDetails, InspectMintRequest, InspectMintResponse,
+            TransferFeeDetails,
+        },
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    solana_sdk::pubkey::Pubkey,
+    solana_zk_sdk::encryption::pod::elgamal::PodElGamalPubkey,
+    spl_token_2022::extension::{
+        confidential_mint_burn::ConfidentialMintBurn,
+        confidential_transfer::ConfidentialTransferMint,
+        confidential_transfer_fee::ConfidentialTransferFeeConfig, pausable::PausableConfig,
+        transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType,
+        StateWithExtensionsOwned,
+    },
+};
+
+/// Handler for inspecting a mint
+///
+/// Describes which confidential operations the mint supports, so that clients can disable the
+/// actions it can't do instead of failing on chain
+pub async fn inspect_mint(
+    State(state): State<AppState>,
+    Json(request): Json<InspectMintRequest>,
+) -> Result<Json<InspectMintResponse>, AppError> {
+    println!("🔍 Starting inspect_mint handler");
+
+    // Deserialize the mint data
+    let mint_account_info = {
+        let mint_account_data = state
+            .account_data(
+                request.mint_token_account.as_ref(),
+                request.mint.as_ref(),
+                "mint_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(mint_account_data)?
+    };
+
+    let extension_types = mint_account_info.get_extension_types()?;
+    println!("✅ Found extensions: {:?}", extension_types);
+
+    let confidential_transfer_mint = mint_account_info
+        .get_extension::<ConfidentialTransferMint>()
+        .ok()
+        .map(|extension| ConfidentialTransferMintDetails {
+            authority: Option::<Pubkey>::from(extension.authority)
+                .map(|authority| authority.to_string()),
+            auto_approve_new_accounts: bool::from(extension.auto_approve_new_accounts),
+            auditor_elgamal_pubkey: Option::<PodElGamalPubkey>::from(
+                extension.auditor_elgamal_pubkey,
+            )
+            .map(|auditor| auditor.to_string()),
+        });
+
+    let confidential_transfer_fee_config = mint_account_info
+        .get_extension::<ConfidentialTransferFeeConfig>()
+        .ok();
+    let transfer_fee = mint_account_info
+        .get_extension::<TransferFeeConfig>()
+        .ok()
+        .map(|extension| TransferFeeDetails {
+            epoch: u64::from(extension.newer_transfer_fee.epoch).to_string(),
+            transfer_fee_basis_points: u16::from(
+                extension.newer_transfer_fee.transfer_fee_basis_points,
+            ),
+            maximum_fee: u64::from(extension.newer_transfer_fee.maximum_fee).to_string(),
+            confidential_transfer_fee_enabled: confidential_transfer_fee_config.is_some(),
+            withdraw_withheld_authority_elgamal_pubkey: confidential_transfer_fee_config.map(
+                |config| {
+                    config
+                        .withdraw_withheld_authority_elgamal_pubkey
+                        .to_string()
+                },
+            ),
+        });
+
+    // Extensions that make confidential transfers fail regardless of the account state
+    let mut blocking_extensions = Vec::new();
+    if extension_types.contains(&ExtensionType::NonTransferable) {
+        blocking_extensions.push(format!("{:?}", ExtensionType::NonTransferable));
+    }
+    if let Ok(pausable_config) = mint_account_info.get_extension::<PausableConfig>() {
+        if bool::from(pausable_config.paused) {
+            blocking_extensions.push(format!("{:?}", ExtensionType::Pausable));
+        }
+    }
+    if !blocking_extensions.is_empty() {
+        println!("⚠️ Blocking extensions: {:?}", blocking_extensions);
+    }
+
+    // A transfer fee without the confidential fee config can't be paid confidentially
+    let fee_supported = transfer_fee
+        .as_ref()
+        .is_none_or(|fee| fee.confidential_transfer_fee_enabled);
+    let supports_confidential_transfers =
+        confidential_transfer_mint.is_some() && blocking_extensions.is_empty() && fee_supported;
+
+    let confidential_mint_burn = mint_account_info
+        .get_extension::<ConfidentialMintBurn>()
+        .is_ok();
+    let supports_confidential_mint_burn = confidential_transfer_mint.is_some()
+        && confidential_mint_burn
+        && blocking_extensions.is_empty();
+
+    println!("✅ Returning mint inspection");
+    Ok(Json(InspectMintResponse {
+        mint_authority: Option::<Pubkey>::from(mint_account_info.base.mint_authority)
+            .map(|authority| authority.to_string()),
+        freeze_authority: Option::<Pubkey>::from(mint_account_info.base.freeze_authority)
+            .map(|authority| authority.to_string()),
+        supply: mint_account_info.base.supply.to_string(),
+        decimals: mint_account_info.base.decimals,
+        confidential_transfer_mint,
+        transfer_fee,
+        confidential_mint_burn,
+        blocking_extensions,
+        supports_confidential_transfers,
+        supports_confidential_mint_burn,
+        extensions: extension_types
+            .into_iter()
+            .map(|extension_type| format!("{:?}", extension_type))
+            .collect(),
+        message: "Mint inspected successfully".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::routes::test_util};
+
+    #[tokio::test]
+    async fn test_inspect_confidential_mint() {
+        let authority = Pubkey::new_unique();
+        let mint_token_account = test_util::confidential_mint_data(&authority, false);
+
+        let Json(response) = inspect_mint(
+            State(AppState::default()),
+            Json(InspectMintRequest {
+                mint_token_account: Some(mint_token_account),
+                mint: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.mint_authority, Some(authority.to_string()));
+        assert_eq!(response.decimals, 2);
+        assert!(response.supports_confidential_transfers);
+        assert!(!response.supports_confidential_mint_burn);
+        assert!(response.blocking_extensions.is_empty());
+
+        let confidential_transfer_mint = response.confidential_transfer_mint.unwrap();
+        assert_eq!(
+            confidential_transfer_mint.authority,
+            Some(authority.to_string())
+        );
+        assert!(confidential_transfer_mint.auto_approve_new_accounts);
+        assert_eq!(
+            confidential_transfer_mint.auditor_elgamal_pubkey,
+            Some(test_util::elgamal_keypair().pubkey().to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inspect_non_transferable_mint() {
+        let authority = Pubkey::new_unique();
+        let mint_token_account = test_util::confidential_mint_data(&authority, true);
+
+        let Json(response) = inspect_mint(
+            State(AppState::default()),
+            Json(InspectMintRequest {
+                mint_token_account: Some(mint_token_account),
+                mint: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert!(!response.supports_confidential_transfers);
+        assert_eq!(response.blocking_extensions, vec!["NonTransferable"]);
+    }
+}
@@ -0,0 +1,278 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{CreateLookupTableRequest, CreateLookupTableResponse},
+        routes::util::{missing_signers, parse_base58_pubkey, parse_fee_payer},
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    solana_address_lookup_table_interface::instruction::{
+        create_lookup_table, extend_lookup_table,
+    },
+    solana_sdk::{
+        message::{v0, VersionedMessage},
+        pubkey::Pubkey,
+        signature::Signature,
+        sysvar::{instructions, recent_blockhashes},
+        transaction::VersionedTransaction,
+    },
+    spl_associated_token_account::get_associated_token_address_with_program_id,
+};
+
+/// Maximum number of addresses added by a single extend instruction, so that each transaction
+/// stays within the packet size limit
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+
+/// Handler building the transactions that create (or extend) an address lookup table with the
+/// recurring accounts of a confidential token account owner
+///
+/// The owner's token account, the mint, the zk ElGamal proof program and the sysvars used by
+/// proof verification and durable nonces are always added, followed by any additional addresses.
+pub async fn create_lookup_table_cb(
+    State(state): State<AppState>,
+    Json(request): Json<CreateLookupTableRequest>,
+) -> Result<Json<CreateLookupTableResponse>, AppError> {
+    println!("🚀 Starting create_lookup_table_cb handler");
+
+    let owner = parse_base58_pubkey(&request.owner)?;
+    let mint = parse_base58_pubkey(&request.mint)?;
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), owner)?;
+    // The sponsor funds the table rent, so it keeps the authority of sponsored tables
+    let authority = if state.is_sponsor(&fee_payer) {
+        fee_payer
+    } else {
+        owner
+    };
+
+    let token_account =
+        get_associated_token_address_with_program_id(&owner, &mint, &spl_token_2022::id());
+    let mut addresses = vec![
+        token_account,
+        mint,
+        solana_zk_sdk::zk_elgamal_proof_program::id(),
+        instructions::id(),
+        recent_blockhashes::id(),
+    ];
+    for address in request.additional_addresses.iter().flatten() {
+        let address = parse_base58_pubkey(address)?;
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    println!("📋 {} addresses for the lookup table", addresses.len());
+
+    // Create the table in the first transaction, unless an existing table is extended
+    let (mut create_instruction, lookup_table_address) = match &request.lookup_table_address {
+        Some(lookup_table_address) => (None, parse_base58_pubkey(lookup_table_address)?),
+        None => {
+            let recent_slot = state.slot(request.recent_slot).await?;
+            let (instruction, lookup_table_address) =
+                create_lookup_table(authority, fee_payer, recent_slot);
+            (Some(instruction), lookup_table_address)
+        }
+    };
+    println!("✅ Lookup table address: {}", lookup_table_address);
+
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+
+    let mut transactions = addresses
+        .chunks(MAX_ADDRESSES_PER_EXTEND)
+        .map(|chunk| {
+            let mut instructions = create_instruction.take().into_iter().collect::<Vec<_>>();
+            instructions.push(extend_lookup_table(
+                lookup_table_address,
+                authority,
+                Some(fee_payer),
+                chunk.to_vec(),
+            ));
+
+            let message =
+                v0::Message::try_compile(&fee_payer, &instructions, &[], client_blockhash)?;
+
+            // Placeholder signatures for the fee payer and the table authority
+            Ok(VersionedTransaction {
+                signatures: vec![
+                    Signature::default();
+                    message.header.num_required_signatures as usize
+                ],
+                message: VersionedMessage::V0(message),
+            })
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    // Sign as fee payer if the transactions are sponsored by the server
+    state
+        .co_sign("create-lookup-table", 0, &mut transactions)
+        .await?;
+
+    let required_signers = transactions.iter().map(missing_signers).collect();
+    let transactions = transactions
+        .iter()
+        .map(|transaction| match bincode::serialize(transaction) {
+            Ok(bytes) => Ok(BASE64_STANDARD.encode(bytes)),
+            Err(_) => Err(AppError::SerializationError),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    println!(
+        "✅ Created {} lookup table transactions",
+        transactions.len()
+    );
+
+    Ok(Json(CreateLookupTableResponse {
+        transactions,
+        required_signers,
+        lookup_table_address: lookup_table_address.to_string(),
+        addresses: addresses.iter().map(Pubkey::to_string).collect(),
+        message: format!(
+            "Lookup table {} transactions created successfully",
+            lookup_table_address
+        ),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{models::AddressLookupTableRequest, routes::test_util},
+        solana_address_lookup_table_interface::state::{
+            LookupTableMeta, ProgramState, LOOKUP_TABLE_META_SIZE,
+        },
+        solana_sdk::{
+            hash::Hash,
+            instruction::{AccountMeta, Instruction},
+            packet::PACKET_DATA_SIZE,
+            signer::Signer,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_create_lookup_table_splits_addresses_across_transactions() {
+        let owner = Pubkey::new_unique();
+        let additional_addresses = (0..30)
+            .map(|_| Pubkey::new_unique().to_string())
+            .collect::<Vec<_>>();
+
+        let Json(response) = create_lookup_table_cb(
+            State(AppState::default()),
+            Json(CreateLookupTableRequest {
+                owner: owner.to_string(),
+                mint: Pubkey::new_unique().to_string(),
+                fee_payer: None,
+                lookup_table_address: None,
+                additional_addresses: Some(additional_addresses),
+                recent_slot: Some(1),
+                latest_blockhash: Some(Hash::new_unique().to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.addresses.len(), 35);
+        assert_eq!(response.transactions.len(), 2);
+        assert_eq!(
+            response.lookup_table_address,
+            create_lookup_table(owner, owner, 1).1.to_string()
+        );
+        for (transaction, required_signers) in
+            response.transactions.iter().zip(&response.required_signers)
+        {
+            let bytes = BASE64_STANDARD.decode(transaction).unwrap();
+            assert!(bytes.len() <= PACKET_DATA_SIZE);
+            assert_eq!(required_signers, &vec![owner.to_string()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_lookup_table_keeps_sponsored_tables_under_the_sponsor() {
+        let (state, sponsor) =
+            test_util::sponsored_state(AppState::default(), &["create-lookup-table"]);
+        let owner = Pubkey::new_unique();
+
+        let Json(response) = create_lookup_table_cb(
+            State(state),
+            Json(CreateLookupTableRequest {
+                owner: owner.to_string(),
+                mint: Pubkey::new_unique().to_string(),
+                fee_payer: Some(sponsor.pubkey().to_string()),
+                lookup_table_address: None,
+                additional_addresses: None,
+                recent_slot: Some(1),
+                latest_blockhash: Some(Hash::new_unique().to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+
+        // The owner can't close the table to take the rent paid by the sponsor
+        assert_eq!(
+            response.lookup_table_address,
+            create_lookup_table(sponsor.pubkey(), sponsor.pubkey(), 1)
+                .1
+                .to_string()
+        );
+        assert_eq!(response.required_signers, vec![Vec::<String>::new()]);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_tables_shorten_compiled_messages() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account =
+            get_associated_token_address_with_program_id(&owner, &mint, &spl_token_2022::id());
+        let lookup_table_address = Pubkey::new_unique();
+
+        // The lookup table contents are fetched over RPC when the request omits them
+        let lookup_table_data = {
+            let mut data = vec![0; LOOKUP_TABLE_META_SIZE];
+            bincode::serialize_into(
+                &mut data[..],
+                &ProgramState::LookupTable(LookupTableMeta::default()),
+            )
+            .unwrap();
+            data.extend_from_slice(token_account.as_ref());
+            data.extend_from_slice(mint.as_ref());
+            BASE64_STANDARD.encode(data)
+        };
+        let state =
+            test_util::mock_rpc_state(vec![(lookup_table_address, lookup_table_data)]).await;
+
+        let lookup_tables = state
+            .lookup_tables(Some(&vec![AddressLookupTableRequest {
+                address: lookup_table_address.to_string(),
+                addresses: None,
+            }]))
+            .await
+            .unwrap();
+        assert_eq!(lookup_tables[0].addresses, vec![token_account, mint]);
+
+        let instruction = Instruction::new_with_bytes(
+            spl_token_2022::id(),
+            &[],
+            vec![
+                AccountMeta::new(token_account, false),
+                AccountMeta::new_readonly(mint, false),
+            ],
+        );
+        let with_lookup_tables = v0::Message::try_compile(
+            &owner,
+            std::slice::from_ref(&instruction),
+            &lookup_tables,
+            Hash::default(),
+        )
+        .unwrap();
+        let without_lookup_tables =
+            v0::Message::try_compile(&owner, &[instruction], &[], Hash::default()).unwrap();
+
+        assert_eq!(with_lookup_tables.address_table_lookups.len(), 1);
+        assert!(
+            VersionedMessage::V0(with_lookup_tables).serialize().len()
+                < VersionedMessage::V0(without_lookup_tables)
+                    .serialize()
+                    .len()
+        );
+    }
+}
@@ -2,7 +2,7 @@ use {
     crate::{
         errors::AppError,
         models::{TransactionRequest, TransactionResponse},
-        routes::util::parse_base58_pubkey,
+        routes::util::{missing_signers, parse_base58_pubkey},
     },
     axum::extract::Json,
     base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
@@ -70,6 +70,7 @@ pub async fn create_memo_transaction(
 
     Ok(Json(TransactionResponse {
         transaction: serialized_transaction,
+        required_signers: missing_signers(&versioned_transaction),
         message: "Transaction created successfully".to_string(),
     }))
 }
@@ -0,0 +1,448 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{MintCbRequest, MultiTransactionResponse},
+        routes::{
+            nonce::TransactionLifetime,
+            packer::pack_transactions,
+            priority_fee::PriorityFee,
+            util::{
+                get_zk_proof_context_state_account_creation_instructions, missing_signers,
+                parse_fee_payer, proof_context_state_space,
+            },
+        },
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    bincode,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+        signer::Signer,
+    },
+    solana_zk_sdk::{
+        encryption::{elgamal::ElGamalPubkey, pod::elgamal::PodElGamalPubkey},
+        zk_elgamal_proof_program::instruction::{close_context_state, ContextStateInfo},
+    },
+    spl_associated_token_account::get_associated_token_address_with_program_id,
+    spl_token_2022::{
+        extension::{
+            confidential_mint_burn::{
+                account_info::SupplyAccountInfo, instruction::confidential_mint_with_split_proofs,
+                ConfidentialMintBurn,
+            },
+            confidential_transfer::{ConfidentialTransferAccount, ConfidentialTransferMint},
+            BaseStateWithExtensions, StateWithExtensionsOwned,
+        },
+        solana_zk_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
+    },
+    spl_token_confidential_transfer_proof_extraction::instruction::ProofLocation,
+    spl_token_confidential_transfer_proof_generation::mint::MintProofData,
+};
+
+/// Handler for the mint-cb endpoint
+///
+/// This endpoint creates the transactions to mint tokens directly into the pending balance of a
+/// confidential token account (requires the `ConfidentialMintBurn` extension on the mint)
+pub async fn mint_cb(
+    State(state): State<AppState>,
+    Json(request): Json<MintCbRequest>,
+) -> Result<Json<MultiTransactionResponse>, AppError> {
+    println!("📝 Processing mint-cb request");
+
+    // Decode amount from request
+    let mint_amount = request
+        .amount
+        .parse::<u64>()
+        .map_err(|_| AppError::InvalidAmount)?;
+    println!("✅ Successfully decoded amount: {}", mint_amount);
+
+    // Decode recipient token account data from request
+    println!("📦 Decoding recipient token account data from request");
+    let recipient_token_account_info = {
+        let recipient_token_account_data = state
+            .account_data(
+                request.recipient_token_account.as_ref(),
+                request.recipient_token_account_address.as_ref(),
+                "recipient_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(
+            recipient_token_account_data,
+        )?
+    };
+    println!(
+        "✅ Successfully decoded recipient token account data from owner {}",
+        recipient_token_account_info.base.owner
+    );
+
+    let mint = recipient_token_account_info.base.mint;
+
+    // Decode mint account data from request
+    println!("📦 Decoding mint account data from request");
+    let mint_account_info = {
+        let mint_account_data = state
+            .mint_data(
+                request.mint_token_account.as_ref(),
+                &mint,
+                "mint_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(mint_account_data)?
+    };
+
+    // Only the mint authority can mint, and it also signs for the proof accounts
+    let mint_authority =
+        Option::<Pubkey>::from(mint_account_info.base.mint_authority).ok_or_else(|| {
+            println!("⛔️ Mint {} has no mint authority", mint);
+            AppError::MintAuthorityNotSet
+        })?;
+    println!("✅ Mint authority: {}", mint_authority);
+
+    let recipient_token_account = get_associated_token_address_with_program_id(
+        &recipient_token_account_info.base.owner,
+        &mint,
+        &spl_token_2022::id(),
+    );
+    println!(
+        "✅ Calculated recipient token account address: {}",
+        recipient_token_account
+    );
+
+    let recipient_elgamal_pubkey: ElGamalPubkey = recipient_token_account_info
+        .get_extension::<ConfidentialTransferAccount>()?
+        .elgamal_pubkey
+        .try_into()?;
+
+    let auditor_elgamal_pubkey_option: Option<ElGamalPubkey> = Option::<PodElGamalPubkey>::from(
+        mint_account_info
+            .get_extension::<ConfidentialTransferMint>()?
+            .auditor_elgamal_pubkey,
+    )
+    .map(|pod| pod.try_into())
+    .transpose()?;
+
+    // ConfidentialMintBurn extension information needed to create proof data
+    let supply_account_info =
+        SupplyAccountInfo::new(mint_account_info.get_extension::<ConfidentialMintBurn>()?);
+
+    // Create the supply ElGamal keypair in a temporary scope
+    let supply_elgamal_keypair = {
+        println!(
+            "🔐 Decoding ElGamal signature: {}",
+            request.elgamal_signature
+        );
+        let decoded_elgamal_signature = BASE64_STANDARD.decode(&request.elgamal_signature)?;
+
+        // Create signature directly from bytes
+        let elgamal_signature = Signature::try_from(decoded_elgamal_signature.as_slice())
+            .map_err(|_| AppError::SerializationError)?;
+
+        ElGamalKeypair::new_from_signature(&elgamal_signature)
+            .map_err(|_| AppError::SerializationError)?
+    };
+    println!("✅ Supply ElGamal keypair created successfully");
+
+    // Create the supply AES key in a temporary scope
+    let supply_aes_key = {
+        println!("🔐 Decoding AES signature: {}", request.aes_signature);
+        let decoded_aes_signature = BASE64_STANDARD.decode(&request.aes_signature)?;
+
+        // Create signature directly from bytes
+        let aes_signature = Signature::try_from(decoded_aes_signature.as_slice())
+            .map_err(|_| AppError::SerializationError)?;
+
+        AeKey::new_from_signature(&aes_signature).map_err(|_| AppError::SerializationError)?
+    };
+    println!("✅ Supply AES key created successfully");
+
+    // The current supply is needed to prove the new supply stays in range
+    let current_supply =
+        supply_account_info.decrypted_current_supply(&supply_aes_key, &supply_elgamal_keypair)?;
+    println!(
+        "✅ Decrypted current confidential supply: {}",
+        current_supply
+    );
+
+    // Generate proof data
+    let MintProofData {
+        equality_proof_data,
+        ciphertext_validity_proof_data_with_ciphertext,
+        range_proof_data,
+    } = supply_account_info.generate_split_mint_proof_data(
+        mint_amount,
+        current_supply,
+        &supply_elgamal_keypair,
+        &recipient_elgamal_pubkey,
+        auditor_elgamal_pubkey_option.as_ref(),
+    )?;
+
+    // Use the proof account rents from the request, or the rent-exempt minimums
+    let range_proof_rent = state
+        .rent(
+            request.range_proof_rent.as_ref(),
+            proof_context_state_space(&range_proof_data),
+        )
+        .await?;
+    let equality_proof_rent = state
+        .rent(
+            request.equality_proof_rent.as_ref(),
+            proof_context_state_space(&equality_proof_data),
+        )
+        .await?;
+    let ciphertext_validity_proof_rent = state
+        .rent(
+            request.ciphertext_validity_proof_rent.as_ref(),
+            proof_context_state_space(&ciphertext_validity_proof_data_with_ciphertext.proof_data),
+        )
+        .await?;
+
+    let new_decryptable_supply = supply_account_info
+        .new_decryptable_supply(mint_amount, &supply_elgamal_keypair, &supply_aes_key)?
+        .into();
+
+    // Pays for every transaction and funds the proof accounts (defaults to the mint authority)
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), mint_authority)?;
+
+    // "Authority" for the proof accounts (to close the accounts after the mint)
+    let context_state_authority = &fee_payer;
+
+    // Generate addresses for proof accounts
+    let equality_proof_context_state_account = Keypair::new();
+    let ciphertext_validity_proof_context_state_account = Keypair::new();
+    let range_proof_context_state_account = Keypair::new();
+
+    // Range Proof Instructions------------------------------------------------------------------------------
+    let (range_create_ix, range_verify_ix) =
+        get_zk_proof_context_state_account_creation_instructions(
+            &fee_payer,
+            &range_proof_context_state_account.pubkey(),
+            context_state_authority,
+            &range_proof_data,
+            range_proof_rent,
+        )?;
+
+    // Equality Proof Instructions---------------------------------------------------------------------------
+    let (equality_create_ix, equality_verify_ix) =
+        get_zk_proof_context_state_account_creation_instructions(
+            &fee_payer,
+            &equality_proof_context_state_account.pubkey(),
+            context_state_authority,
+            &equality_proof_data,
+            equality_proof_rent,
+        )?;
+
+    // Ciphertext Validity Proof Instructions ----------------------------------------------------------------
+    let (cv_create_ix, cv_verify_ix) = get_zk_proof_context_state_account_creation_instructions(
+        &fee_payer,
+        &ciphertext_validity_proof_context_state_account.pubkey(),
+        context_state_authority,
+        &ciphertext_validity_proof_data_with_ciphertext.proof_data,
+        ciphertext_validity_proof_rent,
+    )?;
+
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+    let priority_fee = PriorityFee::from_request(request.priority_fee.as_ref())?;
+
+    // Allocate all proof accounts, then verify the range proof on its own (because it's the
+    // largest) and the remaining proofs together
+    let mut groups = vec![
+        vec![range_create_ix],
+        vec![equality_create_ix],
+        vec![cv_create_ix],
+        vec![range_verify_ix],
+        vec![equality_verify_ix, cv_verify_ix],
+    ];
+
+    // Execute the confidential mint
+    groups.push(confidential_mint_with_split_proofs(
+        &spl_token_2022::id(),
+        &recipient_token_account,
+        &mint,
+        Some(*supply_elgamal_keypair.pubkey()),
+        &ciphertext_validity_proof_data_with_ciphertext.ciphertext_lo,
+        &ciphertext_validity_proof_data_with_ciphertext.ciphertext_hi,
+        &mint_authority,
+        &[],
+        ProofLocation::ContextStateAccount(&equality_proof_context_state_account.pubkey()),
+        ProofLocation::ContextStateAccount(
+            &ciphertext_validity_proof_context_state_account.pubkey(),
+        ),
+        ProofLocation::ContextStateAccount(&range_proof_context_state_account.pubkey()),
+        &new_decryptable_supply,
+    )?);
+
+    // Close Proof Accounts ----------------------------------
+    // Lamports from the closed proof accounts will be sent to this account
+    let destination_account = &fee_payer;
+    let proof_account_keypairs = [
+        &equality_proof_context_state_account,
+        &ciphertext_validity_proof_context_state_account,
+        &range_proof_context_state_account,
+    ];
+    groups.extend(proof_account_keypairs.iter().map(|context_state_account| {
+        vec![close_context_state(
+            ContextStateInfo {
+                context_state_account: &context_state_account.pubkey(),
+                context_state_authority,
+            },
+            destination_account,
+        )]
+    }));
+
+    // Fill as few transactions as possible
+    let transactions = pack_transactions(
+        &fee_payer,
+        groups,
+        &proof_account_keypairs,
+        &mut TransactionLifetime::with_blockhash(client_blockhash),
+        &lookup_tables,
+        priority_fee.as_ref(),
+    )?;
+    let required_signers = transactions.iter().map(missing_signers).collect();
+    let response = MultiTransactionResponse {
+        transactions: transactions
+            .into_iter()
+            .enumerate()
+            .map(|(i, tx)| {
+                let serialized_transaction = match bincode::serialize(&tx) {
+                    Ok(bytes) => BASE64_STANDARD.encode(bytes),
+                    Err(_) => return Err(AppError::SerializationError),
+                };
+                println!("✅ Successfully serialized transaction {}", i + 1);
+
+                Ok(serialized_transaction)
+            })
+            .collect::<Result<Vec<String>, AppError>>()?,
+        required_signers,
+        flow_nonce: None,
+        flow_nonce_count: None,
+        message: "MultiTransaction for confidential mint created successfully".to_string(),
+    };
+
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::routes::test_util,
+        solana_sdk_ids::system_program,
+        solana_zk_sdk::zk_elgamal_proof_program::{
+            self,
+            instruction::ProofInstruction,
+            proof_data::{BatchedRangeProofContext, BatchedRangeProofU128Data, ZkProofData},
+        },
+        spl_token_2022::{
+            extension::confidential_mint_burn::instruction::{
+                ConfidentialMintBurnInstruction, MintInstructionData,
+            },
+            instruction::{decode_instruction_data, decode_instruction_type},
+            solana_zk_sdk::encryption::auth_encryption::AeCiphertext,
+        },
+    };
+
+    // Names an instruction of the flow, to check their order
+    fn step((program_id, data): &(Pubkey, Vec<u8>)) -> &'static str {
+        if *program_id == system_program::id() {
+            "create"
+        } else if *program_id == zk_elgamal_proof_program::id() {
+            match ProofInstruction::instruction_type(data) {
+                Some(ProofInstruction::VerifyBatchedRangeProofU128) => "verify range",
+                Some(ProofInstruction::VerifyCiphertextCommitmentEquality) => "verify equality",
+                Some(ProofInstruction::VerifyBatchedGroupedCiphertext3HandlesValidity) => {
+                    "verify validity"
+                }
+                Some(ProofInstruction::CloseContextState) => "close",
+                _ => "other proof",
+            }
+        } else if *program_id == spl_token_2022::id()
+            && matches!(
+                decode_instruction_type::<ConfidentialMintBurnInstruction>(&data[1..]),
+                Ok(ConfidentialMintBurnInstruction::Mint)
+            )
+        {
+            "mint"
+        } else {
+            "other"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mint_verifies_the_range_proof_before_minting() {
+        let authority = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let Json(response) = mint_cb(
+            State(AppState::default()),
+            Json(MintCbRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                aes_signature: test_util::aes_signature(),
+                recipient_token_account: Some(test_util::confidential_token_account_data(
+                    &recipient, &mint, 0, 0, 0,
+                )),
+                recipient_token_account_address: None,
+                mint_token_account: Some(test_util::confidential_mint_burn_mint_data(
+                    &authority, 1_000,
+                )),
+                amount: "100".to_string(),
+                fee_payer: None,
+                latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                equality_proof_rent: None,
+                ciphertext_validity_proof_rent: None,
+                range_proof_rent: None,
+                address_lookup_tables: None,
+                priority_fee: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        let transactions = response
+            .transactions
+            .iter()
+            .map(|transaction| test_util::decode_transaction(transaction))
+            .collect::<Vec<_>>();
+        let steps = transactions
+            .iter()
+            .map(|instructions| instructions.iter().map(step).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            vec![
+                vec!["create", "create", "create"],
+                vec!["verify range"],
+                vec!["verify equality", "verify validity"],
+                vec!["mint", "close", "close", "close"],
+            ]
+        );
+
+        // The range proof is the largest, so its transaction carries nothing else
+        let (_, range_proof_instruction_data) = &transactions[1][0];
+        ProofInstruction::proof_data::<BatchedRangeProofU128Data, BatchedRangeProofContext>(
+            range_proof_instruction_data,
+        )
+        .unwrap()
+        .verify_proof()
+        .unwrap();
+
+        // The mint leaves the supply the authority can decrypt
+        let (_, mint_instruction_data) = &transactions[3][0];
+        let mint_data =
+            decode_instruction_data::<MintInstructionData>(&mint_instruction_data[1..]).unwrap();
+        let new_decryptable_supply =
+            AeCiphertext::try_from(mint_data.new_decryptable_supply).unwrap();
+        assert_eq!(
+            test_util::aes_key().decrypt(&new_decryptable_supply),
+            Some(1_100)
+        );
+    }
+}
@@ -1,28 +1,66 @@
 pub mod apply;
+pub mod approve_account;
 pub mod audit_transaction;
+pub mod batch_transfer;
+pub mod bundle;
+pub mod burn;
+pub mod check_recipient;
+pub mod configure_credits;
 pub mod create_cb_ata;
 pub mod create_test_token;
 pub mod decrypt;
 pub mod deposit;
+pub mod empty;
 pub mod health;
+pub mod inspect_account;
+pub mod inspect_mint;
+pub mod lookup_table;
 pub mod memo_transaction;
+pub mod mint;
+pub mod nonce;
+pub mod packer;
+pub mod priority_fee;
+pub mod proof_accounts;
 pub mod reveal_elgamal_pubkey;
+pub mod sponsor;
+#[cfg(test)]
+pub mod test_util;
 pub mod transfer;
 pub mod transfer_space;
+pub mod transfer_with_fee;
+pub mod transfer_with_fee_space;
+pub mod update_mint;
 pub mod util;
 pub mod withdraw;
 pub mod withdraw_space;
 
 pub use apply::apply_cb;
+pub use approve_account::approve_account_cb;
 pub use audit_transaction::audit_transaction_cb;
+pub use batch_transfer::batch_transfer_cb;
+pub use bundle::submit_bundle_cb;
+pub use burn::burn_cb;
+pub use check_recipient::check_recipient_cb;
+pub use configure_credits::configure_credits_cb;
 pub use create_cb_ata::create_cb_ata;
 pub use create_test_token::create_test_token_cb;
 pub use decrypt::decrypt_cb;
 pub use deposit::deposit_cb;
+pub use empty::empty_cb;
 pub use health::{health_check, version_check};
+pub use inspect_account::inspect_account;
+pub use inspect_mint::inspect_mint;
+pub use lookup_table::create_lookup_table_cb;
 pub use memo_transaction::create_memo_transaction;
+pub use mint::mint_cb;
+pub use nonce::create_nonce_accounts_cb;
+pub use proof_accounts::recover_proof_accounts_cb;
 pub use reveal_elgamal_pubkey::reveal_elgamal_pubkey_cb;
+pub use sponsor::sponsor_info;
 pub use transfer::transfer_cb;
 pub use transfer_space::transfer_cb_space;
+pub use transfer_with_fee::transfer_with_fee_cb;
+pub use transfer_with_fee_space::transfer_with_fee_cb_space;
+pub use update_mint::update_mint_cb;
 pub use withdraw::withdraw_cb;
 pub use withdraw_space::withdraw_cb_space;
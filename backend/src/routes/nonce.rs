@@ -0,0 +1,416 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{CreateNonceAccountsRequest, CreateNonceAccountsResponse},
+        routes::util::{missing_signers, parse_base58_pubkey, partially_signed_transaction},
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    solana_message::AddressLookupTableAccount,
+    solana_sdk::{
+        hash::Hash,
+        instruction::Instruction,
+        message::v0,
+        nonce::state::{State as NonceState, Versions},
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+        system_instruction,
+    },
+};
+
+/// Maximum number of nonce accounts created by a single transaction
+pub const MAX_NONCE_ACCOUNTS: usize = 5;
+
+/// Maximum number of nonce accounts created by a single request, enough for a batch of
+/// `MAX_BATCH_TRANSFERS` transfers, which takes about five transactions per transfer
+pub const MAX_NONCE_ACCOUNTS_PER_REQUEST: usize = 600;
+
+/// What the transactions of a multi-transaction flow are compiled against
+///
+/// A durable nonce can only be used by one transaction, so a flow needs one nonce account per
+/// transaction, all controlled by the same authority. Every transaction then begins with
+/// `advance_nonce_account`, which leaves no room for a range proof in the instruction data: flows
+/// with durable nonces verify their range proofs from record accounts instead.
+pub struct TransactionLifetime {
+    blockhash: Hash,
+    durable_nonces: Option<DurableNonces>,
+}
+
+struct DurableNonces {
+    authority: Pubkey,
+    nonces: std::vec::IntoIter<(Pubkey, Hash)>, // Unused nonce accounts and their nonce values
+}
+
+impl TransactionLifetime {
+    /// Uses the durable nonce accounts of the request if there are any, and the latest blockhash
+    /// otherwise
+    pub async fn from_request(
+        state: &AppState,
+        latest_blockhash: Option<&String>,
+        nonce_accounts: Option<&Vec<String>>,
+        nonce_account_data: Option<&Vec<String>>,
+        nonce_authority: Option<&String>,
+        default_nonce_authority: Pubkey,
+    ) -> Result<Self, AppError> {
+        let blockhash = state.latest_blockhash(latest_blockhash).await?;
+        let Some(nonce_accounts) = nonce_accounts else {
+            return Ok(Self::with_blockhash(blockhash));
+        };
+
+        if let Some(nonce_account_data) =
+            nonce_account_data.filter(|data| data.len() != nonce_accounts.len())
+        {
+            println!(
+                "⛔️ Got data for {} of {} nonce accounts",
+                nonce_account_data.len(),
+                nonce_accounts.len()
+            );
+            return Err(AppError::NonceAccountCountMismatch {
+                expected: nonce_accounts.len(),
+                provided: nonce_account_data.len(),
+            });
+        }
+
+        let authority = match nonce_authority {
+            Some(nonce_authority) => parse_base58_pubkey(nonce_authority)?,
+            None => default_nonce_authority,
+        };
+
+        let mut nonces = Vec::with_capacity(nonce_accounts.len());
+        for (index, nonce_account) in nonce_accounts.iter().enumerate() {
+            let data = state
+                .account_data(
+                    nonce_account_data.map(|data| &data[index]),
+                    Some(nonce_account),
+                    "nonce_account_data",
+                )
+                .await?;
+            let nonce = parse_nonce(&data, &authority)?;
+            println!("✅ Nonce account {}: nonce {}", nonce_account, nonce);
+
+            nonces.push((parse_base58_pubkey(nonce_account)?, nonce));
+        }
+
+        Ok(Self {
+            blockhash,
+            durable_nonces: Some(DurableNonces {
+                authority,
+                nonces: nonces.into_iter(),
+            }),
+        })
+    }
+
+    /// Compiles every transaction against the blockhash
+    pub fn with_blockhash(blockhash: Hash) -> Self {
+        Self {
+            blockhash,
+            durable_nonces: None,
+        }
+    }
+
+    /// Whether the transactions of the flow advance durable nonces
+    pub fn is_durable(&self) -> bool {
+        self.durable_nonces.is_some()
+    }
+
+    /// Number of durable nonces provided, if the flow uses durable nonces
+    pub fn nonce_count(&self) -> Option<usize> {
+        self.durable_nonces
+            .as_ref()
+            .map(|durable_nonces| durable_nonces.nonces.len())
+    }
+
+    /// Compiles the message of the next transaction in the flow. With durable nonces, the
+    /// transaction begins with `advance_nonce_account` and uses the next unused nonce.
+    pub fn compile(
+        &mut self,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<v0::Message, AppError> {
+        let Some(DurableNonces { authority, nonces }) = &mut self.durable_nonces else {
+            return self.compile_with_blockhash(payer, instructions, lookup_tables);
+        };
+        let (nonce_account, nonce) = nonces.next().ok_or_else(|| {
+            println!("⛔️ No nonce account left for the next durable transaction");
+            AppError::NotEnoughNonceAccounts
+        })?;
+
+        let mut nonce_instructions = vec![system_instruction::advance_nonce_account(
+            &nonce_account,
+            authority,
+        )];
+        nonce_instructions.extend_from_slice(instructions);
+
+        Ok(v0::Message::try_compile(
+            payer,
+            &nonce_instructions,
+            lookup_tables,
+            nonce,
+        )?)
+    }
+
+    // Compiles a message against the latest blockhash
+    fn compile_with_blockhash(
+        &self,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<v0::Message, AppError> {
+        Ok(v0::Message::try_compile(
+            payer,
+            instructions,
+            lookup_tables,
+            self.blockhash,
+        )?)
+    }
+
+    /// Compiles a message as large as the one of a transaction of the flow, durable nonce advance
+    /// included, without using up a nonce
+    pub fn compile_for_size(
+        &self,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<v0::Message, AppError> {
+        match &self.durable_nonces {
+            Some(DurableNonces { authority, .. }) => {
+                // A nonce account outside the lookup tables, for the largest possible message
+                let mut nonce_instructions = vec![system_instruction::advance_nonce_account(
+                    &Pubkey::new_unique(),
+                    authority,
+                )];
+                nonce_instructions.extend_from_slice(instructions);
+
+                Ok(v0::Message::try_compile(
+                    payer,
+                    &nonce_instructions,
+                    lookup_tables,
+                    self.blockhash,
+                )?)
+            }
+            None => self.compile_with_blockhash(payer, instructions, lookup_tables),
+        }
+    }
+}
+
+// Reads the nonce value of an initialized nonce account controlled by `authority`
+fn parse_nonce(data: &[u8], authority: &Pubkey) -> Result<Hash, AppError> {
+    let versions =
+        bincode::deserialize::<Versions>(data).map_err(|_| AppError::InvalidNonceAccount)?;
+    match versions.state() {
+        NonceState::Initialized(nonce_data) if nonce_data.authority == *authority => {
+            Ok(nonce_data.blockhash())
+        }
+        NonceState::Initialized(nonce_data) => {
+            println!(
+                "⛔️ Nonce authority is {}, not {}",
+                nonce_data.authority, authority
+            );
+            Err(AppError::NonceAuthorityMismatch)
+        }
+        NonceState::Uninitialized => Err(AppError::InvalidNonceAccount),
+    }
+}
+
+/// Handler creating durable nonce accounts for multi-transaction flows
+///
+/// The nonce account keypairs are generated and signed for by the server, so the returned
+/// transactions only need the fee payer's signature. Each transaction creates up to
+/// `MAX_NONCE_ACCOUNTS` of them.
+pub async fn create_nonce_accounts_cb(
+    State(state): State<AppState>,
+    Json(request): Json<CreateNonceAccountsRequest>,
+) -> Result<Json<CreateNonceAccountsResponse>, AppError> {
+    println!("🚀 Starting create_nonce_accounts_cb handler");
+
+    let fee_payer = parse_base58_pubkey(&request.fee_payer)?;
+    let nonce_authority = match &request.nonce_authority {
+        Some(nonce_authority) => parse_base58_pubkey(nonce_authority)?,
+        None => fee_payer,
+    };
+    // The sponsor funds the rent of the nonce accounts, so it must remain their authority
+    if state.is_sponsor(&fee_payer) && nonce_authority != fee_payer {
+        println!("⛔️ Sponsored nonce accounts must have the sponsor as authority");
+        return Err(AppError::SponsorshipDenied);
+    }
+
+    let count = request.count.unwrap_or(1);
+    if count == 0 || count > MAX_NONCE_ACCOUNTS_PER_REQUEST {
+        println!("⛔️ Invalid number of nonce accounts: {}", count);
+        return Err(AppError::InvalidNonceAccountCount);
+    }
+
+    // Nonce accounts are rent-exempt system accounts holding the nonce state
+    let rent = state.rent(None, NonceState::size()).await?;
+    println!("💰 Rent per nonce account: {} lamports", rent);
+
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let nonce_account_keypairs = (0..count).map(|_| Keypair::new()).collect::<Vec<_>>();
+    let mut transactions = nonce_account_keypairs
+        .chunks(MAX_NONCE_ACCOUNTS)
+        .map(|nonce_accounts| {
+            let instructions = nonce_accounts
+                .iter()
+                .flat_map(|nonce_account| {
+                    system_instruction::create_nonce_account(
+                        &fee_payer,
+                        &nonce_account.pubkey(),
+                        &nonce_authority,
+                        rent,
+                    )
+                })
+                .collect::<Vec<_>>();
+            let message =
+                v0::Message::try_compile(&fee_payer, &instructions, &[], client_blockhash)?;
+            partially_signed_transaction(message, &nonce_accounts.iter().collect::<Vec<_>>())
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    // Sign as fee payer if the transactions are sponsored by the server
+    state
+        .co_sign("create-nonce-accounts", 0, &mut transactions)
+        .await?;
+
+    let required_signers = transactions.iter().map(missing_signers).collect();
+    let transactions = transactions
+        .iter()
+        .map(|transaction| Ok(BASE64_STANDARD.encode(bincode::serialize(transaction)?)))
+        .collect::<Result<Vec<_>, AppError>>()?;
+    println!(
+        "✅ {} transactions created successfully",
+        transactions.len()
+    );
+
+    Ok(Json(CreateNonceAccountsResponse {
+        transactions,
+        required_signers,
+        nonce_accounts: nonce_account_keypairs
+            .iter()
+            .map(|nonce_account| nonce_account.pubkey().to_string())
+            .collect(),
+        message: format!(
+            "Created transactions for {} nonce accounts with authority {}",
+            count, nonce_authority
+        ),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::routes::test_util,
+        solana_sdk::{packet::PACKET_DATA_SIZE, transaction::VersionedTransaction},
+    };
+
+    #[tokio::test]
+    async fn test_create_nonce_accounts_spreads_them_across_transactions() {
+        let fee_payer = Pubkey::new_unique();
+
+        let Json(response) = create_nonce_accounts_cb(
+            State(AppState::default()),
+            Json(CreateNonceAccountsRequest {
+                fee_payer: fee_payer.to_string(),
+                nonce_authority: None,
+                count: Some(2 * MAX_NONCE_ACCOUNTS + 1),
+                latest_blockhash: Some(Hash::new_unique().to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.nonce_accounts.len(), 2 * MAX_NONCE_ACCOUNTS + 1);
+        assert_eq!(response.transactions.len(), 3);
+
+        // The nonce accounts are signed for by the server, only the fee payer is left
+        for (transaction, required_signers) in
+            response.transactions.iter().zip(&response.required_signers)
+        {
+            let bytes = BASE64_STANDARD.decode(transaction).unwrap();
+            assert!(bytes.len() <= PACKET_DATA_SIZE);
+
+            let transaction: VersionedTransaction = bincode::deserialize(&bytes).unwrap();
+            assert_eq!(required_signers, &vec![fee_payer.to_string()]);
+            assert!(transaction
+                .verify_with_results()
+                .into_iter()
+                .skip(1)
+                .all(|verified| verified));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_nonce_accounts_rejects_invalid_count() {
+        let result = create_nonce_accounts_cb(
+            State(AppState::default()),
+            Json(CreateNonceAccountsRequest {
+                fee_payer: Pubkey::new_unique().to_string(),
+                nonce_authority: None,
+                count: Some(MAX_NONCE_ACCOUNTS_PER_REQUEST + 1),
+                latest_blockhash: Some(Hash::new_unique().to_string()),
+            }),
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::InvalidNonceAccountCount)));
+    }
+
+    #[tokio::test]
+    async fn test_create_nonce_accounts_keeps_sponsored_accounts_under_the_sponsor() {
+        let (state, sponsor) =
+            test_util::sponsored_state(AppState::default(), &["create-nonce-accounts"]);
+
+        let result = create_nonce_accounts_cb(
+            State(state.clone()),
+            Json(CreateNonceAccountsRequest {
+                fee_payer: sponsor.pubkey().to_string(),
+                nonce_authority: Some(Pubkey::new_unique().to_string()),
+                count: Some(1),
+                latest_blockhash: Some(Hash::new_unique().to_string()),
+            }),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::SponsorshipDenied)));
+
+        let Json(response) = create_nonce_accounts_cb(
+            State(state),
+            Json(CreateNonceAccountsRequest {
+                fee_payer: sponsor.pubkey().to_string(),
+                nonce_authority: None,
+                count: Some(1),
+                latest_blockhash: Some(Hash::new_unique().to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+        assert!(response.required_signers.iter().all(Vec::is_empty));
+    }
+
+    #[tokio::test]
+    async fn test_compile_fails_once_nonce_accounts_run_out() {
+        let authority = Pubkey::new_unique();
+        let mut lifetime = TransactionLifetime::from_request(
+            &AppState::default(),
+            Some(&test_util::mock_blockhash().to_string()),
+            Some(&vec![Pubkey::new_unique().to_string()]),
+            Some(&vec![test_util::nonce_account_data(
+                &authority,
+                &Hash::new_unique(),
+            )]),
+            None,
+            authority,
+        )
+        .await
+        .unwrap();
+
+        assert!(lifetime.compile(&authority, &[], &[]).is_ok());
+        assert!(matches!(
+            lifetime.compile(&authority, &[], &[]),
+            Err(AppError::NotEnoughNonceAccounts)
+        ));
+    }
+}
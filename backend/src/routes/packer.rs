@@ -0,0 +1,265 @@
+//! Packs the instructions of a multi-transaction flow into as few transactions as possible.
+//!
+//! Instructions are given as ordered groups. A group always lands in a single transaction (e.g. a
+//! token instruction and the proof verifications it reads at an instruction offset), and groups
+//! keep their order across transactions, so an instruction never runs before the ones it depends
+//! on. Each transaction is filled with as many groups as fit in the packet size limit, leaving
+//! room for the compute budget instructions of the priority fee.
+
+use {
+    crate::{
+        errors::AppError,
+        routes::{
+            nonce::TransactionLifetime,
+            priority_fee::{compute_budget_instructions, compute_unit_limit, PriorityFee},
+            util::partially_signed_transaction,
+        },
+    },
+    solana_message::AddressLookupTableAccount,
+    solana_sdk::{
+        instruction::Instruction,
+        message::{v0, VersionedMessage},
+        packet::PACKET_DATA_SIZE,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+        signer::Signer,
+        transaction::VersionedTransaction,
+    },
+};
+
+// Instructions of one transaction and whether it sets a compute unit limit and price
+struct PlannedTransaction {
+    instructions: Vec<Instruction>,
+    compute_budget: bool,
+}
+
+// Compiles a message as large as the one of the transaction, compute budget instructions included
+fn planned_size(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lifetime: &TransactionLifetime,
+    lookup_tables: &[AddressLookupTableAccount],
+    compute_budget: bool,
+) -> Result<usize, AppError> {
+    let mut budgeted_instructions = if compute_budget {
+        compute_budget_instructions(0, 0)
+    } else {
+        Vec::new()
+    };
+    budgeted_instructions.extend_from_slice(instructions);
+
+    let message = lifetime.compile_for_size(payer, &budgeted_instructions, lookup_tables)?;
+    transaction_size(&message)
+}
+
+/// Serialized size of a transaction for the message, with placeholder signatures
+pub fn transaction_size(message: &v0::Message) -> Result<usize, AppError> {
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+        message: VersionedMessage::V0(message.clone()),
+    };
+
+    Ok(bincode::serialized_size(&transaction)? as usize)
+}
+
+/// Whether the instructions fit in one transaction of the flow, including the durable nonce
+/// advance if the flow uses durable nonces and the compute budget instructions if it has a
+/// priority fee
+pub fn fits_in_transaction(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lifetime: &TransactionLifetime,
+    lookup_tables: &[AddressLookupTableAccount],
+    priority_fee: Option<&PriorityFee>,
+) -> Result<bool, AppError> {
+    let size = planned_size(
+        payer,
+        instructions,
+        lifetime,
+        lookup_tables,
+        priority_fee.is_some(),
+    )?;
+    Ok(size <= PACKET_DATA_SIZE)
+}
+
+/// Packs the instruction groups into transactions and signs them with the server-held keypairs
+/// they require
+///
+/// A group too large to fit along with the compute budget instructions gets a transaction of its
+/// own without them (the range proof verification, in practice). With durable nonces, every
+/// transaction advances one, so a group that does not fit along with the nonce advance fails the
+/// flow rather than falling back to the latest blockhash.
+pub fn pack_transactions(
+    payer: &Pubkey,
+    groups: Vec<Vec<Instruction>>,
+    keypairs: &[&Keypair],
+    lifetime: &mut TransactionLifetime,
+    lookup_tables: &[AddressLookupTableAccount],
+    priority_fee: Option<&PriorityFee>,
+) -> Result<Vec<VersionedTransaction>, AppError> {
+    let fits = |instructions: &[Instruction], compute_budget: bool| {
+        let compute_budget = compute_budget && priority_fee.is_some();
+        planned_size(payer, instructions, lifetime, lookup_tables, compute_budget)
+            .map(|size| size <= PACKET_DATA_SIZE)
+    };
+
+    let mut planned = Vec::<PlannedTransaction>::new();
+    let mut current = Vec::new();
+    for group in groups {
+        let candidate = [current.as_slice(), group.as_slice()].concat();
+        if fits(&candidate, true)? {
+            current = candidate;
+            continue;
+        }
+
+        if !current.is_empty() {
+            planned.push(PlannedTransaction {
+                instructions: std::mem::take(&mut current),
+                compute_budget: true,
+            });
+        }
+
+        if fits(&group, true)? {
+            current = group;
+        } else {
+            if !fits(&group, false)? {
+                println!("⛔️ Instruction group does not fit in a transaction");
+                return Err(AppError::TransactionTooLarge);
+            }
+            println!("⚠️ No room for the priority fee in a transaction");
+            planned.push(PlannedTransaction {
+                instructions: group,
+                compute_budget: false,
+            });
+        }
+    }
+    if !current.is_empty() {
+        planned.push(PlannedTransaction {
+            instructions: current,
+            compute_budget: true,
+        });
+    }
+
+    // A durable nonce is needed for every transaction
+    if let Some(provided) = lifetime.nonce_count() {
+        let expected = planned.len();
+        if provided != expected {
+            println!("⛔️ Expected {} nonce accounts, got {}", expected, provided);
+            return Err(AppError::NonceAccountCountMismatch { expected, provided });
+        }
+    }
+    println!(
+        "📦 Packed {} instructions into {} transactions",
+        planned
+            .iter()
+            .map(|planned| planned.instructions.len())
+            .sum::<usize>(),
+        planned.len()
+    );
+
+    // Set the compute unit limit and price of every transaction with room for it, with a price
+    // spread across all of them for a total budget
+    if let Some(priority_fee) = priority_fee {
+        let limits = planned
+            .iter()
+            .map(|planned| {
+                planned
+                    .compute_budget
+                    .then(|| compute_unit_limit(&planned.instructions, lifetime.is_durable()))
+            })
+            .collect::<Vec<_>>();
+        let micro_lamports = priority_fee.micro_lamports_per_compute_unit(
+            limits.iter().flatten().map(|&limit| limit as u64).sum(),
+        );
+        println!(
+            "✅ Compute unit limits {:?}, price {} micro-lamports",
+            limits, micro_lamports
+        );
+
+        for (planned, limit) in planned.iter_mut().zip(limits) {
+            if let Some(limit) = limit {
+                let mut instructions = compute_budget_instructions(limit, micro_lamports);
+                instructions.append(&mut planned.instructions);
+                planned.instructions = instructions;
+            }
+        }
+    }
+
+    planned
+        .into_iter()
+        .map(|planned| {
+            let message = lifetime.compile(payer, &planned.instructions, lookup_tables)?;
+
+            let signers = &message.account_keys[..message.header.num_required_signatures as usize];
+            let keypairs = keypairs
+                .iter()
+                .copied()
+                .filter(|keypair| signers.contains(&keypair.pubkey()))
+                .collect::<Vec<_>>();
+
+            partially_signed_transaction(message, &keypairs)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{hash::Hash, instruction::AccountMeta},
+    };
+
+    fn instruction(data_len: usize, signer: Option<&Pubkey>) -> Instruction {
+        Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &vec![1; data_len],
+            signer
+                .map(|signer| AccountMeta::new_readonly(*signer, true))
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_pack_transactions_fills_transactions_in_order() {
+        let payer = Pubkey::new_unique();
+        let keypair = Keypair::new();
+        let mut lifetime = TransactionLifetime::with_blockhash(Hash::new_unique());
+
+        let groups = vec![
+            vec![instruction(300, Some(&keypair.pubkey()))],
+            vec![instruction(300, None)],
+            vec![instruction(700, None)],
+            vec![instruction(100, None), instruction(100, None)],
+        ];
+        let transactions =
+            pack_transactions(&payer, groups, &[&keypair], &mut lifetime, &[], None).unwrap();
+
+        // The third group does not fit after the first two, the fourth one does after the third
+        let instruction_counts = transactions
+            .iter()
+            .map(|transaction| transaction.message.instructions().len())
+            .collect::<Vec<_>>();
+        assert_eq!(instruction_counts, vec![2, 3]);
+
+        // Only the transaction needing the keypair is signed by it
+        assert_eq!(transactions[0].signatures.len(), 2);
+        assert!(transactions[0].verify_with_results()[1]);
+        assert_eq!(transactions[1].signatures.len(), 1);
+    }
+
+    #[test]
+    fn test_pack_transactions_rejects_oversized_groups() {
+        let mut lifetime = TransactionLifetime::with_blockhash(Hash::new_unique());
+
+        let result = pack_transactions(
+            &Pubkey::new_unique(),
+            vec![vec![instruction(PACKET_DATA_SIZE, None)]],
+            &[],
+            &mut lifetime,
+            &[],
+            None,
+        );
+        assert!(matches!(result, Err(AppError::TransactionTooLarge)));
+    }
+}
@@ -0,0 +1,284 @@
+//! Compute unit limits and priority fees for the transactions of a flow.
+//!
+//! The compute unit limit of a transaction is the sum of per-instruction estimates, so the
+//! priority fee (charged on the limit, not on the units used) is not paid for the 200k default
+//! units per instruction. Zk ElGamal proof verification is charged a fixed amount per proof type.
+
+use {
+    crate::{errors::AppError, models::PriorityFeeRequest},
+    solana_sdk::{
+        compute_budget::{self, ComputeBudgetInstruction},
+        instruction::Instruction,
+    },
+    solana_sdk_ids::system_program,
+    solana_zk_sdk::zk_elgamal_proof_program::{self, instruction::ProofInstruction},
+    spl_token_2022::{
+        extension::confidential_transfer::instruction::ConfidentialTransferInstruction,
+        instruction::{decode_instruction_type, TokenInstruction},
+    },
+};
+
+/// Maximum compute unit limit of a transaction
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+// Estimates for builtin programs, which charge a fixed amount per instruction
+const BUILTIN_COMPUTE_UNITS: u32 = 150;
+// Estimates for the on-chain programs used by the flows, with headroom over measured usage
+const CONFIDENTIAL_TRANSFER_COMPUTE_UNITS: u32 = 50_000;
+const CONFIDENTIAL_TRANSFER_WITH_FEE_COMPUTE_UNITS: u32 = 70_000;
+const CONFIDENTIAL_ACCOUNT_COMPUTE_UNITS: u32 = 30_000;
+const TOKEN_2022_COMPUTE_UNITS: u32 = 10_000;
+const ASSOCIATED_TOKEN_COMPUTE_UNITS: u32 = 40_000;
+const MEMO_COMPUTE_UNITS: u32 = 30_000;
+const RECORD_COMPUTE_UNITS: u32 = 5_000;
+// The runtime default for an instruction of any other program
+const DEFAULT_COMPUTE_UNITS: u32 = 200_000;
+
+/// Priority fee policy of a flow
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriorityFee {
+    /// Compute unit price of every transaction
+    MicroLamportsPerComputeUnit(u64),
+    /// Budget for the whole flow, spread across its transactions by their compute unit limits
+    TotalLamports(u64),
+}
+
+impl PriorityFee {
+    /// Parses the priority fee policy of a request, which sets exactly one of its fields
+    pub fn from_request(request: Option<&PriorityFeeRequest>) -> Result<Option<Self>, AppError> {
+        let Some(request) = request else {
+            return Ok(None);
+        };
+
+        let parse = |value: &String| {
+            value
+                .parse::<u64>()
+                .map_err(|_| AppError::InvalidPriorityFee)
+        };
+        match (
+            &request.micro_lamports_per_compute_unit,
+            &request.total_lamports,
+        ) {
+            (Some(micro_lamports), None) => Ok(Some(Self::MicroLamportsPerComputeUnit(parse(
+                micro_lamports,
+            )?))),
+            (None, Some(total_lamports)) => Ok(Some(Self::TotalLamports(parse(total_lamports)?))),
+            _ => Err(AppError::InvalidPriorityFee),
+        }
+    }
+
+    /// Parses the priority fee policy of a request, or else its legacy lamport budget for the whole
+    /// flow (`"0"` for none)
+    pub fn from_legacy_request(
+        request: Option<&PriorityFeeRequest>,
+        total_lamports: &str,
+    ) -> Result<Option<Self>, AppError> {
+        if let Some(priority_fee) = Self::from_request(request)? {
+            return Ok(Some(priority_fee));
+        }
+
+        match total_lamports.parse::<u64>() {
+            Ok(0) => Ok(None),
+            Ok(lamports) => Ok(Some(Self::TotalLamports(lamports))),
+            Err(_) => {
+                println!("⛔️ Invalid priority fee: {}", total_lamports);
+                Err(AppError::InvalidPriorityFee)
+            }
+        }
+    }
+
+    /// Compute unit price for transactions with `total_compute_units` as their limits altogether
+    pub fn micro_lamports_per_compute_unit(&self, total_compute_units: u64) -> u64 {
+        match *self {
+            Self::MicroLamportsPerComputeUnit(micro_lamports) => micro_lamports,
+            Self::TotalLamports(lamports) => {
+                let micro_lamports =
+                    lamports as u128 * 1_000_000 / total_compute_units.max(1) as u128;
+                micro_lamports.min(u64::MAX as u128) as u64
+            }
+        }
+    }
+}
+
+/// Estimated compute units of an instruction
+pub fn estimate_compute_units(instruction: &Instruction) -> u32 {
+    let program_id = instruction.program_id;
+    if program_id == zk_elgamal_proof_program::id() {
+        ProofInstruction::instruction_type(&instruction.data)
+            .map_or(DEFAULT_COMPUTE_UNITS, zk_elgamal_proof_compute_units)
+    } else if program_id == spl_token_2022::id() {
+        token_2022_compute_units(&instruction.data)
+    } else if program_id == spl_associated_token_account::id() {
+        ASSOCIATED_TOKEN_COMPUTE_UNITS
+    } else if program_id == spl_memo::id() {
+        MEMO_COMPUTE_UNITS
+    } else if program_id == spl_record::id() {
+        RECORD_COMPUTE_UNITS
+    } else if program_id == system_program::id() || program_id == compute_budget::id() {
+        BUILTIN_COMPUTE_UNITS
+    } else {
+        DEFAULT_COMPUTE_UNITS
+    }
+}
+
+// Compute units charged by the zk ElGamal proof program for each instruction
+fn zk_elgamal_proof_compute_units(instruction_type: ProofInstruction) -> u32 {
+    match instruction_type {
+        ProofInstruction::CloseContextState => 3_300,
+        ProofInstruction::VerifyZeroCiphertext => 6_000,
+        ProofInstruction::VerifyCiphertextCiphertextEquality => 8_000,
+        ProofInstruction::VerifyCiphertextCommitmentEquality => 6_400,
+        ProofInstruction::VerifyPubkeyValidity => 2_600,
+        ProofInstruction::VerifyPercentageWithCap => 6_500,
+        ProofInstruction::VerifyBatchedRangeProofU64 => 111_000,
+        ProofInstruction::VerifyBatchedRangeProofU128 => 200_000,
+        ProofInstruction::VerifyBatchedRangeProofU256 => 368_000,
+        ProofInstruction::VerifyGroupedCiphertext2HandlesValidity => 6_400,
+        ProofInstruction::VerifyBatchedGroupedCiphertext2HandlesValidity => 13_000,
+        ProofInstruction::VerifyGroupedCiphertext3HandlesValidity => 8_100,
+        ProofInstruction::VerifyBatchedGroupedCiphertext3HandlesValidity => 16_400,
+    }
+}
+
+// Confidential transfer instructions do ciphertext arithmetic, the others are plain bookkeeping
+fn token_2022_compute_units(data: &[u8]) -> u32 {
+    if !matches!(
+        TokenInstruction::unpack(data),
+        Ok(TokenInstruction::ConfidentialTransferExtension)
+    ) {
+        return TOKEN_2022_COMPUTE_UNITS;
+    }
+
+    match decode_instruction_type::<ConfidentialTransferInstruction>(&data[1..]) {
+        Ok(ConfidentialTransferInstruction::Transfer) => CONFIDENTIAL_TRANSFER_COMPUTE_UNITS,
+        Ok(ConfidentialTransferInstruction::TransferWithFee) => {
+            CONFIDENTIAL_TRANSFER_WITH_FEE_COMPUTE_UNITS
+        }
+        _ => CONFIDENTIAL_ACCOUNT_COMPUTE_UNITS,
+    }
+}
+
+/// Compute unit limit of a transaction with the instructions, its compute budget instructions
+/// and, if it uses a durable nonce, `advance_nonce_account`
+pub fn compute_unit_limit(instructions: &[Instruction], advances_nonce: bool) -> u32 {
+    let overhead = BUILTIN_COMPUTE_UNITS * if advances_nonce { 3 } else { 2 };
+    let limit = instructions
+        .iter()
+        .map(estimate_compute_units)
+        .fold(overhead, u32::saturating_add);
+    limit.min(MAX_COMPUTE_UNIT_LIMIT)
+}
+
+/// Instructions setting the compute unit limit and price of a transaction
+pub fn compute_budget_instructions(
+    compute_unit_limit: u32,
+    micro_lamports: u64,
+) -> Vec<Instruction> {
+    vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(micro_lamports),
+    ]
+}
+
+/// Prepends the compute budget instructions for the priority fee to the instructions of a single
+/// transaction, or returns them unchanged without a priority fee
+pub fn with_compute_budget(
+    priority_fee: Option<&PriorityFee>,
+    instructions: Vec<Instruction>,
+) -> Vec<Instruction> {
+    let Some(priority_fee) = priority_fee else {
+        return instructions;
+    };
+
+    let limit = compute_unit_limit(&instructions, false);
+    let micro_lamports = priority_fee.micro_lamports_per_compute_unit(limit as u64);
+    println!(
+        "✅ Compute unit limit {}, price {} micro-lamports",
+        limit, micro_lamports
+    );
+
+    let mut budgeted_instructions = compute_budget_instructions(limit, micro_lamports);
+    budgeted_instructions.extend(instructions);
+    budgeted_instructions
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, solana_sdk::pubkey::Pubkey,
+        solana_zk_sdk::zk_elgamal_proof_program::instruction::close_context_state,
+        solana_zk_sdk::zk_elgamal_proof_program::instruction::ContextStateInfo,
+    };
+
+    #[test]
+    fn test_total_lamports_are_spread_by_compute_units() {
+        let close = close_context_state(
+            ContextStateInfo {
+                context_state_account: &Pubkey::new_unique(),
+                context_state_authority: &Pubkey::new_unique(),
+            },
+            &Pubkey::new_unique(),
+        );
+        assert_eq!(estimate_compute_units(&close), 3_300);
+
+        let limit = compute_unit_limit(&[close], false);
+        assert_eq!(limit, 3_600);
+
+        let instructions = with_compute_budget(Some(&PriorityFee::TotalLamports(36)), vec![]);
+        let expected_price = 36 * 1_000_000 / 300;
+        assert_eq!(
+            instructions,
+            compute_budget_instructions(300, expected_price)
+        );
+    }
+
+    #[test]
+    fn test_priority_fee_request_sets_one_field() {
+        let request = |micro_lamports: Option<&str>, total_lamports: Option<&str>| {
+            PriorityFee::from_request(Some(&PriorityFeeRequest {
+                micro_lamports_per_compute_unit: micro_lamports.map(str::to_string),
+                total_lamports: total_lamports.map(str::to_string),
+            }))
+        };
+
+        assert_eq!(
+            request(Some("5"), None).unwrap(),
+            Some(PriorityFee::MicroLamportsPerComputeUnit(5))
+        );
+        assert_eq!(
+            request(None, Some("5")).unwrap(),
+            Some(PriorityFee::TotalLamports(5))
+        );
+        assert!(matches!(
+            request(Some("5"), Some("5")),
+            Err(AppError::InvalidPriorityFee)
+        ));
+        assert!(matches!(
+            request(None, Some("five")),
+            Err(AppError::InvalidPriorityFee)
+        ));
+    }
+
+    #[test]
+    fn test_legacy_priority_fee_rejects_invalid_lamports() {
+        assert_eq!(PriorityFee::from_legacy_request(None, "0").unwrap(), None);
+        assert_eq!(
+            PriorityFee::from_legacy_request(None, "10").unwrap(),
+            Some(PriorityFee::TotalLamports(10))
+        );
+        assert!(matches!(
+            PriorityFee::from_legacy_request(None, "ten"),
+            Err(AppError::InvalidPriorityFee)
+        ));
+
+        // The policy overrides the legacy field
+        let policy = PriorityFeeRequest {
+            micro_lamports_per_compute_unit: Some("5".to_string()),
+            total_lamports: None,
+        };
+        assert_eq!(
+            PriorityFee::from_legacy_request(Some(&policy), "ten").unwrap(),
+            Some(PriorityFee::MicroLamportsPerComputeUnit(5))
+        );
+    }
+}
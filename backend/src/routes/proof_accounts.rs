@@ -0,0 +1,781 @@
+//! Deterministic addresses for the proof context state accounts of a flow.
+//!
+//! The proof accounts of transfers and withdrawals are derived from the owner's ElGamal signature,
+//! the token account and a flow nonce rather than generated at random, so that the accounts left
+//! behind by an abandoned flow (rent included) can always be found and closed again. Without an
+//! explicit nonce, a flow uses one derived from the available balance of the token account, which
+//! only changes once a flow lands. Routes return the nonce they used: once the token instruction
+//! of a flow has landed but its proof accounts are left open, the available balance no longer
+//! yields that nonce, so recovering the accounts takes the returned one.
+//!
+//! A flow interrupted halfway through (e.g. by an expired blockhash) can also be resumed from its
+//! proof accounts. The proofs of a flow share their Pedersen openings, so a verified proof cannot
+//! be paired with a regenerated one: if every proof account is verified, the flow resumes at the
+//! token instruction, and otherwise fresh proofs are verified into the allocated ones. Verified
+//! accounts without all their siblings (e.g. a range proof whose equality proof was verified
+//! inline by a token instruction that never landed) are closed first and allocated again.
+
+use {
+    crate::{
+        errors::AppError,
+        models::{RecoverProofAccountsRequest, RecoverProofAccountsResponse, ResumeRequest},
+        routes::{
+            nonce::TransactionLifetime,
+            packer::pack_transactions,
+            util::{
+                missing_signers, parse_base58_pubkey, parse_fee_payer,
+                parse_token_account_authority,
+            },
+        },
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    bytemuck::Pod,
+    solana_sdk::{
+        hash::hashv,
+        instruction::Instruction,
+        pubkey::Pubkey,
+        signature::{keypair_from_seed, Keypair, Signature},
+        signer::Signer,
+    },
+    solana_zk_sdk::{
+        encryption::{
+            elgamal::{ElGamalCiphertext, ElGamalKeypair},
+            grouped_elgamal::GroupedElGamal,
+            pedersen::Pedersen,
+            pod::elgamal::{PodElGamalCiphertext, PodElGamalPubkey},
+        },
+        zk_elgamal_proof_program::{
+            instruction::{close_context_state, ContextStateInfo, ProofInstruction},
+            proof_data::{
+                BatchedGroupedCiphertext3HandlesValidityProofContext,
+                BatchedGroupedCiphertext3HandlesValidityProofData, BatchedRangeProofContext,
+                BatchedRangeProofU64Data, CiphertextCommitmentEqualityProofContext,
+                CiphertextCommitmentEqualityProofData, ProofType,
+            },
+            state::{ProofContextState, ProofContextStateMeta},
+        },
+    },
+    spl_associated_token_account::get_associated_token_address_with_program_id,
+    spl_token_2022::extension::{
+        confidential_transfer::ConfidentialTransferAccount, BaseStateWithExtensions,
+        StateWithExtensionsOwned,
+    },
+    spl_token_confidential_transfer_ciphertext_arithmetic::{subtract_from, subtract_with_lo_hi},
+    spl_token_confidential_transfer_proof_extraction::{
+        encryption::PodTransferAmountCiphertext, transfer::TransferProofContext,
+        withdraw::WithdrawProofContext,
+    },
+    std::collections::HashMap,
+};
+
+/// Proof context state accounts of the transfer and withdraw flows, and the record accounts their
+/// range proofs are written into when the flows use durable nonces
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofAccount {
+    TransferEquality,
+    TransferCiphertextValidity,
+    TransferRange,
+    TransferRangeRecord,
+    WithdrawEquality,
+    WithdrawRange,
+    WithdrawRangeRecord,
+}
+
+impl ProofAccount {
+    pub const ALL: [Self; 7] = [
+        Self::TransferEquality,
+        Self::TransferCiphertextValidity,
+        Self::TransferRange,
+        Self::TransferRangeRecord,
+        Self::WithdrawEquality,
+        Self::WithdrawRange,
+        Self::WithdrawRangeRecord,
+    ];
+
+    fn label(&self) -> &'static [u8] {
+        match self {
+            Self::TransferEquality => b"transfer-equality",
+            Self::TransferCiphertextValidity => b"transfer-ciphertext-validity",
+            Self::TransferRange => b"transfer-range",
+            Self::TransferRangeRecord => b"transfer-range-record",
+            Self::WithdrawEquality => b"withdraw-equality",
+            Self::WithdrawRange => b"withdraw-range",
+            Self::WithdrawRangeRecord => b"withdraw-range-record",
+        }
+    }
+
+    /// Instruction closing the account and returning its rent to the authority
+    pub fn close_instruction(&self, address: &Pubkey, authority: &Pubkey) -> Instruction {
+        match self {
+            Self::TransferRangeRecord | Self::WithdrawRangeRecord => {
+                spl_record::instruction::close_account(address, authority, authority)
+            }
+            _ => close_context_state(
+                ContextStateInfo {
+                    context_state_account: address,
+                    context_state_authority: authority,
+                },
+                authority,
+            ),
+        }
+    }
+
+    /// Instruction verifying a placeholder proof into an allocated but unverified proof account,
+    /// whose authority is still unset, so that the authority can close it afterwards. Record
+    /// accounts are initialized along with their allocation and need none.
+    pub fn placeholder_verify_instruction(
+        &self,
+        address: &Pubkey,
+        authority: &Pubkey,
+    ) -> Result<Option<Instruction>, AppError> {
+        let context_state_info = Some(ContextStateInfo {
+            context_state_account: address,
+            context_state_authority: authority,
+        });
+
+        // Proofs about a zero amount under a throwaway key, of the type the account was sized for
+        let elgamal_keypair = ElGamalKeypair::new_rand();
+        let (commitment, opening) = Pedersen::new(0u64);
+        let instruction = match self {
+            Self::TransferEquality | Self::WithdrawEquality => {
+                let ciphertext = elgamal_keypair.pubkey().encrypt_with(0u64, &opening);
+                let proof_data = CiphertextCommitmentEqualityProofData::new(
+                    &elgamal_keypair,
+                    &ciphertext,
+                    &commitment,
+                    &opening,
+                    0,
+                )
+                .map_err(|_| AppError::ProofGeneration)?;
+                ProofInstruction::VerifyCiphertextCommitmentEquality
+                    .encode_verify_proof(context_state_info, &proof_data)
+            }
+            Self::TransferCiphertextValidity => {
+                let pubkey = elgamal_keypair.pubkey();
+                let ciphertext = GroupedElGamal::encrypt_with([pubkey; 3], 0u64, &opening);
+                let proof_data = BatchedGroupedCiphertext3HandlesValidityProofData::new(
+                    pubkey,
+                    pubkey,
+                    pubkey,
+                    &ciphertext,
+                    &ciphertext,
+                    0,
+                    0,
+                    &opening,
+                    &opening,
+                )
+                .map_err(|_| AppError::ProofGeneration)?;
+                ProofInstruction::VerifyBatchedGroupedCiphertext3HandlesValidity
+                    .encode_verify_proof(context_state_info, &proof_data)
+            }
+            // Every batched range proof has the same context, so the cheapest one fits either
+            Self::TransferRange | Self::WithdrawRange => {
+                let proof_data = BatchedRangeProofU64Data::new(
+                    vec![&commitment],
+                    vec![0],
+                    vec![64],
+                    vec![&opening],
+                )
+                .map_err(|_| AppError::ProofGeneration)?;
+                ProofInstruction::VerifyBatchedRangeProofU64
+                    .encode_verify_proof(context_state_info, &proof_data)
+            }
+            Self::TransferRangeRecord | Self::WithdrawRangeRecord => return Ok(None),
+        };
+
+        Ok(Some(instruction))
+    }
+
+    /// Keypair of the proof account for a flow of the token account
+    pub fn keypair(
+        &self,
+        elgamal_signature: &Signature,
+        token_account: &Pubkey,
+        flow_nonce: u64,
+    ) -> Keypair {
+        let seed = hashv(&[
+            b"proof-context",
+            elgamal_signature.as_ref(),
+            token_account.as_ref(),
+            &flow_nonce.to_le_bytes(),
+            self.label(),
+        ]);
+        keypair_from_seed(seed.as_ref()).expect("a hash is a valid keypair seed")
+    }
+}
+
+/// Returns the flow nonce from the request, or the one derived from the available balance
+pub fn flow_nonce(flow_nonce: Option<u64>, account: &ConfidentialTransferAccount) -> u64 {
+    let flow_nonce = flow_nonce.unwrap_or_else(|| {
+        let hash = hashv(&[bytemuck::bytes_of(&account.available_balance)]);
+        u64::from_le_bytes(hash.to_bytes()[..8].try_into().unwrap())
+    });
+    println!("✅ Using flow nonce {}", flow_nonce);
+    flow_nonce
+}
+
+/// Proof accounts left behind by an interrupted attempt of a flow, to resume it from
+#[derive(Default)]
+pub struct ExistingProofAccounts {
+    // Account data of the proof accounts that exist, verified or only allocated
+    accounts: HashMap<Pubkey, Vec<u8>>,
+}
+
+impl ExistingProofAccounts {
+    /// Reads the proof accounts of a request in resume mode, which must be proof accounts of the
+    /// flow
+    pub async fn from_request(
+        state: &AppState,
+        resume: Option<&ResumeRequest>,
+        flow_proof_accounts: &[Pubkey],
+    ) -> Result<Self, AppError> {
+        let mut accounts = HashMap::new();
+        for proof_account in resume.into_iter().flat_map(|resume| &resume.proof_accounts) {
+            let address = parse_base58_pubkey(&proof_account.address)?;
+            if !flow_proof_accounts.contains(&address) {
+                println!("⛔️ {} is not a proof account of the flow", address);
+                return Err(AppError::UnknownProofAccount);
+            }
+
+            let data = state
+                .account_data(
+                    proof_account.data.as_ref(),
+                    Some(&proof_account.address),
+                    "resume.proof_accounts.data",
+                )
+                .await?;
+            accounts.insert(address, data);
+        }
+        println!("✅ Resuming from {} proof accounts", accounts.len());
+
+        Ok(Self { accounts })
+    }
+
+    /// Whether the proof account is already allocated
+    pub fn exists(&self, address: &Pubkey) -> bool {
+        self.accounts.contains_key(address)
+    }
+
+    /// Whether a proof is already verified into the proof account
+    pub fn is_verified(&self, address: &Pubkey) -> bool {
+        self.accounts
+            .get(address)
+            .is_some_and(|data| is_verified(data))
+    }
+
+    // The proof context verified into the proof account, which the flow must be able to close
+    fn proof_context<T: Pod>(
+        &self,
+        address: &Pubkey,
+        proof_type: ProofType,
+        context_state_authority: &Pubkey,
+    ) -> Result<T, AppError> {
+        let data = self.accounts.get(address).ok_or_else(|| {
+            println!("⛔️ Proof account {} is not verified", address);
+            AppError::StaleProofAccounts
+        })?;
+        let state = ProofContextState::<T>::try_from_bytes(data).map_err(|_| {
+            println!("⛔️ Proof account {} is not verified", address);
+            AppError::StaleProofAccounts
+        })?;
+        if ProofType::try_from(state.proof_type) != Ok(proof_type)
+            || state.context_state_authority != *context_state_authority
+        {
+            println!("⛔️ Proof account {} holds another proof", address);
+            return Err(AppError::StaleProofAccounts);
+        }
+
+        Ok(state.proof_context)
+    }
+
+    // Whether every proof account of the flow is verified, so that it resumes at the token
+    // instruction
+    fn all_verified(&self, addresses: &[&Pubkey]) -> bool {
+        addresses.iter().all(|address| self.is_verified(address))
+    }
+
+    /// Forgets the verified proof accounts of the flow unless all of them are, returning them so
+    /// that they are closed before fresh proofs are verified into them
+    pub fn discard_partially_verified(&mut self, addresses: &[&Pubkey]) -> Vec<Pubkey> {
+        if self.all_verified(addresses) {
+            return Vec::new();
+        }
+
+        let discarded = addresses
+            .iter()
+            .filter(|address| self.is_verified(address))
+            .map(|address| **address)
+            .collect::<Vec<_>>();
+        for address in &discarded {
+            println!("✅ Closing partially verified proof account {}", address);
+            self.accounts.remove(address);
+        }
+        discarded
+    }
+
+    /// The proof contexts of a transfer resumed at the transfer instruction, or `None` if its
+    /// proofs are still to be verified
+    pub fn transfer_proof_context(
+        &self,
+        equality_proof_account: &Pubkey,
+        ciphertext_validity_proof_account: &Pubkey,
+        range_proof_account: &Pubkey,
+        context_state_authority: &Pubkey,
+    ) -> Result<Option<TransferProofContext>, AppError> {
+        if !self.all_verified(&[
+            equality_proof_account,
+            ciphertext_validity_proof_account,
+            range_proof_account,
+        ]) {
+            return Ok(None);
+        }
+
+        let equality_proof_context = self
+            .proof_context::<CiphertextCommitmentEqualityProofContext>(
+                equality_proof_account,
+                ProofType::CiphertextCommitmentEquality,
+                context_state_authority,
+            )?;
+        let ciphertext_validity_proof_context =
+            self.proof_context::<BatchedGroupedCiphertext3HandlesValidityProofContext>(
+                ciphertext_validity_proof_account,
+                ProofType::BatchedGroupedCiphertext3HandlesValidity,
+                context_state_authority,
+            )?;
+        let range_proof_context = self.proof_context::<BatchedRangeProofContext>(
+            range_proof_account,
+            ProofType::BatchedRangeProofU128,
+            context_state_authority,
+        )?;
+
+        TransferProofContext::verify_and_extract(
+            &equality_proof_context,
+            &ciphertext_validity_proof_context,
+            &range_proof_context,
+        )
+        .map(Some)
+        .map_err(|_| AppError::StaleProofAccounts)
+    }
+
+    /// The proof contexts of a withdrawal resumed at the withdraw instruction, or `None` if its
+    /// proofs are still to be verified
+    pub fn withdraw_proof_context(
+        &self,
+        equality_proof_account: &Pubkey,
+        range_proof_account: &Pubkey,
+        context_state_authority: &Pubkey,
+    ) -> Result<Option<WithdrawProofContext>, AppError> {
+        if !self.all_verified(&[equality_proof_account, range_proof_account]) {
+            return Ok(None);
+        }
+
+        let equality_proof_context = self
+            .proof_context::<CiphertextCommitmentEqualityProofContext>(
+                equality_proof_account,
+                ProofType::CiphertextCommitmentEquality,
+                context_state_authority,
+            )?;
+        let range_proof_context = self.proof_context::<BatchedRangeProofContext>(
+            range_proof_account,
+            ProofType::BatchedRangeProofU64,
+            context_state_authority,
+        )?;
+
+        WithdrawProofContext::verify_and_extract(&equality_proof_context, &range_proof_context)
+            .map(Some)
+            .map_err(|_| AppError::StaleProofAccounts)
+    }
+}
+
+// Whether a proof is verified into the context state account data, which is zeroed until then
+fn is_verified(data: &[u8]) -> bool {
+    ProofContextStateMeta::try_from_bytes(data)
+        .is_ok_and(|meta| ProofType::try_from(meta.proof_type) != Ok(ProofType::Uninitialized))
+}
+
+// Whether the ciphertext encrypts the amount under the keypair
+fn encrypts_amount(
+    ciphertext: &PodElGamalCiphertext,
+    amount: u64,
+    elgamal_keypair: &ElGamalKeypair,
+) -> bool {
+    subtract_from(ciphertext, amount)
+        .and_then(|ciphertext| ElGamalCiphertext::try_from(ciphertext).ok())
+        .is_some_and(|ciphertext| ciphertext.decrypt_u32(elgamal_keypair.secret()) == Some(0))
+}
+
+/// Checks that verified transfer proofs move the amount from the current available balance of
+/// the source account to the recipient
+pub fn check_transfer_proof_context(
+    proof_context: &TransferProofContext,
+    source_account: &ConfidentialTransferAccount,
+    source_elgamal_keypair: &ElGamalKeypair,
+    destination_elgamal_pubkey: &PodElGamalPubkey,
+    new_available_balance: u64,
+) -> Result<(), AppError> {
+    let pubkeys = &proof_context.transfer_pubkeys;
+    if pubkeys.source != PodElGamalPubkey::from(*source_elgamal_keypair.pubkey())
+        || pubkeys.destination != *destination_elgamal_pubkey
+    {
+        println!("⛔️ Transfer proofs are for other accounts");
+        return Err(AppError::StaleProofAccounts);
+    }
+
+    // The transfer instruction subtracts the amount ciphertexts from the available balance
+    let source_ciphertext = |ciphertext: &PodTransferAmountCiphertext| {
+        ciphertext
+            .try_extract_ciphertext(0)
+            .map_err(|_| AppError::StaleProofAccounts)
+    };
+    let new_source_ciphertext = subtract_with_lo_hi(
+        &source_account.available_balance,
+        &source_ciphertext(&proof_context.ciphertext_lo)?,
+        &source_ciphertext(&proof_context.ciphertext_hi)?,
+    );
+    if new_source_ciphertext != Some(proof_context.new_source_ciphertext)
+        || !encrypts_amount(
+            &proof_context.new_source_ciphertext,
+            new_available_balance,
+            source_elgamal_keypair,
+        )
+    {
+        println!("⛔️ Transfer proofs are for another balance or amount");
+        return Err(AppError::StaleProofAccounts);
+    }
+
+    Ok(())
+}
+
+/// Checks that verified withdraw proofs withdraw the amount from the current available balance
+pub fn check_withdraw_proof_context(
+    proof_context: &WithdrawProofContext,
+    account: &ConfidentialTransferAccount,
+    elgamal_keypair: &ElGamalKeypair,
+    amount: u64,
+) -> Result<(), AppError> {
+    if proof_context.source_pubkey != PodElGamalPubkey::from(*elgamal_keypair.pubkey())
+        || subtract_from(&account.available_balance, amount)
+            != Some(proof_context.remaining_balance_ciphertext)
+    {
+        println!("⛔️ Withdraw proofs are for another account, balance or amount");
+        return Err(AppError::StaleProofAccounts);
+    }
+
+    Ok(())
+}
+
+/// Handler closing the proof accounts left behind by abandoned transfer and withdraw flows
+///
+/// The proof accounts of the given flows (or of the flow the token account would start next) are
+/// derived again, and those that still exist are closed, returning their rent to the fee payer
+/// of the flow. A context state account only gets an authority once a proof is verified into
+/// it, so allocated but unverified ones first get a placeholder proof with the fee payer as
+/// authority.
+pub async fn recover_proof_accounts_cb(
+    State(state): State<AppState>,
+    Json(request): Json<RecoverProofAccountsRequest>,
+) -> Result<Json<RecoverProofAccountsResponse>, AppError> {
+    println!("🚀 Starting recover_proof_accounts_cb handler");
+
+    let token_account_info = {
+        let token_account_data = state
+            .account_data(
+                request.token_account_data.as_ref(),
+                request.token_account_address.as_ref(),
+                "token_account_data",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(token_account_data)?
+    };
+    let token_account = get_associated_token_address_with_program_id(
+        &token_account_info.base.owner,
+        &token_account_info.base.mint,
+        &spl_token_2022::id(),
+    );
+
+    let elgamal_signature = {
+        let decoded_elgamal_signature = BASE64_STANDARD.decode(&request.elgamal_signature)?;
+        Signature::try_from(decoded_elgamal_signature.as_slice())
+            .map_err(|_| AppError::SerializationError)?
+    };
+
+    // Proof account authority, which receives the rent back (defaults to the fee payer of the
+    // flows, the owner or the first multisig signer)
+    let authority = parse_token_account_authority(
+        &token_account_info.base.owner,
+        request.multisig.as_ref(),
+        request.multisig_signers.as_ref(),
+    )?;
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), authority.fee_payer())?;
+
+    // Every proof account the flows may have created
+    let flow_nonces = match &request.flow_nonces {
+        Some(flow_nonces) => flow_nonces.clone(),
+        None => vec![flow_nonce(
+            None,
+            token_account_info.get_extension::<ConfidentialTransferAccount>()?,
+        )],
+    };
+    let candidates = flow_nonces
+        .iter()
+        .flat_map(|&flow_nonce| {
+            ProofAccount::ALL.iter().map(move |proof_account| {
+                let address = proof_account
+                    .keypair(&elgamal_signature, &token_account, flow_nonce)
+                    .pubkey();
+                (address, *proof_account)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let proof_accounts = state
+        .existing_accounts(
+            request.proof_accounts.as_ref(),
+            &candidates
+                .iter()
+                .map(|(address, _)| *address)
+                .collect::<Vec<_>>(),
+            "proof_accounts",
+        )
+        .await?;
+    println!("✅ Found {} leftover proof accounts", proof_accounts.len());
+
+    // Context state accounts and record accounts are closed by their own programs
+    let mut groups = Vec::new();
+    for (address, proof_account) in &candidates {
+        let Some(data) = proof_accounts.get(address) else {
+            continue;
+        };
+        if !is_verified(data) {
+            if let Some(instruction) =
+                proof_account.placeholder_verify_instruction(address, &fee_payer)?
+            {
+                println!("✅ Verifying a placeholder proof into {}", address);
+                groups.push(vec![instruction]);
+            }
+        }
+        groups.push(vec![proof_account.close_instruction(address, &fee_payer)]);
+    }
+
+    let mut lifetime = TransactionLifetime::with_blockhash(
+        state
+            .latest_blockhash(request.latest_blockhash.as_ref())
+            .await?,
+    );
+    let mut transactions = pack_transactions(&fee_payer, groups, &[], &mut lifetime, &[], None)?;
+
+    // Sign as fee payer if the transactions are sponsored by the server
+    state
+        .co_sign("recover-proof-accounts", 0, &mut transactions)
+        .await?;
+
+    let required_signers = transactions.iter().map(missing_signers).collect();
+    let transactions = transactions
+        .iter()
+        .map(|transaction| Ok(BASE64_STANDARD.encode(bincode::serialize(transaction)?)))
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    Ok(Json(RecoverProofAccountsResponse {
+        message: format!(
+            "{} transactions closing {} leftover proof accounts created successfully",
+            transactions.len(),
+            proof_accounts.len()
+        ),
+        transactions,
+        required_signers,
+        proof_accounts: candidates
+            .iter()
+            .filter(|(address, _)| proof_accounts.contains_key(address))
+            .map(|(address, _)| address.to_string())
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{models::ProofAccountRequest, routes::test_util},
+        bytemuck::Zeroable,
+        solana_sdk::transaction::VersionedTransaction,
+        std::mem::size_of,
+    };
+
+    fn elgamal_signature() -> Signature {
+        Signature::try_from(
+            BASE64_STANDARD
+                .decode(test_util::elgamal_signature())
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_proof_accounts_are_derived_deterministically() {
+        let token_account = Pubkey::new_unique();
+        let keypair = |proof_account: ProofAccount, flow_nonce| {
+            proof_account
+                .keypair(&elgamal_signature(), &token_account, flow_nonce)
+                .pubkey()
+        };
+
+        assert_eq!(
+            keypair(ProofAccount::TransferRange, 1),
+            keypair(ProofAccount::TransferRange, 1)
+        );
+        assert_ne!(
+            keypair(ProofAccount::TransferRange, 1),
+            keypair(ProofAccount::TransferRange, 2)
+        );
+        assert_ne!(
+            keypair(ProofAccount::TransferRange, 1),
+            keypair(ProofAccount::WithdrawRange, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recover_proof_accounts_closes_leftover_accounts() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let fee_payer = Pubkey::new_unique();
+        let token_account =
+            get_associated_token_address_with_program_id(&owner, &mint, &spl_token_2022::id());
+
+        // An abandoned withdraw verified its equality proof, but only allocated its range proof
+        // account, whose data is still zeroed
+        let proof_account = |proof_account: ProofAccount| {
+            proof_account
+                .keypair(&elgamal_signature(), &token_account, 7)
+                .pubkey()
+        };
+        let verified = proof_account(ProofAccount::WithdrawEquality);
+        let unverified = proof_account(ProofAccount::WithdrawRange);
+        let verified_data = ProofContextState::encode(
+            &fee_payer,
+            ProofType::CiphertextCommitmentEquality,
+            &CiphertextCommitmentEqualityProofContext::zeroed(),
+        );
+        let unverified_data = vec![0; size_of::<ProofContextState<BatchedRangeProofContext>>()];
+        let state = test_util::mock_rpc_state(vec![
+            (verified, BASE64_STANDARD.encode(verified_data)),
+            (unverified, BASE64_STANDARD.encode(unverified_data)),
+        ])
+        .await;
+
+        let Json(response) = recover_proof_accounts_cb(
+            State(state),
+            Json(RecoverProofAccountsRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                token_account_data: Some(test_util::confidential_token_account_data(
+                    &owner, &mint, 0, 0, 0,
+                )),
+                token_account_address: None,
+                flow_nonces: Some(vec![7]),
+                proof_accounts: None,
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: Some(fee_payer.to_string()),
+                latest_blockhash: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            response.proof_accounts,
+            vec![verified.to_string(), unverified.to_string()]
+        );
+
+        // The unverified account gets a placeholder proof with the fee payer as authority before
+        // both are closed
+        let instructions = response
+            .transactions
+            .iter()
+            .flat_map(|transaction| {
+                let transaction: VersionedTransaction =
+                    bincode::deserialize(&BASE64_STANDARD.decode(transaction).unwrap()).unwrap();
+                let account_keys = transaction.message.static_account_keys().to_vec();
+                transaction
+                    .message
+                    .instructions()
+                    .iter()
+                    .map(|instruction| {
+                        let accounts = instruction
+                            .accounts
+                            .iter()
+                            .map(|index| account_keys[*index as usize])
+                            .collect::<Vec<_>>();
+                        (
+                            ProofInstruction::instruction_type(&instruction.data).unwrap(),
+                            accounts,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instructions,
+            vec![
+                (
+                    ProofInstruction::CloseContextState,
+                    vec![verified, fee_payer, fee_payer]
+                ),
+                (
+                    ProofInstruction::VerifyBatchedRangeProofU64,
+                    vec![unverified, fee_payer]
+                ),
+                (
+                    ProofInstruction::CloseContextState,
+                    vec![unverified, fee_payer, fee_payer]
+                ),
+            ]
+        );
+        assert!(response
+            .required_signers
+            .iter()
+            .all(|signers| signers == &vec![fee_payer.to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_recover_proof_accounts_defaults_to_the_first_multisig_signer() {
+        let multisig = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let signers = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let token_account =
+            get_associated_token_address_with_program_id(&multisig, &mint, &spl_token_2022::id());
+        let leftover = ProofAccount::TransferRangeRecord
+            .keypair(&elgamal_signature(), &token_account, 7)
+            .pubkey();
+
+        let Json(response) = recover_proof_accounts_cb(
+            State(AppState::default()),
+            Json(RecoverProofAccountsRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                token_account_data: Some(test_util::confidential_token_account_data(
+                    &multisig, &mint, 0, 0, 0,
+                )),
+                token_account_address: None,
+                flow_nonces: Some(vec![7]),
+                proof_accounts: Some(vec![ProofAccountRequest {
+                    address: leftover.to_string(),
+                    data: Some(BASE64_STANDARD.encode([0; 8])),
+                }]),
+                multisig: Some(multisig.to_string()),
+                multisig_signers: Some(signers.iter().map(Pubkey::to_string).collect()),
+                fee_payer: None,
+                latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+
+        // The record account is closed by the proof account authority of the flow
+        assert_eq!(response.proof_accounts, vec![leftover.to_string()]);
+        assert_eq!(
+            response.required_signers,
+            vec![vec![signers[0].to_string()]]
+        );
+    }
+}
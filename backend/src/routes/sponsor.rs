@@ -0,0 +1,33 @@
+use {
+    crate::{models::SponsorInfoResponse, state::AppState},
+    axum::extract::{Json, State},
+};
+
+/// Handler describing the server's fee sponsorship
+///
+/// Clients set the returned `fee_payer` as the `fee_payer` of a sponsored route, and the server
+/// co-signs the generated transactions as fee payer
+pub async fn sponsor_info(State(state): State<AppState>) -> Json<SponsorInfoResponse> {
+    match &state.sponsor {
+        Some(sponsor) => Json(SponsorInfoResponse {
+            fee_payer: Some(sponsor.pubkey().to_string()),
+            routes: sponsor.policy().routes().to_vec(),
+            max_amount: sponsor
+                .policy()
+                .max_amount()
+                .map(|max_amount| max_amount.to_string()),
+            max_lamports: sponsor
+                .policy()
+                .max_lamports()
+                .map(|max_lamports| max_lamports.to_string()),
+            message: "Fee sponsorship is enabled".to_string(),
+        }),
+        None => Json(SponsorInfoResponse {
+            fee_payer: None,
+            routes: Vec::new(),
+            max_amount: None,
+            max_lamports: None,
+            message: "Fee sponsorship is not configured".to_string(),
+        }),
+    }
+}
@@ -0,0 +1,465 @@
+//! Helpers to build account data, a mock RPC node and a stub block engine for route tests
+
+use {
+    crate::{
+        block_engine::BlockEngine,
+        rpc::Rpc,
+        signer::{LocalKeyfileSigner, Sponsor, SponsorshipPolicy},
+        state::AppState,
+    },
+    axum::{extract::State, routing::post, Json, Router},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    serde_json::{json, Value},
+    solana_sdk::{
+        hash::Hash,
+        nonce::state::{DurableNonce, State as NonceState, Versions},
+        packet::PACKET_DATA_SIZE,
+        program_option::COption,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+        transaction::VersionedTransaction,
+    },
+    spl_token_2022::{
+        extension::{
+            confidential_mint_burn::ConfidentialMintBurn,
+            confidential_transfer::{ConfidentialTransferAccount, ConfidentialTransferMint},
+            confidential_transfer_fee::ConfidentialTransferFeeConfig,
+            memo_transfer::MemoTransfer,
+            non_transferable::NonTransferable,
+            transfer_fee::{TransferFee, TransferFeeConfig},
+            BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        },
+        solana_zk_sdk::encryption::{
+            auth_encryption::AeKey, elgamal::ElGamalKeypair, pod::elgamal::PodElGamalPubkey,
+        },
+        state::{Account, AccountState, Mint},
+    },
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// Base64 encoded signature used to derive the test ElGamal keypair
+pub fn elgamal_signature() -> String {
+    BASE64_STANDARD.encode(Signature::from([7; 64]).as_ref())
+}
+
+/// Base64 encoded signature used to derive the test AES key
+pub fn aes_signature() -> String {
+    BASE64_STANDARD.encode(Signature::from([9; 64]).as_ref())
+}
+
+pub fn elgamal_keypair() -> ElGamalKeypair {
+    ElGamalKeypair::new_from_signature(&Signature::from([7; 64])).unwrap()
+}
+
+pub fn aes_key() -> AeKey {
+    AeKey::new_from_signature(&Signature::from([9; 64])).unwrap()
+}
+
+/// Builds base64 encoded token account data with a `ConfidentialTransferAccount` extension
+/// encrypted under the test keys
+pub fn confidential_token_account_data(
+    owner: &Pubkey,
+    mint: &Pubkey,
+    available_balance: u64,
+    pending_balance: u64,
+    pending_balance_credit_counter: u64,
+) -> String {
+    token_account_data(
+        owner,
+        mint,
+        available_balance,
+        pending_balance,
+        pending_balance_credit_counter,
+        false,
+    )
+}
+
+/// Builds base64 encoded data of an empty confidential token account that also requires a memo
+/// on incoming transfers
+pub fn memo_required_token_account_data(owner: &Pubkey, mint: &Pubkey) -> String {
+    token_account_data(owner, mint, 0, 0, 0, true)
+}
+
+fn token_account_data(
+    owner: &Pubkey,
+    mint: &Pubkey,
+    available_balance: u64,
+    pending_balance: u64,
+    pending_balance_credit_counter: u64,
+    require_incoming_transfer_memos: bool,
+) -> String {
+    let elgamal_keypair = elgamal_keypair();
+    let aes_key = aes_key();
+
+    let mut extension_types = vec![ExtensionType::ConfidentialTransferAccount];
+    if require_incoming_transfer_memos {
+        extension_types.push(ExtensionType::MemoTransfer);
+    }
+    let account_len =
+        ExtensionType::try_calculate_account_len::<Account>(&extension_types).unwrap();
+    let mut data = vec![0; account_len];
+    let mut state = StateWithExtensionsMut::<Account>::unpack_uninitialized(&mut data).unwrap();
+
+    state.base = Account {
+        mint: *mint,
+        owner: *owner,
+        amount: 0,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+
+    let extension = state
+        .init_extension::<ConfidentialTransferAccount>(true)
+        .unwrap();
+    extension.approved = true.into();
+    extension.elgamal_pubkey = (*elgamal_keypair.pubkey()).into();
+    extension.pending_balance_lo = elgamal_keypair
+        .pubkey()
+        .encrypt(pending_balance & 0xffff)
+        .into();
+    extension.pending_balance_hi = elgamal_keypair
+        .pubkey()
+        .encrypt(pending_balance >> 16)
+        .into();
+    extension.available_balance = elgamal_keypair.pubkey().encrypt(available_balance).into();
+    extension.decryptable_available_balance = aes_key.encrypt(available_balance).into();
+    extension.allow_confidential_credits = true.into();
+    extension.allow_non_confidential_credits = true.into();
+    extension.pending_balance_credit_counter = pending_balance_credit_counter.into();
+    extension.maximum_pending_balance_credit_counter = 65536.into();
+
+    if require_incoming_transfer_memos {
+        state
+            .init_extension::<MemoTransfer>(true)
+            .unwrap()
+            .require_incoming_transfer_memos = true.into();
+    }
+
+    BASE64_STANDARD.encode(data)
+}
+
+/// Builds base64 encoded mint data with a `ConfidentialTransferMint` extension using the test
+/// ElGamal key as auditor, optionally marked as non-transferable
+pub fn confidential_mint_data(authority: &Pubkey, non_transferable: bool) -> String {
+    mint_data(authority, non_transferable, None, None)
+}
+
+/// Builds base64 encoded data of a confidential mint charging a transfer fee, with the test
+/// ElGamal key as auditor and withdraw withheld authority
+pub fn confidential_fee_mint_data(
+    authority: &Pubkey,
+    transfer_fee_basis_points: u16,
+    maximum_fee: u64,
+) -> String {
+    mint_data(
+        authority,
+        false,
+        Some((transfer_fee_basis_points, maximum_fee)),
+        None,
+    )
+}
+
+/// Builds base64 encoded data of a confidential mint with a `ConfidentialMintBurn` extension,
+/// whose `supply` is encrypted under the test ElGamal and AES keys
+pub fn confidential_mint_burn_mint_data(authority: &Pubkey, supply: u64) -> String {
+    mint_data(authority, false, None, Some(supply))
+}
+
+fn mint_data(
+    authority: &Pubkey,
+    non_transferable: bool,
+    transfer_fee: Option<(u16, u64)>,
+    confidential_supply: Option<u64>,
+) -> String {
+    let mut extension_types = vec![ExtensionType::ConfidentialTransferMint];
+    if non_transferable {
+        extension_types.push(ExtensionType::NonTransferable);
+    }
+    if transfer_fee.is_some() {
+        extension_types.push(ExtensionType::TransferFeeConfig);
+        extension_types.push(ExtensionType::ConfidentialTransferFeeConfig);
+    }
+    if confidential_supply.is_some() {
+        extension_types.push(ExtensionType::ConfidentialMintBurn);
+    }
+
+    let mint_len = ExtensionType::try_calculate_account_len::<Mint>(&extension_types).unwrap();
+    let mut data = vec![0; mint_len];
+    let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+
+    state.base = Mint {
+        mint_authority: COption::Some(*authority),
+        supply: 0,
+        decimals: 2,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+
+    let extension = state
+        .init_extension::<ConfidentialTransferMint>(true)
+        .unwrap();
+    extension.authority = Some(*authority).try_into().unwrap();
+    extension.auto_approve_new_accounts = true.into();
+    extension.auditor_elgamal_pubkey = Some(PodElGamalPubkey::from(*elgamal_keypair().pubkey()))
+        .try_into()
+        .unwrap();
+
+    if non_transferable {
+        state.init_extension::<NonTransferable>(true).unwrap();
+    }
+
+    if let Some((transfer_fee_basis_points, maximum_fee)) = transfer_fee {
+        let transfer_fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: maximum_fee.into(),
+            transfer_fee_basis_points: transfer_fee_basis_points.into(),
+        };
+        let extension = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        extension.transfer_fee_config_authority = Some(*authority).try_into().unwrap();
+        extension.withdraw_withheld_authority = Some(*authority).try_into().unwrap();
+        extension.older_transfer_fee = transfer_fee;
+        extension.newer_transfer_fee = transfer_fee;
+
+        let extension = state
+            .init_extension::<ConfidentialTransferFeeConfig>(true)
+            .unwrap();
+        extension.authority = Some(*authority).try_into().unwrap();
+        extension.withdraw_withheld_authority_elgamal_pubkey =
+            PodElGamalPubkey::from(*elgamal_keypair().pubkey());
+    }
+
+    if let Some(confidential_supply) = confidential_supply {
+        let elgamal_keypair = elgamal_keypair();
+        let extension = state.init_extension::<ConfidentialMintBurn>(true).unwrap();
+        extension.confidential_supply =
+            elgamal_keypair.pubkey().encrypt(confidential_supply).into();
+        extension.decryptable_supply = aes_key().encrypt(confidential_supply).into();
+        extension.supply_elgamal_pubkey = (*elgamal_keypair.pubkey()).into();
+    }
+
+    BASE64_STANDARD.encode(data)
+}
+
+/// Builds base64 encoded data of a nonce account controlled by `authority`, holding the nonce
+/// derived from `blockhash`
+pub fn nonce_account_data(authority: &Pubkey, blockhash: &Hash) -> String {
+    let state = Versions::new(NonceState::new_initialized(
+        authority,
+        DurableNonce::from_blockhash(blockhash),
+        5_000,
+    ));
+
+    BASE64_STANDARD.encode(bincode::serialize(&state).unwrap())
+}
+
+/// Decodes a base64 encoded transaction of a response into the program ids and data of its
+/// instructions, checking that it fits in a packet
+pub fn decode_transaction(transaction: &str) -> Vec<(Pubkey, Vec<u8>)> {
+    let bytes = BASE64_STANDARD.decode(transaction).unwrap();
+    assert!(bytes.len() <= PACKET_DATA_SIZE);
+
+    let transaction: VersionedTransaction = bincode::deserialize(&bytes).unwrap();
+    let account_keys = transaction.message.static_account_keys();
+    transaction
+        .message
+        .instructions()
+        .iter()
+        .map(|instruction| {
+            (
+                account_keys[instruction.program_id_index as usize],
+                instruction.data.clone(),
+            )
+        })
+        .collect()
+}
+
+/// Blockhash returned by the mock RPC node
+pub fn mock_blockhash() -> Hash {
+    Hash::new_from_array([3; 32])
+}
+
+/// Rent-exempt minimum per byte of account data returned by the mock RPC node
+pub const MOCK_RENT_PER_BYTE: u64 = 10;
+
+/// Epoch returned by the mock RPC node
+pub const MOCK_EPOCH: u64 = 42;
+
+// Answers the JSON-RPC methods used by `Rpc` from a fixed set of base64 encoded accounts
+async fn mock_rpc_handler(
+    State(accounts): State<Arc<HashMap<String, String>>>,
+    Json(request): Json<Value>,
+) -> Json<Value> {
+    let slot_context = json!({ "slot": 1 });
+    let result = match request["method"].as_str().unwrap_or_default() {
+        "getAccountInfo" => {
+            let address = request["params"][0].as_str().unwrap_or_default();
+            let value = accounts.get(address).map(|data| {
+                json!({
+                    "data": [data, "base64"],
+                    "executable": false,
+                    "lamports": 1_000_000,
+                    "owner": spl_token_2022::id().to_string(),
+                    "rentEpoch": 0,
+                    "space": BASE64_STANDARD.decode(data).unwrap().len(),
+                })
+            });
+            json!({ "context": slot_context, "value": value })
+        }
+        "getMultipleAccounts" => {
+            let values = request["params"][0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|address| {
+                    accounts.get(address.as_str().unwrap()).map(|data| {
+                        json!({
+                            "data": [data, "base64"],
+                            "executable": false,
+                            "lamports": 1_000_000,
+                            "owner": spl_token_2022::id().to_string(),
+                            "rentEpoch": 0,
+                            "space": BASE64_STANDARD.decode(data).unwrap().len(),
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({ "context": slot_context, "value": values })
+        }
+        "getLatestBlockhash" => json!({
+            "context": slot_context,
+            "value": {
+                "blockhash": mock_blockhash().to_string(),
+                "lastValidBlockHeight": 100,
+            },
+        }),
+        "getMinimumBalanceForRentExemption" => {
+            json!(request["params"][0].as_u64().unwrap_or_default() * MOCK_RENT_PER_BYTE)
+        }
+        "getEpochInfo" => json!({
+            "absoluteSlot": 1,
+            "blockHeight": 1,
+            "epoch": MOCK_EPOCH,
+            "slotIndex": 1,
+            "slotsInEpoch": 432_000,
+            "transactionCount": null,
+        }),
+        "getVersion" => json!({ "solana-core": "2.1.11", "feature-set": 0 }),
+        method => {
+            return Json(json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": -32601, "message": format!("Method not found: {}", method) },
+            }))
+        }
+    };
+
+    Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+}
+
+/// Starts a local mock RPC node serving the given base64 encoded accounts and returns an
+/// `AppState` that fetches from it
+pub async fn mock_rpc_state(accounts: Vec<(Pubkey, String)>) -> AppState {
+    let accounts = accounts
+        .into_iter()
+        .map(|(address, data)| (address.to_string(), data))
+        .collect::<HashMap<_, _>>();
+    let app = Router::new()
+        .route("/", post(mock_rpc_handler))
+        .with_state(Arc::new(accounts));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    AppState {
+        rpc: Some(Arc::new(Rpc::new(url))),
+        ..AppState::default()
+    }
+}
+
+/// Returns `state` with a sponsor key sponsoring `routes`, along with the sponsor keypair
+pub fn sponsored_state(state: AppState, routes: &[&str]) -> (AppState, Keypair) {
+    let keypair = Keypair::new();
+    let policy = SponsorshipPolicy::new(
+        routes.iter().map(|route| route.to_string()).collect(),
+        None,
+        None,
+    );
+    let state = AppState {
+        sponsor: Some(Arc::new(Sponsor::new(
+            Box::new(LocalKeyfileSigner::new(keypair.insecure_clone())),
+            policy,
+        ))),
+        ..state
+    };
+    (state, keypair)
+}
+
+/// Tip account returned by the stub block engine
+pub fn mock_tip_account() -> Pubkey {
+    Pubkey::new_from_array([7; 32])
+}
+
+/// Bundle id returned by the stub block engine
+pub const MOCK_BUNDLE_ID: &str = "mock-bundle-id";
+
+// Bundles received by the stub block engine, as their base58 encoded transactions
+type SubmittedBundles = Arc<Mutex<Vec<Vec<String>>>>;
+
+// Answers the JSON-RPC methods of the block engine bundle API, recording submitted bundles
+async fn mock_block_engine_handler(
+    State(bundles): State<SubmittedBundles>,
+    Json(request): Json<Value>,
+) -> Json<Value> {
+    let result = match request["method"].as_str().unwrap_or_default() {
+        "getTipAccounts" => json!([mock_tip_account().to_string()]),
+        "sendBundle" => {
+            let transactions = request["params"][0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|transaction| transaction.as_str().unwrap().to_string())
+                .collect();
+            bundles.lock().unwrap().push(transactions);
+            json!(MOCK_BUNDLE_ID)
+        }
+        method => {
+            return Json(json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": -32601, "message": format!("Method not found: {}", method) },
+            }))
+        }
+    };
+
+    Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+}
+
+/// Starts a local stub block engine and returns an `AppState` submitting bundles to it, along
+/// with the bundles it receives
+pub async fn mock_block_engine_state() -> (AppState, SubmittedBundles) {
+    let bundles = SubmittedBundles::default();
+    let app = Router::new()
+        .route("/api/v1/bundles", post(mock_block_engine_handler))
+        .with_state(bundles.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/api/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let state = AppState {
+        block_engine: Some(Arc::new(BlockEngine::new(&url, None))),
+        ..AppState::default()
+    };
+    (state, bundles)
+}
@@ -2,25 +2,29 @@ use {
     crate::{
         errors::AppError,
         models::{MultiTransactionResponse, TransferCbRequest},
-        routes::util::parse_latest_blockhash,
+        routes::{
+            bundle::{check_bundle_size, tip_instruction},
+            check_recipient::check_recipient_readiness,
+            nonce::TransactionLifetime,
+            packer::{fits_in_transaction, pack_transactions},
+            priority_fee::PriorityFee,
+            proof_accounts::{
+                check_transfer_proof_context, flow_nonce, ExistingProofAccounts, ProofAccount,
+            },
+            util::{
+                check_sender_balance, get_zk_proof_context_state_account_creation_instructions,
+                get_zk_proof_record_verification_instructions, missing_signers, parse_fee_payer,
+                parse_token_account_authority, proof_context_state_space, proof_record_space,
+                with_memo,
+            },
+        },
+        state::AppState,
     },
-    axum::extract::Json,
+    axum::extract::{Json, State},
     base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
     bincode,
-    solana_sdk::{
-        instruction::Instruction,
-        message::{v0, VersionedMessage},
-        pubkey::Pubkey,
-        signature::{Keypair, NullSigner, Signature},
-        signer::Signer,
-        system_instruction,
-        transaction::VersionedTransaction,
-    },
-    solana_zk_sdk::zk_elgamal_proof_program::{
-        instruction::{close_context_state, ContextStateInfo},
-        proof_data::ZkProofData,
-        state::ProofContextState,
-    },
+    solana_sdk::{signature::Signature, signer::Signer},
+    solana_zk_sdk::zk_elgamal_proof_program::instruction::{close_context_state, ContextStateInfo},
     spl_associated_token_account::get_associated_token_address_with_program_id,
     spl_token_2022::{
         error::TokenError,
@@ -29,21 +33,24 @@ use {
                 account_info::TransferAccountInfo, instruction::transfer,
                 ConfidentialTransferAccount,
             },
+            memo_transfer::memo_required,
             BaseStateWithExtensions, StateWithExtensionsOwned,
         },
         solana_zk_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
     },
-    spl_token_confidential_transfer_proof_extraction::instruction::{
-        zk_proof_type_to_instruction, ProofLocation,
+    spl_token_confidential_transfer_proof_extraction::{
+        encryption::PodTransferAmountCiphertext,
+        instruction::{ProofData, ProofLocation},
     },
     spl_token_confidential_transfer_proof_generation::transfer::TransferProofData,
-    std::mem::size_of,
+    std::num::NonZeroI8,
 };
 
 /// Handler for the transfer-cb endpoint
 ///
 /// This endpoint creates a transaction to transfer tokens between confidential token accounts
 pub async fn transfer_cb(
+    State(state): State<AppState>,
     Json(request): Json<TransferCbRequest>,
 ) -> Result<Json<MultiTransactionResponse>, AppError> {
     println!("📝 Processing transfer-cb request");
@@ -59,24 +66,16 @@ pub async fn transfer_cb(
         transfer_amount_lamports
     );
 
-    // Parse rent values for proof account creation
-    let equality_proof_rent = request
-        .equality_proof_rent
-        .parse::<u64>()
-        .map_err(|_| AppError::SerializationError)?;
-    let ciphertext_validity_proof_rent = request
-        .ciphertext_validity_proof_rent
-        .parse::<u64>()
-        .map_err(|_| AppError::SerializationError)?;
-    let range_proof_rent = request
-        .range_proof_rent
-        .parse::<u64>()
-        .map_err(|_| AppError::SerializationError)?;
-
     // Decode sender token account data from request
     println!("📦 Decoding sender token account data from request");
     let sender_token_account_info = {
-        let sender_token_account_data = BASE64_STANDARD.decode(&request.sender_token_account)?;
+        let sender_token_account_data = state
+            .account_data(
+                request.sender_token_account.as_ref(),
+                request.sender_token_account_address.as_ref(),
+                "sender_token_account",
+            )
+            .await?;
         StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(
             sender_token_account_data,
         )?
@@ -89,8 +88,13 @@ pub async fn transfer_cb(
     // Decode recipient token account data from request
     println!("📦 Decoding recipient token account data from request");
     let recipient_token_account_info = {
-        let recipient_token_account_data =
-            BASE64_STANDARD.decode(&request.recipient_token_account)?;
+        let recipient_token_account_data = state
+            .account_data(
+                request.recipient_token_account.as_ref(),
+                request.recipient_token_account_address.as_ref(),
+                "recipient_token_account",
+            )
+            .await?;
         StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(
             recipient_token_account_data,
         )?
@@ -100,6 +104,16 @@ pub async fn transfer_cb(
         recipient_token_account_info.base.owner.to_string()
     );
 
+    // Reject early if the recipient can't receive, before generating the (slow) proofs
+    check_recipient_readiness(&recipient_token_account_info, 1)?;
+    if memo_required(&recipient_token_account_info) && request.memo.is_none() {
+        println!(
+            "⛔️ Recipient account of {} requires a memo",
+            recipient_token_account_info.base.owner
+        );
+        return Err(AppError::RecipientMemoRequired);
+    }
+
     // Verify that both accounts reference the same mint
     let mint = {
         let sender_mint = sender_token_account_info.base.mint;
@@ -119,6 +133,13 @@ pub async fn transfer_cb(
 
     // Get the sender token account pubkey
     let sender_ata_authority = sender_token_account_info.base.owner;
+    let sender_authority = parse_token_account_authority(
+        &sender_ata_authority,
+        request.multisig.as_ref(),
+        request.multisig_signers.as_ref(),
+    )?;
+    // Pays for every transaction (the sender, or the first signer of a multisig sender)
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), sender_authority.fee_payer())?;
     let sender_token_account = get_associated_token_address_with_program_id(
         &sender_ata_authority,
         &mint,
@@ -141,19 +162,14 @@ pub async fn transfer_cb(
         recipient_token_account
     );
 
-    // Must first create 3 accounts to store proofs before transferring tokens
-    // This must be done in a separate transactions because the proofs are too large for single transaction:
+    // The transfer needs 3 proofs, which are too large to all fit in the transfer transaction.
+    // Those that do not fit are first verified into proof accounts in separate transactions:
     // Equality Proof - prove that two ciphertexts encrypt the same value
     // Ciphertext Validity Proof - prove that ciphertexts are properly generated
     // Range Proof - prove that ciphertexts encrypt a value in a specified range (0, u64::MAX)
 
     // "Authority" for the proof accounts (to close the accounts after the transfer)
-    let context_state_authority = &sender_ata_authority;
-
-    // Generate addresses for proof accounts
-    let equality_proof_context_state_account = Keypair::new();
-    let ciphertext_validity_proof_context_state_account = Keypair::new();
-    let range_proof_context_state_account = Keypair::new();
+    let context_state_authority = &fee_payer;
 
     // ConfidentialTransferAccount extension information needed to create proof data
     let sender_transfer_account_info = {
@@ -171,7 +187,13 @@ pub async fn transfer_cb(
 
     // Get auditor ElGamal pubkey from the mint account data
     let auditor_elgamal_pubkey_option = {
-        let mint_account_data = BASE64_STANDARD.decode(&request.mint_token_account)?;
+        let mint_account_data = state
+            .mint_data(
+                request.mint_token_account.as_ref(),
+                &mint,
+                "mint_token_account",
+            )
+            .await?;
 
         Option::<solana_zk_sdk::encryption::pod::elgamal::PodElGamalPubkey>::from(
             StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(mint_account_data)?
@@ -184,7 +206,7 @@ pub async fn transfer_cb(
 
     // Create the ElGamal keypair and AES key for the sender token account
     // Create the sender's ElGamal keypair in a temporary scope
-    let sender_elgamal_keypair = {
+    let elgamal_signature = {
         println!(
             "🔐 Decoding ElGamal signature: {}",
             request.elgamal_signature
@@ -200,9 +222,10 @@ pub async fn transfer_cb(
             .map_err(|_| AppError::SerializationError)?;
         println!("✅ ElGamal signature created successfully");
 
-        ElGamalKeypair::new_from_signature(&elgamal_signature)
-            .map_err(|_| AppError::SerializationError)?
+        elgamal_signature
     };
+    let sender_elgamal_keypair = ElGamalKeypair::new_from_signature(&elgamal_signature)
+        .map_err(|_| AppError::SerializationError)?;
     println!("✅ ElGamal keypair created successfully");
 
     // Create the sender's AES key in a temporary scope
@@ -223,6 +246,53 @@ pub async fn transfer_cb(
     };
     println!("✅ AES key created successfully");
 
+    // Derive the addresses of the proof accounts, so that those of an abandoned flow can be
+    // recovered
+    let flow_nonce = flow_nonce(
+        request.flow_nonce,
+        sender_token_account_info.get_extension::<ConfidentialTransferAccount>()?,
+    );
+    let proof_account_keypair = |proof_account: ProofAccount| {
+        proof_account.keypair(&elgamal_signature, &sender_token_account, flow_nonce)
+    };
+    let equality_proof_context_state_account =
+        proof_account_keypair(ProofAccount::TransferEquality);
+    let ciphertext_validity_proof_context_state_account =
+        proof_account_keypair(ProofAccount::TransferCiphertextValidity);
+    let range_proof_context_state_account = proof_account_keypair(ProofAccount::TransferRange);
+    let range_proof_record_account = proof_account_keypair(ProofAccount::TransferRangeRecord);
+    let equality_proof_context_state_address = equality_proof_context_state_account.pubkey();
+    let ciphertext_validity_proof_context_state_address =
+        ciphertext_validity_proof_context_state_account.pubkey();
+    let range_proof_context_state_address = range_proof_context_state_account.pubkey();
+    let range_proof_record_address = range_proof_record_account.pubkey();
+
+    // Proof accounts of an interrupted attempt, to return only its missing transactions
+    let mut existing_proof_accounts = ExistingProofAccounts::from_request(
+        &state,
+        request.resume.as_ref(),
+        &[
+            equality_proof_context_state_address,
+            ciphertext_validity_proof_context_state_address,
+            range_proof_context_state_address,
+            range_proof_record_address,
+        ],
+    )
+    .await?;
+    let discarded_proof_accounts = existing_proof_accounts.discard_partially_verified(&[
+        &equality_proof_context_state_address,
+        &ciphertext_validity_proof_context_state_address,
+        &range_proof_context_state_address,
+    ]);
+
+    // Fail with the balances instead of a generic proof generation error
+    let available_balance = check_sender_balance(
+        sender_token_account_info.get_extension::<ConfidentialTransferAccount>()?,
+        &sender_elgamal_keypair,
+        &sender_aes_key,
+        transfer_amount_lamports,
+    )?;
+
     // Generate proof data
     let TransferProofData {
         equality_proof_data,
@@ -236,12 +306,32 @@ pub async fn transfer_cb(
         auditor_elgamal_pubkey_option.as_ref(),
     )?;
 
+    // Use the proof account rents from the request, or the rent-exempt minimums
+    let range_proof_rent = state
+        .rent(
+            request.range_proof_rent.as_ref(),
+            proof_context_state_space(&range_proof_data),
+        )
+        .await?;
+    let equality_proof_rent = state
+        .rent(
+            request.equality_proof_rent.as_ref(),
+            proof_context_state_space(&equality_proof_data),
+        )
+        .await?;
+    let ciphertext_validity_proof_rent = state
+        .rent(
+            request.ciphertext_validity_proof_rent.as_ref(),
+            proof_context_state_space(&ciphertext_validity_proof_data_with_ciphertext.proof_data),
+        )
+        .await?;
+
     // Create 3 proofs ------------------------------------------------------
 
     // Range Proof Instructions------------------------------------------------------------------------------
     let (range_create_ix, range_verify_ix) =
         get_zk_proof_context_state_account_creation_instructions(
-            &sender_ata_authority,
+            &fee_payer,
             &range_proof_context_state_account.pubkey(),
             &context_state_authority,
             &range_proof_data,
@@ -251,7 +341,7 @@ pub async fn transfer_cb(
     // Equality Proof Instructions---------------------------------------------------------------------------
     let (equality_create_ix, equality_verify_ix) =
         get_zk_proof_context_state_account_creation_instructions(
-            &sender_ata_authority,
+            &fee_payer,
             &equality_proof_context_state_account.pubkey(),
             &context_state_authority,
             &equality_proof_data,
@@ -260,7 +350,7 @@ pub async fn transfer_cb(
 
     // Ciphertext Validity Proof Instructions ----------------------------------------------------------------
     let (cv_create_ix, cv_verify_ix) = get_zk_proof_context_state_account_creation_instructions(
-        &sender_ata_authority,
+        &fee_payer,
         &ciphertext_validity_proof_context_state_account.pubkey(),
         &context_state_authority,
         &ciphertext_validity_proof_data_with_ciphertext.proof_data,
@@ -268,198 +358,271 @@ pub async fn transfer_cb(
     )?;
 
     // Transact Proofs ------------------------------------------------------------------------------------
-    let client_blockhash = parse_latest_blockhash(&request.latest_blockhash)?;
-
-    // Parse priority fee
-    let priority_fee = match request.priority_fee.parse::<u64>() {
-        Ok(fee) => fee,
-        Err(_) => {
-            println!(
-                "⚠️ Invalid priority fee format: {}, defaulting to 0",
-                request.priority_fee
-            );
-            0
-        }
-    };
-
-    // Transaction 1: Allocate all proof accounts at once.
-    let tx1 = {
-        // Create instructions vector
-        let mut instructions = Vec::new();
-
-        // Add priority fee instructions if the fee is greater than 0
-        if priority_fee > 0 {
-            // Convert lamports to micro-lamports per compute unit
-            // For example, 10,000,000 lamports with 200,000 compute units = 50,000 micro-lamports per CU
-            let micro_lamports = priority_fee * 1_000_000 / 200_000;
-
-            // Add compute budget program instructions
-            let compute_budget_program_id = solana_sdk::compute_budget::id();
-
-            // Set compute unit limit (optional but recommended)
-            instructions.push(solana_sdk::instruction::Instruction::new_with_borsh(
-                compute_budget_program_id,
-                &solana_sdk::compute_budget::ComputeBudgetInstruction::SetComputeUnitLimit(200_000),
-                vec![],
-            ));
-
-            // Set compute unit price (priority fee)
-            instructions.push(solana_sdk::instruction::Instruction::new_with_borsh(
-                compute_budget_program_id,
-                &solana_sdk::compute_budget::ComputeBudgetInstruction::SetComputeUnitPrice(
-                    micro_lamports,
-                ),
-                vec![],
-            ));
-        }
-
-        // Add the original instructions
-        instructions.push(range_create_ix.clone());
-        instructions.push(equality_create_ix.clone());
-        instructions.push(cv_create_ix.clone());
-
-        // Rest of the code remains the same...
-        let message =
-            v0::Message::try_compile(&sender_ata_authority, &instructions, &[], client_blockhash)?;
-
-        // Create a versioned message
-        let versioned_message = VersionedMessage::V0(message.clone());
-
-        VersionedTransaction::try_new(
-            versioned_message,
-            &[
-                &NullSigner::new(&sender_ata_authority) as &dyn Signer,
-                &range_proof_context_state_account,
-                &equality_proof_context_state_account,
-                &ciphertext_validity_proof_context_state_account,
-            ],
+    // Transactions with room for it advance their own durable nonce if nonce accounts are given,
+    // so that slow approvals cannot expire the flow halfway through
+    let mut lifetime = TransactionLifetime::from_request(
+        &state,
+        request.latest_blockhash.as_ref(),
+        request.nonce_accounts.as_ref(),
+        request.nonce_account_data.as_ref(),
+        request.nonce_authority.as_ref(),
+        fee_payer,
+    )
+    .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+
+    // With durable nonces, the range proof is verified from a record account instead
+    let range_proof_record =
+        lifetime.is_durable() || existing_proof_accounts.exists(&range_proof_record_address);
+    let range_verify_groups = if range_proof_record {
+        let range_proof_record_rent = if existing_proof_accounts.exists(&range_proof_record_address)
+        {
+            None
+        } else {
+            Some(
+                state
+                    .rent(None, proof_record_space(&range_proof_data))
+                    .await?,
+            )
+        };
+        get_zk_proof_record_verification_instructions(
+            &fee_payer,
+            &range_proof_record_address,
+            &range_proof_context_state_address,
+            context_state_authority,
+            &range_proof_data,
+            range_proof_record_rent,
         )?
+    } else {
+        vec![vec![range_verify_ix]]
     };
 
-    // Transaction 2: Encode Range Proof on its own (because it's the largest).
-    let tx2 = {
-        let message = v0::Message::try_compile(
-            &sender_ata_authority,
-            &[range_verify_ix],
-            &[],
-            client_blockhash,
-        )?;
-
-        // Create a versioned transaction with a placeholder signature for the sender
-        VersionedTransaction {
-            // Single placeholder signature for the sender as the fee payer.
-            signatures: vec![Signature::default()],
-            message: VersionedMessage::V0(message),
-        }
-    };
-
-    // Transaction 3: Encode all remaining proofs.
-    let tx3 = {
-        let message = v0::Message::try_compile(
-            &sender_ata_authority,
-            &[equality_verify_ix, cv_verify_ix],
-            &[],
-            client_blockhash,
-        )?;
+    // The priority fee policy, or else the legacy lamport budget of the whole flow
+    let priority_fee = PriorityFee::from_legacy_request(
+        request.priority_fee_policy.as_ref(),
+        &request.priority_fee,
+    )?;
 
-        // Create a versioned transaction with a placeholder signature for the sender
-        VersionedTransaction {
-            // Single placeholder signature for the sender
-            signatures: vec![Signature::default()],
-            message: VersionedMessage::V0(message),
+    // Transfer with Split Proofs -------------------------------------------
+    let new_decryptable_available_balance = sender_transfer_account_info
+        .new_decryptable_available_balance(transfer_amount_lamports, &sender_aes_key)
+        .map_err(|_| TokenError::AccountDecryption)?
+        .into();
+
+    // Resumed with verified proofs, the transfer carries the auditor ciphertexts they hold instead
+    // of the regenerated ones
+    let (auditor_ciphertext_lo, auditor_ciphertext_hi) = match existing_proof_accounts
+        .transfer_proof_context(
+            &equality_proof_context_state_address,
+            &ciphertext_validity_proof_context_state_address,
+            &range_proof_context_state_address,
+            context_state_authority,
+        )? {
+        Some(proof_context) => {
+            check_transfer_proof_context(
+                &proof_context,
+                sender_token_account_info.get_extension::<ConfidentialTransferAccount>()?,
+                &sender_elgamal_keypair,
+                &recipient_elgamal_pubkey.into(),
+                available_balance - transfer_amount_lamports,
+            )?;
+            let auditor_ciphertext = |ciphertext: &PodTransferAmountCiphertext| {
+                ciphertext
+                    .try_extract_ciphertext(2)
+                    .map_err(|_| AppError::StaleProofAccounts)
+            };
+            (
+                auditor_ciphertext(&proof_context.ciphertext_lo)?,
+                auditor_ciphertext(&proof_context.ciphertext_hi)?,
+            )
         }
+        None => (
+            ciphertext_validity_proof_data_with_ciphertext.ciphertext_lo,
+            ciphertext_validity_proof_data_with_ciphertext.ciphertext_hi,
+        ),
     };
 
-    // Transaction 4: Execute transfer (below)
-    // Transfer with Split Proofs -------------------------------------------
-    let tx4 = {
-        let new_decryptable_available_balance = sender_transfer_account_info
-            .new_decryptable_available_balance(transfer_amount_lamports, &sender_aes_key)
-            .map_err(|_| TokenError::AccountDecryption)?
-            .into();
+    // The equality and ciphertext validity proofs are verified in the transfer transaction (at an
+    // instruction offset) if they fit there, and pre-verified into context state accounts
+    // otherwise. The range proof never fits, and a proof account left by an interrupted attempt is
+    // always used.
+    let transfer_instructions = |inline_equality_proof: bool, inline_validity_proof: bool| {
+        let mut next_offset = 1;
+        let mut next_instruction_offset = || {
+            let offset = NonZeroI8::new(next_offset).unwrap();
+            next_offset += 1;
+            offset
+        };
+        let equality_proof_location = if inline_equality_proof {
+            ProofLocation::InstructionOffset(
+                next_instruction_offset(),
+                ProofData::InstructionData(&equality_proof_data),
+            )
+        } else {
+            ProofLocation::ContextStateAccount(&equality_proof_context_state_address)
+        };
+        let validity_proof_location = if inline_validity_proof {
+            ProofLocation::InstructionOffset(
+                next_instruction_offset(),
+                ProofData::InstructionData(
+                    &ciphertext_validity_proof_data_with_ciphertext.proof_data,
+                ),
+            )
+        } else {
+            ProofLocation::ContextStateAccount(&ciphertext_validity_proof_context_state_address)
+        };
 
-        let instructions = transfer(
+        transfer(
             &spl_token_2022::id(),
             &sender_token_account,
             &mint,
             &recipient_token_account,
             &new_decryptable_available_balance,
-            &ciphertext_validity_proof_data_with_ciphertext.ciphertext_lo,
-            &ciphertext_validity_proof_data_with_ciphertext.ciphertext_hi,
+            &auditor_ciphertext_lo,
+            &auditor_ciphertext_hi,
             &sender_ata_authority,
-            &vec![],
-            ProofLocation::ContextStateAccount(&equality_proof_context_state_account.pubkey()),
-            ProofLocation::ContextStateAccount(
-                &ciphertext_validity_proof_context_state_account.pubkey(),
-            ),
-            ProofLocation::ContextStateAccount(&range_proof_context_state_account.pubkey()),
+            &sender_authority.signers(),
+            equality_proof_location,
+            validity_proof_location,
+            ProofLocation::ContextStateAccount(&range_proof_context_state_address),
+        )
+        .map(|instructions| with_memo(request.memo.as_ref(), instructions))
+    };
+    let inline_equality_proof = !existing_proof_accounts
+        .exists(&equality_proof_context_state_address)
+        && fits_in_transaction(
+            &fee_payer,
+            &transfer_instructions(true, false)?,
+            &lifetime,
+            &lookup_tables,
+            priority_fee.as_ref(),
         )?;
+    let inline_validity_proof = !existing_proof_accounts
+        .exists(&ciphertext_validity_proof_context_state_address)
+        && fits_in_transaction(
+            &fee_payer,
+            &transfer_instructions(inline_equality_proof, true)?,
+            &lifetime,
+            &lookup_tables,
+            priority_fee.as_ref(),
+        )?;
+    println!(
+        "✅ Proofs verified in the transfer transaction: equality {}, ciphertext validity {}",
+        inline_equality_proof, inline_validity_proof
+    );
 
-        let message =
-            v0::Message::try_compile(&sender_ata_authority, &instructions, &[], client_blockhash)?;
+    // Create the context state accounts for the other proofs, in the order they are verified
+    let mut context_state_proofs = vec![(
+        &range_proof_context_state_account,
+        range_create_ix,
+        range_verify_groups,
+    )];
+    if !inline_equality_proof {
+        context_state_proofs.push((
+            &equality_proof_context_state_account,
+            equality_create_ix,
+            vec![vec![equality_verify_ix]],
+        ));
+    }
+    if !inline_validity_proof {
+        context_state_proofs.push((
+            &ciphertext_validity_proof_context_state_account,
+            cv_create_ix,
+            vec![vec![cv_verify_ix]],
+        ));
+    }
+
+    // Close the proof accounts an interrupted attempt only partially verified, allocate the proof
+    // accounts, then verify the proofs into them, skipping the steps that attempt already took
+    let mut groups = discarded_proof_accounts
+        .iter()
+        .map(|context_state_account| {
+            vec![close_context_state(
+                ContextStateInfo {
+                    context_state_account,
+                    context_state_authority,
+                },
+                &fee_payer,
+            )]
+        })
+        .collect::<Vec<_>>();
+    groups.extend(
+        context_state_proofs
+            .iter()
+            .filter(|(keypair, _, _)| !existing_proof_accounts.exists(&keypair.pubkey()))
+            .map(|(_, create_ix, _)| vec![create_ix.clone()]),
+    );
+    groups.extend(
+        context_state_proofs
+            .iter()
+            .filter(|(keypair, _, _)| !existing_proof_accounts.is_verified(&keypair.pubkey()))
+            .flat_map(|(_, _, verify_groups)| verify_groups.clone()),
+    );
 
-        // Create a versioned transaction with a placeholder signature for the sender
-        VersionedTransaction {
-            // Single placeholder signature for the sender
-            signatures: vec![Signature::default()],
-            message: VersionedMessage::V0(message),
-        }
-    };
+    // Execute the transfer along with its inline proofs
+    groups.push(transfer_instructions(
+        inline_equality_proof,
+        inline_validity_proof,
+    )?);
 
-    // Transaction 5: (below)
     // Close Proof Accounts --------------------------------------------------
-    let tx5 = {
-        // Lamports from the closed proof accounts will be sent to this account
-        let destination_account = &sender_ata_authority;
-
-        // Close the equality proof account
-        let close_equality_proof_instruction = close_context_state(
-            ContextStateInfo {
-                context_state_account: &equality_proof_context_state_account.pubkey(),
-                context_state_authority: &context_state_authority,
-            },
-            &destination_account,
-        );
-
-        // Close the ciphertext validity proof account
-        let close_ciphertext_validity_proof_instruction = close_context_state(
-            ContextStateInfo {
-                context_state_account: &ciphertext_validity_proof_context_state_account.pubkey(),
-                context_state_authority: &context_state_authority,
-            },
-            &destination_account,
-        );
-
-        // Close the range proof account
-        let close_range_proof_instruction = close_context_state(
-            ContextStateInfo {
-                context_state_account: &range_proof_context_state_account.pubkey(),
-                context_state_authority: &context_state_authority,
-            },
-            &destination_account,
-        );
+    // Lamports from the closed proof accounts will be sent to this account
+    let destination_account = &fee_payer;
+    groups.extend(
+        context_state_proofs
+            .iter()
+            .map(|(context_state_account, _, _)| {
+                vec![close_context_state(
+                    ContextStateInfo {
+                        context_state_account: &context_state_account.pubkey(),
+                        context_state_authority,
+                    },
+                    destination_account,
+                )]
+            }),
+    );
 
-        let message = v0::Message::try_compile(
-            &sender_ata_authority,
-            &[
-                close_equality_proof_instruction,
-                close_ciphertext_validity_proof_instruction,
-                close_range_proof_instruction,
-            ],
-            &[],
-            client_blockhash,
-        )?;
+    // In bundle mode, the last transaction tips the block engine to land the flow atomically
+    if let Some(tip_instruction) = tip_instruction(
+        &state,
+        &fee_payer,
+        &sender_authority.fee_payer(),
+        request.bundle.as_ref(),
+    )
+    .await?
+    {
+        groups.push(vec![tip_instruction]);
+    }
+
+    // Fill as few transactions as possible, with placeholder signatures for the fee payer and,
+    // for a multisig sender, each of its signers
+    let mut proof_account_keypairs = context_state_proofs
+        .iter()
+        .map(|(context_state_account, _, _)| *context_state_account)
+        .collect::<Vec<_>>();
+    if range_proof_record {
+        proof_account_keypairs.push(&range_proof_record_account);
+    }
+    let mut transactions = pack_transactions(
+        &fee_payer,
+        groups,
+        &proof_account_keypairs,
+        &mut lifetime,
+        &lookup_tables,
+        priority_fee.as_ref(),
+    )?;
+    if request.bundle.is_some() {
+        check_bundle_size(transactions.len())?;
+    }
 
-        // Create a versioned transaction with a placeholder signature for the sender
-        VersionedTransaction {
-            // Single placeholder signature for the sender
-            signatures: vec![Signature::default()],
-            message: VersionedMessage::V0(message),
-        }
-    };
+    // Sign as fee payer if the transactions are sponsored by the server
+    state
+        .co_sign("transfer-cb", transfer_amount_lamports, &mut transactions)
+        .await?;
 
-    // Return all transactions
-    let transactions = vec![tx1, tx2, tx3, tx4, tx5];
+    let required_signers = transactions.iter().map(missing_signers).collect();
     let response = MultiTransactionResponse {
         transactions: transactions
             .into_iter()
@@ -474,52 +637,327 @@ pub async fn transfer_cb(
                 Ok(serialized_transaction)
             })
             .collect::<Result<Vec<String>, AppError>>()?,
+        required_signers,
+        flow_nonce: Some(flow_nonce),
+        flow_nonce_count: Some(1),
         message: "MultiTransaction for confidential transfer created successfully".to_string(),
     };
 
     Ok(Json(response))
 }
 
-/// Refactored version of spl_token_client::token::Token::confidential_transfer_create_context_state_account().
-/// Instead of sending transactions internally or calculating rent via RPC, this function now accepts
-/// the rent value from the caller and returns the instructions to be used externally.
-fn get_zk_proof_context_state_account_creation_instructions<
-    ZK: bytemuck::Pod + ZkProofData<U>,
-    U: bytemuck::Pod,
->(
-    fee_payer_pubkey: &Pubkey,
-    context_state_account_pubkey: &Pubkey,
-    context_state_authority_pubkey: &Pubkey,
-    proof_data: &ZK,
-    rent: u64,
-) -> Result<(Instruction, Instruction), AppError> {
-    let space = size_of::<ProofContextState<U>>();
-    println!("📊 Context state account space required: {} bytes", space);
-    println!(
-        "💰 Using provided rent for context state account: {} lamports",
-        rent
-    );
-
-    let context_state_info = ContextStateInfo {
-        context_state_account: context_state_account_pubkey,
-        context_state_authority: context_state_authority_pubkey,
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            models::BundleRequest,
+            routes::{bundle::MAX_BUNDLE_TRANSACTIONS, test_util},
+        },
+        solana_sdk::{
+            hash::Hash, nonce::state::DurableNonce, packet::PACKET_DATA_SIZE, pubkey::Pubkey,
+            system_instruction::SystemInstruction, transaction::VersionedTransaction,
+        },
     };
 
-    let instruction_type = zk_proof_type_to_instruction(ZK::PROOF_TYPE)?;
-
-    println!("🔧 Creating context state account with inputs: fee_payer={}, context_state_account={}, rent={}, space={}",
-        fee_payer_pubkey, context_state_account_pubkey, rent, space);
-    let create_account_ix = system_instruction::create_account(
-        fee_payer_pubkey,
-        context_state_account_pubkey,
-        rent,
-        space as u64,
-        &solana_zk_sdk::zk_elgamal_proof_program::id(),
-    );
-
-    let verify_proof_ix =
-        instruction_type.encode_verify_proof(Some(context_state_info), proof_data);
-
-    // Return a tuple containing the create account instruction and verify proof instruction.
-    Ok((create_account_ix, verify_proof_ix))
+    #[tokio::test]
+    async fn test_transfer_with_durable_nonces_fits_in_packets() {
+        let sender = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let nonces = (0..5u8)
+            .map(|i| Hash::new_from_array([i + 10; 32]))
+            .collect::<Vec<_>>();
+
+        let Json(response) = transfer_cb(
+            State(AppState::default()),
+            Json(TransferCbRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                aes_signature: test_util::aes_signature(),
+                sender_token_account: Some(test_util::confidential_token_account_data(
+                    &sender, &mint, 1_000, 0, 0,
+                )),
+                sender_token_account_address: None,
+                recipient_token_account: Some(test_util::confidential_token_account_data(
+                    &recipient, &mint, 0, 0, 0,
+                )),
+                recipient_token_account_address: None,
+                mint_token_account: Some(test_util::confidential_mint_data(&sender, false)),
+                amount: "100".to_string(),
+                priority_fee: "10000".to_string(),
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: None,
+                latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                address_lookup_tables: None,
+                nonce_accounts: Some((0..5).map(|_| Pubkey::new_unique().to_string()).collect()),
+                nonce_account_data: Some(
+                    nonces
+                        .iter()
+                        .map(|nonce| test_util::nonce_account_data(&sender, nonce))
+                        .collect(),
+                ),
+                nonce_authority: None,
+                equality_proof_rent: None,
+                ciphertext_validity_proof_rent: None,
+                range_proof_rent: None,
+                priority_fee_policy: None,
+                bundle: None,
+                flow_nonce: None,
+                resume: None,
+                memo: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        let transactions = response
+            .transactions
+            .iter()
+            .map(|transaction| {
+                let bytes = BASE64_STANDARD.decode(transaction).unwrap();
+                assert!(bytes.len() <= PACKET_DATA_SIZE);
+                bincode::deserialize::<VersionedTransaction>(&bytes).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // Every transaction uses its own nonce, including the range proof verification, which
+        // reads the proof from a record account written in chunks
+        assert_eq!(transactions.len(), nonces.len());
+        for (transaction, nonce) in transactions.iter().zip(&nonces) {
+            assert_eq!(
+                transaction.message.recent_blockhash(),
+                DurableNonce::from_blockhash(nonce).as_hash()
+            );
+        }
+        assert!(transactions.iter().any(|transaction| transaction
+            .message
+            .static_account_keys()
+            .contains(&spl_record::id())));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_bundle_tips_in_last_transaction() {
+        let sender = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (state, _) = test_util::mock_block_engine_state().await;
+
+        let Json(response) = transfer_cb(
+            State(state),
+            Json(TransferCbRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                aes_signature: test_util::aes_signature(),
+                sender_token_account: Some(test_util::confidential_token_account_data(
+                    &sender, &mint, 1_000, 0, 0,
+                )),
+                sender_token_account_address: None,
+                recipient_token_account: Some(test_util::confidential_token_account_data(
+                    &recipient, &mint, 0, 0, 0,
+                )),
+                recipient_token_account_address: None,
+                mint_token_account: Some(test_util::confidential_mint_data(&sender, false)),
+                amount: "100".to_string(),
+                priority_fee: "0".to_string(),
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: None,
+                latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                address_lookup_tables: None,
+                nonce_accounts: None,
+                nonce_account_data: None,
+                nonce_authority: None,
+                equality_proof_rent: None,
+                ciphertext_validity_proof_rent: None,
+                range_proof_rent: None,
+                priority_fee_policy: None,
+                flow_nonce: None,
+                resume: None,
+                memo: None,
+                bundle: Some(BundleRequest {
+                    tip_lamports: "10000".to_string(),
+                    tip_account: None,
+                }),
+            }),
+        )
+        .await
+        .unwrap();
+        assert!(response.transactions.len() <= MAX_BUNDLE_TRANSACTIONS);
+
+        // The tip to the block engine's tip account is the very last instruction
+        let last_transaction = bincode::deserialize::<VersionedTransaction>(
+            &BASE64_STANDARD
+                .decode(response.transactions.last().unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+        let account_keys = last_transaction.message.static_account_keys();
+        let tip = last_transaction.message.instructions().last().unwrap();
+        assert_eq!(
+            bincode::deserialize::<SystemInstruction>(&tip.data).unwrap(),
+            SystemInstruction::Transfer { lamports: 10_000 }
+        );
+        assert_eq!(
+            account_keys[tip.accounts[1] as usize],
+            test_util::mock_tip_account()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sponsored_transfer_bundle_tips_from_the_sender() {
+        let sender = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (state, _) = test_util::mock_block_engine_state().await;
+        let (state, sponsor) = test_util::sponsored_state(state, &["transfer-cb"]);
+
+        let transfer = |tip_account: Option<String>| {
+            transfer_cb(
+                State(state.clone()),
+                Json(TransferCbRequest {
+                    elgamal_signature: test_util::elgamal_signature(),
+                    aes_signature: test_util::aes_signature(),
+                    sender_token_account: Some(test_util::confidential_token_account_data(
+                        &sender, &mint, 1_000, 0, 0,
+                    )),
+                    sender_token_account_address: None,
+                    recipient_token_account: Some(test_util::confidential_token_account_data(
+                        &recipient, &mint, 0, 0, 0,
+                    )),
+                    recipient_token_account_address: None,
+                    mint_token_account: Some(test_util::confidential_mint_data(&sender, false)),
+                    amount: "100".to_string(),
+                    priority_fee: "0".to_string(),
+                    multisig: None,
+                    multisig_signers: None,
+                    fee_payer: Some(sponsor.pubkey().to_string()),
+                    latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                    address_lookup_tables: None,
+                    nonce_accounts: None,
+                    nonce_account_data: None,
+                    nonce_authority: None,
+                    equality_proof_rent: None,
+                    ciphertext_validity_proof_rent: None,
+                    range_proof_rent: None,
+                    priority_fee_policy: None,
+                    flow_nonce: None,
+                    resume: None,
+                    memo: None,
+                    bundle: Some(BundleRequest {
+                        tip_lamports: "1000000000".to_string(),
+                        tip_account,
+                    }),
+                }),
+            )
+        };
+
+        // Tips only go to the block engine's own tip accounts
+        let result = transfer(Some(Pubkey::new_unique().to_string())).await;
+        assert!(matches!(result, Err(AppError::InvalidTipAccount)));
+
+        let Json(response) = transfer(Some(test_util::mock_tip_account().to_string()))
+            .await
+            .unwrap();
+
+        // The sponsor co-signs every transaction but the tip is paid by the sender
+        let last_transaction = bincode::deserialize::<VersionedTransaction>(
+            &BASE64_STANDARD
+                .decode(response.transactions.last().unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+        let account_keys = last_transaction.message.static_account_keys();
+        let tip = last_transaction.message.instructions().last().unwrap();
+        assert_eq!(account_keys[0], sponsor.pubkey());
+        assert_eq!(account_keys[tip.accounts[0] as usize], sender);
+        assert_eq!(
+            account_keys[tip.accounts[1] as usize],
+            test_util::mock_tip_account()
+        );
+        assert!(response
+            .required_signers
+            .iter()
+            .all(|signers| !signers.contains(&sponsor.pubkey().to_string())));
+        assert!(response
+            .required_signers
+            .last()
+            .unwrap()
+            .contains(&sender.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_puts_memo_right_before_the_transfer() {
+        let sender = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let transfer = |memo: Option<&str>| {
+            transfer_cb(
+                State(AppState::default()),
+                Json(TransferCbRequest {
+                    elgamal_signature: test_util::elgamal_signature(),
+                    aes_signature: test_util::aes_signature(),
+                    sender_token_account: Some(test_util::confidential_token_account_data(
+                        &sender, &mint, 1_000, 0, 0,
+                    )),
+                    sender_token_account_address: None,
+                    recipient_token_account: Some(test_util::memo_required_token_account_data(
+                        &recipient, &mint,
+                    )),
+                    recipient_token_account_address: None,
+                    mint_token_account: Some(test_util::confidential_mint_data(&sender, false)),
+                    amount: "100".to_string(),
+                    priority_fee: "0".to_string(),
+                    multisig: None,
+                    multisig_signers: None,
+                    fee_payer: None,
+                    latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                    address_lookup_tables: None,
+                    nonce_accounts: None,
+                    nonce_account_data: None,
+                    nonce_authority: None,
+                    equality_proof_rent: None,
+                    ciphertext_validity_proof_rent: None,
+                    range_proof_rent: None,
+                    priority_fee_policy: None,
+                    bundle: None,
+                    flow_nonce: None,
+                    resume: None,
+                    memo: memo.map(str::to_string),
+                }),
+            )
+        };
+
+        // The recipient rejects transfers without a memo
+        assert!(matches!(
+            transfer(None).await,
+            Err(AppError::RecipientMemoRequired)
+        ));
+
+        let Json(response) = transfer(Some("invoice-42")).await.unwrap();
+        let memos = response
+            .transactions
+            .iter()
+            .flat_map(|transaction| {
+                let transaction: VersionedTransaction =
+                    bincode::deserialize(&BASE64_STANDARD.decode(transaction).unwrap()).unwrap();
+                let account_keys = transaction.message.static_account_keys().to_vec();
+                let instructions = transaction.message.instructions().to_vec();
+                instructions
+                    .windows(2)
+                    .filter(|pair| {
+                        account_keys[pair[1].program_id_index as usize] == spl_token_2022::id()
+                    })
+                    .map(|pair| {
+                        assert_eq!(
+                            account_keys[pair[0].program_id_index as usize],
+                            spl_memo::id()
+                        );
+                        pair[0].data.clone()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(memos, vec![b"invoice-42".to_vec()]);
+    }
 }
@@ -1,12 +1,14 @@
 use {
-    crate::{errors::AppError, models::TransferCbSpaceResponse},
-    axum::extract::Json,
+    crate::{errors::AppError, models::TransferCbSpaceResponse, state::AppState},
+    axum::extract::{Json, State},
     solana_zk_sdk::zk_elgamal_proof_program,
     std::mem::size_of,
 };
 
-/// GET handler to provide space requirements for transfer-cb operation
-pub async fn transfer_cb_space() -> Result<Json<TransferCbSpaceResponse>, AppError> {
+/// GET handler to provide space and rent requirements for transfer-cb operation
+pub async fn transfer_cb_space(
+    State(state): State<AppState>,
+) -> Result<Json<TransferCbSpaceResponse>, AppError> {
     println!("📊 Processing transfer-cb-space request");
 
     let equality_proof_space = size_of::<zk_elgamal_proof_program::state::ProofContextState<
@@ -18,13 +20,16 @@ pub async fn transfer_cb_space() -> Result<Json<TransferCbSpaceResponse>, AppErr
     >>();
 
     let range_proof_space = size_of::<zk_elgamal_proof_program::state::ProofContextState<
-        solana_zk_sdk::zk_elgamal_proof_program::proof_data::batched_range_proof::batched_range_proof_u128::BatchedRangeProofU128Data
+        solana_zk_sdk::zk_elgamal_proof_program::proof_data::batched_range_proof::BatchedRangeProofContext
     >>();
 
     Ok(Json(TransferCbSpaceResponse {
         equality_proof_space,
         ciphertext_validity_proof_space,
         range_proof_space,
-        message: "Space requirements for transfer-cb proofs".to_string(),
+        equality_proof_rent: state.rent(None, equality_proof_space).await?,
+        ciphertext_validity_proof_rent: state.rent(None, ciphertext_validity_proof_space).await?,
+        range_proof_rent: state.rent(None, range_proof_space).await?,
+        message: "Space and rent requirements for transfer-cb proofs".to_string(),
     }))
 }
@@ -0,0 +1,607 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{MultiTransactionResponse, TransferWithFeeCbRequest},
+        routes::{
+            check_recipient::check_recipient_readiness,
+            nonce::TransactionLifetime,
+            packer::pack_transactions,
+            priority_fee::PriorityFee,
+            util::{
+                check_sender_balance, get_zk_proof_context_state_account_creation_instructions,
+                get_zk_proof_record_verification_instructions, missing_signers, parse_fee_payer,
+                proof_context_state_space, proof_record_space,
+            },
+        },
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    bincode,
+    solana_sdk::{
+        signature::{Keypair, Signature},
+        signer::Signer,
+    },
+    solana_zk_sdk::{
+        encryption::{elgamal::ElGamalPubkey, pod::elgamal::PodElGamalPubkey},
+        zk_elgamal_proof_program::instruction::{close_context_state, ContextStateInfo},
+    },
+    spl_associated_token_account::get_associated_token_address_with_program_id,
+    spl_token_2022::{
+        error::TokenError,
+        extension::{
+            confidential_transfer::{
+                account_info::TransferAccountInfo, instruction::transfer_with_fee,
+                ConfidentialTransferAccount, ConfidentialTransferMint,
+            },
+            confidential_transfer_fee::ConfidentialTransferFeeConfig,
+            transfer_fee::TransferFeeConfig,
+            BaseStateWithExtensions, StateWithExtensionsOwned,
+        },
+        solana_zk_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
+    },
+    spl_token_confidential_transfer_proof_extraction::instruction::ProofLocation,
+    spl_token_confidential_transfer_proof_generation::transfer_with_fee::TransferWithFeeProofData,
+};
+
+/// Handler for the transfer-with-fee-cb endpoint
+///
+/// This endpoint creates the transactions to transfer tokens between confidential token accounts
+/// of a mint that charges transfer fees (`TransferFeeConfig` + `ConfidentialTransferFeeConfig`)
+pub async fn transfer_with_fee_cb(
+    State(state): State<AppState>,
+    Json(request): Json<TransferWithFeeCbRequest>,
+) -> Result<Json<MultiTransactionResponse>, AppError> {
+    println!("📝 Processing transfer-with-fee-cb request");
+
+    // Decode amount from request
+    println!("📦 Decoding amount from request");
+    let transfer_amount_lamports = request
+        .amount
+        .parse::<u64>()
+        .map_err(|_| AppError::InvalidAmount)?;
+    println!(
+        "✅ Successfully decoded amount: {}",
+        transfer_amount_lamports
+    );
+
+    // Decode sender token account data from request
+    println!("📦 Decoding sender token account data from request");
+    let sender_token_account_info = {
+        let sender_token_account_data = state
+            .account_data(
+                request.sender_token_account.as_ref(),
+                request.sender_token_account_address.as_ref(),
+                "sender_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(
+            sender_token_account_data,
+        )?
+    };
+    println!(
+        "✅ Successfully decoded sender token account data from owner {}",
+        sender_token_account_info.base.owner
+    );
+
+    // Decode recipient token account data from request
+    println!("📦 Decoding recipient token account data from request");
+    let recipient_token_account_info = {
+        let recipient_token_account_data = state
+            .account_data(
+                request.recipient_token_account.as_ref(),
+                request.recipient_token_account_address.as_ref(),
+                "recipient_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(
+            recipient_token_account_data,
+        )?
+    };
+    println!(
+        "✅ Successfully decoded recipient token account data from owner {}",
+        recipient_token_account_info.base.owner
+    );
+
+    // Reject early if the recipient can't receive, before generating the (slow) proofs
+    check_recipient_readiness(&recipient_token_account_info, 1)?;
+
+    // Verify that both accounts reference the same mint
+    let mint = {
+        let sender_mint = sender_token_account_info.base.mint;
+        let recipient_mint = recipient_token_account_info.base.mint;
+
+        if sender_mint != recipient_mint {
+            println!(
+                "⛔️ Mint mismatch: sender mint {} does not match recipient mint {}",
+                sender_mint, recipient_mint
+            );
+            return Err(AppError::MintMismatch);
+        }
+
+        sender_mint
+    };
+
+    // Get the sender token account pubkey
+    let sender_ata_authority = sender_token_account_info.base.owner;
+    let sender_token_account = get_associated_token_address_with_program_id(
+        &sender_ata_authority,
+        &mint,
+        &spl_token_2022::id(),
+    );
+    println!(
+        "✅ Calculated sender token account address: {}",
+        sender_token_account
+    );
+
+    // Get the recipient token account address
+    let recipient_ata_authority = recipient_token_account_info.base.owner;
+    let recipient_token_account = get_associated_token_address_with_program_id(
+        &recipient_ata_authority,
+        &mint,
+        &spl_token_2022::id(),
+    );
+    println!(
+        "✅ Calculated recipient token account address: {}",
+        recipient_token_account
+    );
+
+    // Read the auditor and fee configuration from the mint account data
+    println!("📦 Decoding mint account data from request");
+    let mint_account_info = {
+        let mint_account_data = state
+            .mint_data(
+                request.mint_token_account.as_ref(),
+                &mint,
+                "mint_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(mint_account_data)?
+    };
+
+    let auditor_elgamal_pubkey_option: Option<ElGamalPubkey> = Option::<PodElGamalPubkey>::from(
+        mint_account_info
+            .get_extension::<ConfidentialTransferMint>()?
+            .auditor_elgamal_pubkey,
+    )
+    .map(|pod| pod.try_into())
+    .transpose()?;
+
+    // The fee that is currently in effect depends on the epoch
+    let epoch = state.epoch(request.epoch).await?;
+    let (fee_rate_basis_points, maximum_fee) = {
+        let transfer_fee_config = mint_account_info
+            .get_extension::<TransferFeeConfig>()
+            .map_err(|e| {
+                println!("⛔️ Mint has no TransferFeeConfig extension: {}", e);
+                e
+            })?;
+        let transfer_fee = transfer_fee_config.get_epoch_fee(epoch);
+
+        (
+            u16::from(transfer_fee.transfer_fee_basis_points),
+            u64::from(transfer_fee.maximum_fee),
+        )
+    };
+    println!(
+        "✅ Transfer fee for epoch {}: {} basis points, maximum fee {}",
+        epoch, fee_rate_basis_points, maximum_fee
+    );
+
+    // Withheld fees are encrypted under the withdraw withheld authority's ElGamal pubkey
+    let withdraw_withheld_authority_elgamal_pubkey: ElGamalPubkey = mint_account_info
+        .get_extension::<ConfidentialTransferFeeConfig>()
+        .map_err(|e| {
+            println!(
+                "⛔️ Mint has no ConfidentialTransferFeeConfig extension: {}",
+                e
+            );
+            e
+        })?
+        .withdraw_withheld_authority_elgamal_pubkey
+        .try_into()?;
+
+    // Must first create 5 accounts to store proofs before transferring tokens
+    // This must be done in separate transactions because the proofs are too large for single transaction:
+    // Equality Proof - prove that two ciphertexts encrypt the same value
+    // Transfer Amount Ciphertext Validity Proof - prove that the transfer amount ciphertexts are properly generated
+    // Fee Sigma Proof - prove that the fee ciphertext encrypts the correct percentage (with cap) of the transfer amount
+    // Fee Ciphertext Validity Proof - prove that the fee ciphertexts are properly generated
+    // Range Proof - prove that ciphertexts encrypt a value in a specified range (0, u64::MAX)
+
+    // Pays for every transaction and funds the proof accounts (defaults to the sender)
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), sender_ata_authority)?;
+
+    // "Authority" for the proof accounts (to close the accounts after the transfer)
+    let context_state_authority = &fee_payer;
+
+    // Generate addresses for proof accounts
+    let equality_proof_context_state_account = Keypair::new();
+    let transfer_amount_ciphertext_validity_proof_context_state_account = Keypair::new();
+    let fee_sigma_proof_context_state_account = Keypair::new();
+    let fee_ciphertext_validity_proof_context_state_account = Keypair::new();
+    let range_proof_context_state_account = Keypair::new();
+    let range_proof_record_account = Keypair::new();
+
+    // ConfidentialTransferAccount extension information needed to create proof data
+    let sender_transfer_account_info = {
+        let sender_account_extension_data =
+            sender_token_account_info.get_extension::<ConfidentialTransferAccount>()?;
+
+        TransferAccountInfo::new(sender_account_extension_data)
+    };
+
+    let recipient_elgamal_pubkey: ElGamalPubkey = recipient_token_account_info
+        .get_extension::<ConfidentialTransferAccount>()?
+        .elgamal_pubkey
+        .try_into()?;
+
+    // Create the sender's ElGamal keypair in a temporary scope
+    let sender_elgamal_keypair = {
+        println!(
+            "🔐 Decoding ElGamal signature: {}",
+            request.elgamal_signature
+        );
+        let decoded_elgamal_signature = BASE64_STANDARD.decode(&request.elgamal_signature)?;
+
+        // Create signature directly from bytes
+        let elgamal_signature = Signature::try_from(decoded_elgamal_signature.as_slice())
+            .map_err(|_| AppError::SerializationError)?;
+
+        ElGamalKeypair::new_from_signature(&elgamal_signature)
+            .map_err(|_| AppError::SerializationError)?
+    };
+    println!("✅ ElGamal keypair created successfully");
+
+    // Create the sender's AES key in a temporary scope
+    let sender_aes_key = {
+        println!("🔐 Decoding AES signature: {}", request.aes_signature);
+        let decoded_aes_signature = BASE64_STANDARD.decode(&request.aes_signature)?;
+
+        // Create signature directly from bytes
+        let aes_signature = Signature::try_from(decoded_aes_signature.as_slice())
+            .map_err(|_| AppError::SerializationError)?;
+
+        AeKey::new_from_signature(&aes_signature).map_err(|_| AppError::SerializationError)?
+    };
+    println!("✅ AES key created successfully");
+
+    // Fail with the balances instead of a generic proof generation error
+    check_sender_balance(
+        sender_token_account_info.get_extension::<ConfidentialTransferAccount>()?,
+        &sender_elgamal_keypair,
+        &sender_aes_key,
+        transfer_amount_lamports,
+    )?;
+
+    // Generate proof data
+    let TransferWithFeeProofData {
+        equality_proof_data,
+        transfer_amount_ciphertext_validity_proof_data_with_ciphertext,
+        percentage_with_cap_proof_data,
+        fee_ciphertext_validity_proof_data,
+        range_proof_data,
+    } = sender_transfer_account_info.generate_split_transfer_with_fee_proof_data(
+        transfer_amount_lamports,
+        &sender_elgamal_keypair,
+        &sender_aes_key,
+        &recipient_elgamal_pubkey,
+        auditor_elgamal_pubkey_option.as_ref(),
+        &withdraw_withheld_authority_elgamal_pubkey,
+        fee_rate_basis_points,
+        maximum_fee,
+    )?;
+
+    println!("✅ Transfer with fee proof data generated successfully");
+
+    // Use the proof account rents from the request, or the rent-exempt minimums
+    let range_proof_rent = state
+        .rent(
+            request.range_proof_rent.as_ref(),
+            proof_context_state_space(&range_proof_data),
+        )
+        .await?;
+    let equality_proof_rent = state
+        .rent(
+            request.equality_proof_rent.as_ref(),
+            proof_context_state_space(&equality_proof_data),
+        )
+        .await?;
+    let transfer_amount_ciphertext_validity_proof_rent = state
+        .rent(
+            request
+                .transfer_amount_ciphertext_validity_proof_rent
+                .as_ref(),
+            proof_context_state_space(
+                &transfer_amount_ciphertext_validity_proof_data_with_ciphertext.proof_data,
+            ),
+        )
+        .await?;
+    let fee_sigma_proof_rent = state
+        .rent(
+            request.fee_sigma_proof_rent.as_ref(),
+            proof_context_state_space(&percentage_with_cap_proof_data),
+        )
+        .await?;
+    let fee_ciphertext_validity_proof_rent = state
+        .rent(
+            request.fee_ciphertext_validity_proof_rent.as_ref(),
+            proof_context_state_space(&fee_ciphertext_validity_proof_data),
+        )
+        .await?;
+
+    let range_proof_record_rent = state
+        .rent(None, proof_record_space(&range_proof_data))
+        .await?;
+
+    // Create 5 proofs ------------------------------------------------------
+
+    // Range Proof Instructions------------------------------------------------------------------------------
+    // The batched u256 range proof is too large for the instruction data of a transaction, so it
+    // is written into a record account and verified from there
+    let (range_create_ix, _) = get_zk_proof_context_state_account_creation_instructions(
+        &fee_payer,
+        &range_proof_context_state_account.pubkey(),
+        context_state_authority,
+        &range_proof_data,
+        range_proof_rent,
+    )?;
+    let range_verify_groups = get_zk_proof_record_verification_instructions(
+        &fee_payer,
+        &range_proof_record_account.pubkey(),
+        &range_proof_context_state_account.pubkey(),
+        context_state_authority,
+        &range_proof_data,
+        Some(range_proof_record_rent),
+    )?;
+
+    // Equality Proof Instructions---------------------------------------------------------------------------
+    let (equality_create_ix, equality_verify_ix) =
+        get_zk_proof_context_state_account_creation_instructions(
+            &fee_payer,
+            &equality_proof_context_state_account.pubkey(),
+            context_state_authority,
+            &equality_proof_data,
+            equality_proof_rent,
+        )?;
+
+    // Transfer Amount Ciphertext Validity Proof Instructions ------------------------------------------------
+    let (transfer_cv_create_ix, transfer_cv_verify_ix) =
+        get_zk_proof_context_state_account_creation_instructions(
+            &fee_payer,
+            &transfer_amount_ciphertext_validity_proof_context_state_account.pubkey(),
+            context_state_authority,
+            &transfer_amount_ciphertext_validity_proof_data_with_ciphertext.proof_data,
+            transfer_amount_ciphertext_validity_proof_rent,
+        )?;
+
+    // Fee Sigma Proof Instructions --------------------------------------------------------------------------
+    let (fee_sigma_create_ix, fee_sigma_verify_ix) =
+        get_zk_proof_context_state_account_creation_instructions(
+            &fee_payer,
+            &fee_sigma_proof_context_state_account.pubkey(),
+            context_state_authority,
+            &percentage_with_cap_proof_data,
+            fee_sigma_proof_rent,
+        )?;
+
+    // Fee Ciphertext Validity Proof Instructions ------------------------------------------------------------
+    let (fee_cv_create_ix, fee_cv_verify_ix) =
+        get_zk_proof_context_state_account_creation_instructions(
+            &fee_payer,
+            &fee_ciphertext_validity_proof_context_state_account.pubkey(),
+            context_state_authority,
+            &fee_ciphertext_validity_proof_data,
+            fee_ciphertext_validity_proof_rent,
+        )?;
+
+    // Transact Proofs ------------------------------------------------------------------------------------
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+
+    // The priority fee policy, or else the legacy lamport budget of the whole flow. Every
+    // transaction sets its compute unit limit, even without a priority fee: the range proof
+    // verification alone takes more than the default limit
+    let priority_fee = PriorityFee::from_legacy_request(
+        request.priority_fee_policy.as_ref(),
+        &request.priority_fee,
+    )?
+    .unwrap_or(PriorityFee::MicroLamportsPerComputeUnit(0));
+
+    // Allocate all proof accounts, then verify the range proof from its record account and the
+    // other proofs from instruction data
+    let mut groups = vec![
+        vec![range_create_ix],
+        vec![equality_create_ix],
+        vec![transfer_cv_create_ix],
+        vec![fee_sigma_create_ix],
+        vec![fee_cv_create_ix],
+    ];
+    groups.extend(range_verify_groups);
+    groups.push(vec![equality_verify_ix, transfer_cv_verify_ix]);
+    groups.push(vec![fee_sigma_verify_ix, fee_cv_verify_ix]);
+
+    // Transfer with Split Proofs -------------------------------------------
+    let new_decryptable_available_balance = sender_transfer_account_info
+        .new_decryptable_available_balance(transfer_amount_lamports, &sender_aes_key)
+        .map_err(|_| TokenError::AccountDecryption)?
+        .into();
+
+    groups.push(transfer_with_fee(
+        &spl_token_2022::id(),
+        &sender_token_account,
+        &mint,
+        &recipient_token_account,
+        &new_decryptable_available_balance,
+        &transfer_amount_ciphertext_validity_proof_data_with_ciphertext.ciphertext_lo,
+        &transfer_amount_ciphertext_validity_proof_data_with_ciphertext.ciphertext_hi,
+        &sender_ata_authority,
+        &[],
+        ProofLocation::ContextStateAccount(&equality_proof_context_state_account.pubkey()),
+        ProofLocation::ContextStateAccount(
+            &transfer_amount_ciphertext_validity_proof_context_state_account.pubkey(),
+        ),
+        ProofLocation::ContextStateAccount(&fee_sigma_proof_context_state_account.pubkey()),
+        ProofLocation::ContextStateAccount(
+            &fee_ciphertext_validity_proof_context_state_account.pubkey(),
+        ),
+        ProofLocation::ContextStateAccount(&range_proof_context_state_account.pubkey()),
+    )?);
+
+    // Close Proof Accounts --------------------------------------------------
+    // Lamports from the closed proof accounts will be sent to this account
+    let destination_account = &fee_payer;
+    let proof_account_keypairs = [
+        &equality_proof_context_state_account,
+        &transfer_amount_ciphertext_validity_proof_context_state_account,
+        &fee_sigma_proof_context_state_account,
+        &fee_ciphertext_validity_proof_context_state_account,
+        &range_proof_context_state_account,
+    ];
+    groups.extend(proof_account_keypairs.iter().map(|context_state_account| {
+        vec![close_context_state(
+            ContextStateInfo {
+                context_state_account: &context_state_account.pubkey(),
+                context_state_authority,
+            },
+            destination_account,
+        )]
+    }));
+
+    // Fill as few transactions as possible
+    let mut transactions = pack_transactions(
+        &fee_payer,
+        groups,
+        &[
+            proof_account_keypairs.as_slice(),
+            &[&range_proof_record_account],
+        ]
+        .concat(),
+        &mut TransactionLifetime::with_blockhash(client_blockhash),
+        &lookup_tables,
+        Some(&priority_fee),
+    )?;
+
+    // Sign as fee payer if the transactions are sponsored by the server
+    state
+        .co_sign(
+            "transfer-with-fee-cb",
+            transfer_amount_lamports,
+            &mut transactions,
+        )
+        .await?;
+
+    let required_signers = transactions.iter().map(missing_signers).collect();
+    let response = MultiTransactionResponse {
+        transactions: transactions
+            .into_iter()
+            .enumerate()
+            .map(|(i, tx)| {
+                let serialized_transaction = match bincode::serialize(&tx) {
+                    Ok(bytes) => BASE64_STANDARD.encode(bytes),
+                    Err(_) => return Err(AppError::SerializationError),
+                };
+                println!("✅ Successfully serialized transaction {}", i + 1);
+
+                Ok(serialized_transaction)
+            })
+            .collect::<Result<Vec<String>, AppError>>()?,
+        required_signers,
+        flow_nonce: None,
+        flow_nonce_count: None,
+        message: "MultiTransaction for confidential transfer with fee created successfully"
+            .to_string(),
+    };
+
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::routes::test_util,
+        solana_sdk::{
+            borsh1::try_from_slice_unchecked,
+            compute_budget::{self, ComputeBudgetInstruction},
+            packet::PACKET_DATA_SIZE,
+            pubkey::Pubkey,
+            transaction::VersionedTransaction,
+        },
+        solana_zk_sdk::zk_elgamal_proof_program::{self, instruction::ProofInstruction},
+    };
+
+    #[tokio::test]
+    async fn test_transfer_with_fee_fits_in_packets_with_compute_unit_limits() {
+        let sender = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let Json(response) = transfer_with_fee_cb(
+            State(AppState::default()),
+            Json(TransferWithFeeCbRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                aes_signature: test_util::aes_signature(),
+                sender_token_account: Some(test_util::confidential_token_account_data(
+                    &sender, &mint, 1_000, 0, 0,
+                )),
+                sender_token_account_address: None,
+                recipient_token_account: Some(test_util::confidential_token_account_data(
+                    &recipient, &mint, 0, 0, 0,
+                )),
+                recipient_token_account_address: None,
+                mint_token_account: Some(test_util::confidential_fee_mint_data(&sender, 100, 10)),
+                amount: "100".to_string(),
+                epoch: Some(0),
+                priority_fee: "0".to_string(),
+                fee_payer: None,
+                latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                equality_proof_rent: None,
+                transfer_amount_ciphertext_validity_proof_rent: None,
+                fee_sigma_proof_rent: None,
+                fee_ciphertext_validity_proof_rent: None,
+                range_proof_rent: None,
+                address_lookup_tables: None,
+                priority_fee_policy: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        let mut range_proof_compute_unit_limit = None;
+        for transaction in &response.transactions {
+            let bytes = BASE64_STANDARD.decode(transaction).unwrap();
+            assert!(bytes.len() <= PACKET_DATA_SIZE);
+
+            // Every transaction sets its compute unit limit
+            let transaction: VersionedTransaction = bincode::deserialize(&bytes).unwrap();
+            let account_keys = transaction.message.static_account_keys();
+            let instructions = transaction.message.instructions();
+            assert_eq!(
+                account_keys[instructions[0].program_id_index as usize],
+                compute_budget::id()
+            );
+            let Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) =
+                try_from_slice_unchecked(&instructions[0].data)
+            else {
+                panic!("transaction without a compute unit limit");
+            };
+
+            // The u256 range proof is verified from its record account
+            if instructions.iter().any(|instruction| {
+                account_keys[instruction.program_id_index as usize]
+                    == zk_elgamal_proof_program::id()
+                    && ProofInstruction::instruction_type(&instruction.data)
+                        == Some(ProofInstruction::VerifyBatchedRangeProofU256)
+            }) {
+                assert!(account_keys.contains(&spl_record::id()));
+                range_proof_compute_unit_limit = Some(limit);
+            }
+        }
+        assert!(range_proof_compute_unit_limit.unwrap() >= 368_000);
+    }
+}
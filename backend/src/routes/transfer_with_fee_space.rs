@@ -0,0 +1,51 @@
+use {
+    crate::{errors::AppError, models::TransferWithFeeCbSpaceResponse, state::AppState},
+    axum::extract::{Json, State},
+    solana_zk_sdk::zk_elgamal_proof_program,
+    std::mem::size_of,
+};
+
+/// GET handler to provide space and rent requirements for transfer-with-fee-cb operation
+pub async fn transfer_with_fee_cb_space(
+    State(state): State<AppState>,
+) -> Result<Json<TransferWithFeeCbSpaceResponse>, AppError> {
+    println!("📊 Processing transfer-with-fee-cb-space request");
+
+    let equality_proof_space = size_of::<zk_elgamal_proof_program::state::ProofContextState<
+        solana_zk_sdk::zk_elgamal_proof_program::proof_data::ciphertext_commitment_equality::CiphertextCommitmentEqualityProofContext
+    >>();
+
+    let transfer_amount_ciphertext_validity_proof_space = size_of::<zk_elgamal_proof_program::state::ProofContextState<
+        solana_zk_sdk::zk_elgamal_proof_program::proof_data::batched_grouped_ciphertext_validity::BatchedGroupedCiphertext3HandlesValidityProofContext
+    >>();
+
+    let fee_sigma_proof_space = size_of::<zk_elgamal_proof_program::state::ProofContextState<
+        solana_zk_sdk::zk_elgamal_proof_program::proof_data::percentage_with_cap::PercentageWithCapProofContext
+    >>();
+
+    let fee_ciphertext_validity_proof_space = size_of::<zk_elgamal_proof_program::state::ProofContextState<
+        solana_zk_sdk::zk_elgamal_proof_program::proof_data::batched_grouped_ciphertext_validity::BatchedGroupedCiphertext2HandlesValidityProofContext
+    >>();
+
+    let range_proof_space = size_of::<zk_elgamal_proof_program::state::ProofContextState<
+        solana_zk_sdk::zk_elgamal_proof_program::proof_data::batched_range_proof::BatchedRangeProofContext
+    >>();
+
+    Ok(Json(TransferWithFeeCbSpaceResponse {
+        equality_proof_space,
+        transfer_amount_ciphertext_validity_proof_space,
+        fee_sigma_proof_space,
+        fee_ciphertext_validity_proof_space,
+        range_proof_space,
+        equality_proof_rent: state.rent(None, equality_proof_space).await?,
+        transfer_amount_ciphertext_validity_proof_rent: state
+            .rent(None, transfer_amount_ciphertext_validity_proof_space)
+            .await?,
+        fee_sigma_proof_rent: state.rent(None, fee_sigma_proof_space).await?,
+        fee_ciphertext_validity_proof_rent: state
+            .rent(None, fee_ciphertext_validity_proof_space)
+            .await?,
+        range_proof_rent: state.rent(None, range_proof_space).await?,
+        message: "Space and rent requirements for transfer-with-fee-cb proofs".to_string(),
+    }))
+}
@@ -0,0 +1,240 @@
+use {
+    crate::{
+        errors::AppError,
+        models::{TransactionResponse, UpdateMintCbRequest},
+        routes::{
+            priority_fee::{with_compute_budget, PriorityFee},
+            util::{missing_signers, parse_base58_pubkey, parse_fee_payer},
+        },
+        state::AppState,
+    },
+    axum::extract::{Json, State},
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    bincode,
+    solana_sdk::{
+        message::{v0, VersionedMessage},
+        pubkey::Pubkey,
+        signature::Signature,
+        transaction::VersionedTransaction,
+    },
+    solana_zk_sdk::encryption::pod::elgamal::PodElGamalPubkey,
+    spl_token_2022::extension::{
+        confidential_transfer::{instruction::update_mint, ConfidentialTransferMint},
+        BaseStateWithExtensions, StateWithExtensionsOwned,
+    },
+    std::str::FromStr,
+};
+
+/// Handler for updating the confidential transfer configuration of a mint
+///
+/// Rotates (or, with `remove_auditor`, removes) the auditor ElGamal pubkey and updates the
+/// auto-approve setting, keeping the current values of omitted fields. The transaction must be
+/// signed by the confidential transfer authority of the mint.
+pub async fn update_mint_cb(
+    State(state): State<AppState>,
+    Json(request): Json<UpdateMintCbRequest>,
+) -> Result<Json<TransactionResponse>, AppError> {
+    println!("🚀 Starting update_mint_cb handler");
+
+    let mint = parse_base58_pubkey(&request.mint)?;
+    let authority = parse_base58_pubkey(&request.authority)?;
+    println!(
+        "✅ Request data is correct: mint={}, authority={}",
+        mint, authority
+    );
+
+    // Deserialize the mint data
+    println!("📦 Decoding mint account data from request");
+    let mint_account_info = {
+        let mint_account_data = state
+            .mint_data(
+                request.mint_token_account.as_ref(),
+                &mint,
+                "mint_token_account",
+            )
+            .await?;
+        StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(mint_account_data)?
+    };
+    let confidential_transfer_mint =
+        mint_account_info.get_extension::<ConfidentialTransferMint>()?;
+
+    // Only the confidential transfer authority of the mint can update it
+    let confidential_transfer_authority =
+        Option::<Pubkey>::from(confidential_transfer_mint.authority).ok_or_else(|| {
+            println!("⛔️ Mint {} has no confidential transfer authority", mint);
+            AppError::ConfidentialTransferAuthorityNotSet
+        })?;
+    if confidential_transfer_authority != authority {
+        println!(
+            "⛔️ Authority mismatch: caller {} is not the confidential transfer authority {}",
+            authority, confidential_transfer_authority
+        );
+        return Err(AppError::ConfidentialTransferAuthorityMismatch);
+    }
+
+    // A missing auditor key keeps the current auditor, removing it has to be explicit
+    let auditor_elgamal_pubkey = match (
+        &request.auditor_elgamal_pubkey,
+        request.remove_auditor.unwrap_or(false),
+    ) {
+        (Some(_), true) => {
+            println!("⛔️ Both a new auditor ElGamal pubkey and its removal were requested");
+            return Err(AppError::ConflictingAuditorUpdate);
+        }
+        (Some(elgamal_string), false) => {
+            let elgamal_pubkey = PodElGamalPubkey::from_str(elgamal_string).map_err(|_| {
+                println!(
+                    "⛔️ Failed to parse auditor ElGamal pubkey: {}",
+                    elgamal_string
+                );
+                AppError::InvalidPublicKey
+            })?;
+            println!("✅ New auditor ElGamal pubkey: {}", elgamal_pubkey);
+            Some(elgamal_pubkey)
+        }
+        (None, true) => {
+            println!("⚠️ Removing the auditor");
+            None
+        }
+        (None, false) => {
+            let current_auditor =
+                Option::<PodElGamalPubkey>::from(confidential_transfer_mint.auditor_elgamal_pubkey);
+            match &current_auditor {
+                Some(elgamal_pubkey) => {
+                    println!("✅ Keeping the auditor ElGamal pubkey: {}", elgamal_pubkey)
+                }
+                None => println!("✅ The mint keeps having no auditor"),
+            }
+            current_auditor
+        }
+    };
+
+    let auto_approve_new_accounts = request
+        .auto_approve_new_accounts
+        .unwrap_or_else(|| bool::from(confidential_transfer_mint.auto_approve_new_accounts));
+    println!(
+        "✅ Auto approve new accounts: {}",
+        auto_approve_new_accounts
+    );
+
+    let update_mint_instruction = update_mint(
+        &spl_token_2022::id(),
+        &mint,                     // Mint
+        &authority,                // Confidential transfer authority
+        &[],                       // Additional signers
+        auto_approve_new_accounts, // Auto approve new accounts
+        auditor_elgamal_pubkey,    // Optional auditor ElGamal key
+    )?;
+    println!("✅ Update mint instruction created successfully");
+
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), authority)?;
+
+    // Use the blockhash from the request, or fetch the latest one over RPC
+    let client_blockhash = state
+        .latest_blockhash(request.latest_blockhash.as_ref())
+        .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+    let priority_fee = PriorityFee::from_request(request.priority_fee.as_ref())?;
+
+    // Create a V0 message with the provided blockhash
+    let v0_message = v0::Message::try_compile(
+        &fee_payer,
+        &with_compute_budget(priority_fee.as_ref(), vec![update_mint_instruction]),
+        &lookup_tables,
+        client_blockhash,
+    )
+    .map_err(|_| AppError::SerializationError)?;
+
+    // Get the number of required signatures before moving v0_message
+    let num_required_signatures = v0_message.header.num_required_signatures as usize;
+
+    // Create a versioned transaction with placeholder signatures for required signers
+    // (will be replaced by the wallet)
+    let versioned_transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); num_required_signatures],
+        message: VersionedMessage::V0(v0_message),
+    };
+
+    // Serialize the transaction to base64
+    let serialized_transaction = match bincode::serialize(&versioned_transaction) {
+        Ok(bytes) => BASE64_STANDARD.encode(bytes),
+        Err(_) => return Err(AppError::SerializationError),
+    };
+
+    println!("✅ Transaction created successfully");
+
+    Ok(Json(TransactionResponse {
+        transaction: serialized_transaction,
+        required_signers: missing_signers(&versioned_transaction),
+        message: format!("Update mint transaction created for mint: {}", mint),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::routes::test_util,
+        solana_zk_sdk::encryption::elgamal::ElGamalKeypair,
+        spl_token_2022::{
+            extension::confidential_transfer::instruction::UpdateMintData,
+            instruction::decode_instruction_data,
+        },
+    };
+
+    async fn updated_auditor(
+        auditor_elgamal_pubkey: Option<String>,
+        remove_auditor: Option<bool>,
+    ) -> Result<Option<PodElGamalPubkey>, AppError> {
+        let authority = Pubkey::new_unique();
+        let Json(response) = update_mint_cb(
+            State(AppState::default()),
+            Json(UpdateMintCbRequest {
+                mint: Pubkey::new_unique().to_string(),
+                authority: authority.to_string(),
+                mint_token_account: Some(test_util::confidential_mint_data(&authority, false)),
+                auditor_elgamal_pubkey,
+                remove_auditor,
+                auto_approve_new_accounts: None,
+                fee_payer: None,
+                latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                address_lookup_tables: None,
+                priority_fee: None,
+            }),
+        )
+        .await?;
+
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&BASE64_STANDARD.decode(response.transaction).unwrap()).unwrap();
+        let data = &transaction.message.instructions()[0].data;
+        let update_mint_data = decode_instruction_data::<UpdateMintData>(&data[1..]).unwrap();
+        Ok(Option::<PodElGamalPubkey>::from(
+            update_mint_data.auditor_elgamal_pubkey,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_update_mint_only_removes_the_auditor_when_asked() {
+        let current_auditor = PodElGamalPubkey::from(*test_util::elgamal_keypair().pubkey());
+        let new_auditor = PodElGamalPubkey::from(*ElGamalKeypair::new_rand().pubkey());
+
+        // An omitted auditor key keeps the current auditor
+        assert_eq!(
+            updated_auditor(None, None).await.unwrap(),
+            Some(current_auditor)
+        );
+        assert_eq!(
+            updated_auditor(Some(new_auditor.to_string()), None)
+                .await
+                .unwrap(),
+            Some(new_auditor)
+        );
+        assert_eq!(updated_auditor(None, Some(true)).await.unwrap(), None);
+        assert!(matches!(
+            updated_auditor(Some(new_auditor.to_string()), Some(true)).await,
+            Err(AppError::ConflictingAuditorUpdate)
+        ));
+    }
+}
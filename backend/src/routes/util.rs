@@ -4,9 +4,31 @@ use {
     bs58,
     solana_sdk::{
         hash::Hash,
+        instruction::Instruction,
+        message::{v0, VersionedMessage},
         pubkey::{Pubkey, PUBKEY_BYTES},
+        signature::{Keypair, Signature},
+        signer::{Signer, SignerError},
+        system_instruction,
+        transaction::VersionedTransaction,
     },
-    std::str::FromStr,
+    solana_zk_sdk::{
+        encryption::{auth_encryption::AeCiphertext, elgamal::ElGamalCiphertext},
+        zk_elgamal_proof_program::{
+            instruction::ContextStateInfo, proof_data::ZkProofData, state::ProofContextState,
+        },
+    },
+    spl_memo::build_memo,
+    spl_record::state::RecordData,
+    spl_token_2022::{
+        extension::confidential_transfer::{
+            account_info::combine_balances, ConfidentialTransferAccount,
+        },
+        instruction::MAX_SIGNERS,
+        solana_zk_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
+    },
+    spl_token_confidential_transfer_proof_extraction::instruction::zk_proof_type_to_instruction,
+    std::{mem::size_of, str::FromStr},
 };
 
 // Helper function to parse a base58 address string into a Pubkey
@@ -74,3 +96,494 @@ pub fn parse_base64_base58_pubkey(encoded_address: &str) -> Result<Pubkey, AppEr
 
     Ok(pubkey)
 }
+
+/// Refactored version of spl_token_client::token::Token::confidential_transfer_create_context_state_account().
+/// Instead of sending transactions internally or calculating rent via RPC, this function now accepts
+/// the rent value from the caller and returns the instructions to be used externally.
+pub fn get_zk_proof_context_state_account_creation_instructions<
+    ZK: bytemuck::Pod + ZkProofData<U>,
+    U: bytemuck::Pod,
+>(
+    fee_payer_pubkey: &Pubkey,
+    context_state_account_pubkey: &Pubkey,
+    context_state_authority_pubkey: &Pubkey,
+    proof_data: &ZK,
+    rent: u64,
+) -> Result<(Instruction, Instruction), AppError> {
+    let space = proof_context_state_space(proof_data);
+    println!("📊 Context state account space required: {} bytes", space);
+    println!("💰 Rent for context state account: {} lamports", rent);
+
+    let context_state_info = ContextStateInfo {
+        context_state_account: context_state_account_pubkey,
+        context_state_authority: context_state_authority_pubkey,
+    };
+
+    let instruction_type = zk_proof_type_to_instruction(ZK::PROOF_TYPE)?;
+
+    println!("🔧 Creating context state account with inputs: fee_payer={}, context_state_account={}, rent={}, space={}",
+        fee_payer_pubkey, context_state_account_pubkey, rent, space);
+    let create_account_ix = system_instruction::create_account(
+        fee_payer_pubkey,
+        context_state_account_pubkey,
+        rent,
+        space as u64,
+        &solana_zk_sdk::zk_elgamal_proof_program::id(),
+    );
+
+    let verify_proof_ix =
+        instruction_type.encode_verify_proof(Some(context_state_info), proof_data);
+
+    // Return a tuple containing the create account instruction and verify proof instruction.
+    Ok((create_account_ix, verify_proof_ix))
+}
+
+// Helper function to get the space of the context state account storing the context of a proof
+pub fn proof_context_state_space<ZK: bytemuck::Pod + ZkProofData<U>, U: bytemuck::Pod>(
+    _proof_data: &ZK,
+) -> usize {
+    size_of::<ProofContextState<U>>()
+}
+
+// Helper function to get the space of the record account a proof is verified from
+pub fn proof_record_space<ZK: bytemuck::Pod + ZkProofData<U>, U: bytemuck::Pod>(
+    _proof_data: &ZK,
+) -> usize {
+    RecordData::WRITABLE_START_INDEX + size_of::<ZK>()
+}
+
+/// Maximum number of proof bytes written into a record account per transaction, leaving room for
+/// a durable nonce advance, the compute budget instructions and a separate nonce authority
+pub const RECORD_CHUNK_SIZE: usize = 640;
+
+/// Instruction groups verifying a proof into its context state account from a record account,
+/// for proofs too large to fit in a transaction as instruction data: a range proof in flows whose
+/// every transaction advances a durable nonce, or a batched u256 range proof in any flow.
+///
+/// The record account is created with `record_rent` (`None` if an interrupted attempt left it
+/// behind), the proof is written into it in chunks, and the verification closes it in the same
+/// transaction.
+pub fn get_zk_proof_record_verification_instructions<
+    ZK: bytemuck::Pod + ZkProofData<U>,
+    U: bytemuck::Pod,
+>(
+    fee_payer_pubkey: &Pubkey,
+    record_account_pubkey: &Pubkey,
+    context_state_account_pubkey: &Pubkey,
+    context_state_authority_pubkey: &Pubkey,
+    proof_data: &ZK,
+    record_rent: Option<u64>,
+) -> Result<Vec<Vec<Instruction>>, AppError> {
+    let proof_bytes = bytemuck::bytes_of(proof_data);
+    let space = proof_record_space(proof_data);
+    println!(
+        "📊 Record account {}: {} bytes",
+        record_account_pubkey, space
+    );
+
+    // The proof account authority also controls the record account
+    let mut groups = Vec::new();
+    if let Some(rent) = record_rent {
+        groups.push(vec![
+            system_instruction::create_account(
+                fee_payer_pubkey,
+                record_account_pubkey,
+                rent,
+                space as u64,
+                &spl_record::id(),
+            ),
+            spl_record::instruction::initialize(
+                record_account_pubkey,
+                context_state_authority_pubkey,
+            ),
+        ]);
+    }
+    groups.extend(
+        proof_bytes
+            .chunks(RECORD_CHUNK_SIZE)
+            .enumerate()
+            .map(|(index, chunk)| {
+                vec![spl_record::instruction::write(
+                    record_account_pubkey,
+                    context_state_authority_pubkey,
+                    (index * RECORD_CHUNK_SIZE) as u64,
+                    chunk,
+                )]
+            }),
+    );
+
+    let context_state_info = ContextStateInfo {
+        context_state_account: context_state_account_pubkey,
+        context_state_authority: context_state_authority_pubkey,
+    };
+    groups.push(vec![
+        zk_proof_type_to_instruction(ZK::PROOF_TYPE)?.encode_verify_proof_from_account(
+            Some(context_state_info),
+            record_account_pubkey,
+            RecordData::WRITABLE_START_INDEX as u32,
+        ),
+        spl_record::instruction::close_account(
+            record_account_pubkey,
+            context_state_authority_pubkey,
+            fee_payer_pubkey,
+        ),
+    ]);
+
+    Ok(groups)
+}
+
+// Helper function to parse an optional rent override from the request
+pub fn parse_rent_override(rent: Option<&String>) -> Result<Option<u64>, AppError> {
+    rent.map(|rent| {
+        rent.parse::<u64>()
+            .map_err(|_| AppError::SerializationError)
+    })
+    .transpose()
+}
+
+// Helper function to put the memo of a request, if any, right before the instruction moving
+// funds, where the `MemoTransfer` extension of a recipient requires it
+pub fn with_memo(memo: Option<&String>, instructions: Vec<Instruction>) -> Vec<Instruction> {
+    match memo {
+        Some(memo) => {
+            let mut memo_instructions = vec![build_memo(memo.as_bytes(), &[])];
+            memo_instructions.extend(instructions);
+            memo_instructions
+        }
+        None => instructions,
+    }
+}
+
+// Helper function to build a transaction signed only by the server-held keypairs, leaving
+// placeholder signatures for every other required signer
+pub fn partially_signed_transaction(
+    message: v0::Message,
+    keypairs: &[&Keypair],
+) -> Result<VersionedTransaction, AppError> {
+    let num_required_signatures = message.header.num_required_signatures as usize;
+    let mut transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); num_required_signatures],
+        message: VersionedMessage::V0(message),
+    };
+
+    let message_data = transaction.message.serialize();
+    for keypair in keypairs {
+        let index = transaction.message.static_account_keys()[..num_required_signatures]
+            .iter()
+            .position(|signer| *signer == keypair.pubkey())
+            .ok_or(SignerError::KeypairPubkeyMismatch)?;
+        transaction.signatures[index] = keypair.sign_message(&message_data);
+    }
+
+    Ok(transaction)
+}
+
+// Helper function to list the signers whose signatures are still placeholders in a transaction
+pub fn missing_signers(transaction: &VersionedTransaction) -> Vec<String> {
+    let num_required_signatures = transaction.message.header().num_required_signatures as usize;
+    transaction.message.static_account_keys()[..num_required_signatures]
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(signer, _)| signer.to_string())
+        .collect()
+}
+
+// Helper function to parse the optional fee payer of a request, which pays the transaction fees
+// and proof account rent instead of the default payer (e.g. to sponsor users without SOL)
+pub fn parse_fee_payer(
+    fee_payer: Option<&String>,
+    default_fee_payer: Pubkey,
+) -> Result<Pubkey, AppError> {
+    match fee_payer {
+        Some(fee_payer) => {
+            let fee_payer = parse_base58_pubkey(fee_payer)?;
+            println!("✅ Using sponsored fee payer: {}", fee_payer);
+            Ok(fee_payer)
+        }
+        None => Ok(default_fee_payer),
+    }
+}
+
+/// Authority of a token account: either its owner wallet, or an SPL multisig together with the
+/// signers approving the instruction
+pub struct TokenAccountAuthority {
+    pub authority: Pubkey,
+    pub multisig_signers: Vec<Pubkey>,
+}
+
+impl TokenAccountAuthority {
+    /// Additional signers in the shape expected by the token instruction builders
+    pub fn signers(&self) -> Vec<&Pubkey> {
+        self.multisig_signers.iter().collect()
+    }
+
+    /// A multisig account can't sign (or pay for) transactions, so its first signer does
+    pub fn fee_payer(&self) -> Pubkey {
+        self.multisig_signers
+            .first()
+            .copied()
+            .unwrap_or(self.authority)
+    }
+}
+
+// Helper function to resolve the authority of a token account from the optional multisig
+// fields of a request
+pub fn parse_token_account_authority(
+    owner: &Pubkey,
+    multisig: Option<&String>,
+    multisig_signers: Option<&Vec<String>>,
+) -> Result<TokenAccountAuthority, AppError> {
+    let multisig_signers = multisig_signers
+        .map(|signers| {
+            signers
+                .iter()
+                .map(|signer| parse_base58_pubkey(signer))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let Some(multisig) = multisig else {
+        if !multisig_signers.is_empty() {
+            println!("⛔️ Multisig signers provided without a multisig account");
+            return Err(AppError::MultisigNotProvided);
+        }
+        return Ok(TokenAccountAuthority {
+            authority: *owner,
+            multisig_signers,
+        });
+    };
+
+    let multisig = parse_base58_pubkey(multisig)?;
+    if multisig != *owner {
+        println!(
+            "⛔️ Multisig {} is not the token account owner {}",
+            multisig, owner
+        );
+        return Err(AppError::MultisigOwnerMismatch);
+    }
+    if multisig_signers.is_empty() || multisig_signers.len() > MAX_SIGNERS {
+        println!(
+            "⛔️ Invalid number of multisig signers: {} (expected 1 to {})",
+            multisig_signers.len(),
+            MAX_SIGNERS
+        );
+        return Err(AppError::InvalidMultisigSigners);
+    }
+    println!(
+        "✅ Using multisig {} with {} signers",
+        multisig,
+        multisig_signers.len()
+    );
+
+    Ok(TokenAccountAuthority {
+        authority: multisig,
+        multisig_signers,
+    })
+}
+
+/// Decrypts the pending balance of a confidential token account.
+/// The pending balance is only encrypted under the ElGamal key, split into a low (16 bit) and a
+/// high (48 bit) part that are each decrypted via discrete log.
+pub fn decrypt_pending_balance(
+    confidential_transfer_account: &ConfidentialTransferAccount,
+    elgamal_keypair: &ElGamalKeypair,
+) -> Result<u64, AppError> {
+    let pending_balance_lo =
+        ElGamalCiphertext::try_from(confidential_transfer_account.pending_balance_lo)?;
+    let pending_balance_hi =
+        ElGamalCiphertext::try_from(confidential_transfer_account.pending_balance_hi)?;
+
+    let decrypted_pending_balance_lo = elgamal_keypair
+        .secret()
+        .decrypt_u32(&pending_balance_lo)
+        .ok_or(AppError::DecryptionError)?;
+    let decrypted_pending_balance_hi = elgamal_keypair
+        .secret()
+        .decrypt_u32(&pending_balance_hi)
+        .ok_or(AppError::DecryptionError)?;
+
+    combine_balances(decrypted_pending_balance_lo, decrypted_pending_balance_hi)
+        .ok_or(AppError::DecryptionError)
+}
+
+/// Verifies that the available balance of the sender covers the requested amount, and returns it.
+/// Proof generation fails with a generic error when it doesn't, so this is checked up front to
+/// return the available and requested amounts instead (and the pending balance, which is only
+/// decrypted when the available balance is insufficient).
+pub fn check_sender_balance(
+    confidential_transfer_account: &ConfidentialTransferAccount,
+    elgamal_keypair: &ElGamalKeypair,
+    aes_key: &AeKey,
+    requested: u64,
+) -> Result<u64, AppError> {
+    let decryptable_available_balance =
+        AeCiphertext::try_from(confidential_transfer_account.decryptable_available_balance)
+            .map_err(|_| AppError::SerializationError)?;
+    let available = aes_key
+        .decrypt(&decryptable_available_balance)
+        .ok_or(AppError::DecryptionError)?;
+    println!("✅ Decrypted available balance: {}", available);
+
+    if available >= requested {
+        return Ok(available);
+    }
+
+    let pending = decrypt_pending_balance(confidential_transfer_account, elgamal_keypair)?;
+    println!(
+        "⛔️ Insufficient balance: requested {}, available {}, pending {}",
+        requested, available, pending
+    );
+    Err(AppError::InsufficientConfidentialBalance {
+        available,
+        requested,
+        pending,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{routes::test_util, state::AppState},
+        axum::{http::StatusCode, response::IntoResponse},
+        solana_sdk::{rent::Rent, system_instruction::SystemInstruction},
+        solana_zk_sdk::zk_elgamal_proof_program::proof_data::{
+            ZeroCiphertextProofContext, ZeroCiphertextProofData,
+        },
+        spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensionsOwned},
+    };
+
+    fn check(available: u64, pending: u64, requested: u64) -> Result<u64, AppError> {
+        let token_account_data = test_util::confidential_token_account_data(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            available,
+            pending,
+            1,
+        );
+        let token_account_info =
+            StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(
+                BASE64_STANDARD.decode(token_account_data).unwrap(),
+            )
+            .unwrap();
+
+        check_sender_balance(
+            token_account_info
+                .get_extension::<ConfidentialTransferAccount>()
+                .unwrap(),
+            &test_util::elgamal_keypair(),
+            &test_util::aes_key(),
+            requested,
+        )
+    }
+
+    #[test]
+    fn test_parse_token_account_authority() {
+        let owner = Pubkey::new_unique();
+        let signers = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let signer_strings = signers.iter().map(|signer| signer.to_string()).collect();
+
+        let single = parse_token_account_authority(&owner, None, None).unwrap();
+        assert_eq!(single.fee_payer(), owner);
+        assert!(single.signers().is_empty());
+
+        let multisig =
+            parse_token_account_authority(&owner, Some(&owner.to_string()), Some(&signer_strings))
+                .unwrap();
+        assert_eq!(multisig.authority, owner);
+        assert_eq!(multisig.fee_payer(), signers[0]);
+        assert_eq!(multisig.signers(), vec![&signers[0], &signers[1]]);
+
+        assert!(matches!(
+            parse_token_account_authority(
+                &owner,
+                Some(&Pubkey::new_unique().to_string()),
+                Some(&signer_strings),
+            ),
+            Err(AppError::MultisigOwnerMismatch)
+        ));
+        assert!(matches!(
+            parse_token_account_authority(&owner, Some(&owner.to_string()), None),
+            Err(AppError::InvalidMultisigSigners)
+        ));
+        assert!(matches!(
+            parse_token_account_authority(&owner, None, Some(&signer_strings)),
+            Err(AppError::MultisigNotProvided)
+        ));
+    }
+
+    #[test]
+    fn test_check_sender_balance() {
+        assert_eq!(check(100, 0, 100).unwrap(), 100);
+
+        let error = check(100, 50, 120).unwrap_err();
+        assert!(matches!(
+            error,
+            AppError::InsufficientConfidentialBalance {
+                available: 100,
+                requested: 120,
+                pending: 50,
+            }
+        ));
+        assert!(error.to_string().contains("apply the pending balance"));
+        assert_eq!(
+            error.into_response().status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let error = check(100, 10, 120).unwrap_err();
+        assert!(!error.to_string().contains("apply the pending balance"));
+    }
+
+    #[tokio::test]
+    async fn test_proof_context_state_rent() {
+        let elgamal_keypair = test_util::elgamal_keypair();
+        let ciphertext = elgamal_keypair.pubkey().encrypt(0_u64);
+        let proof_data = ZeroCiphertextProofData::new(&elgamal_keypair, &ciphertext).unwrap();
+        let space = proof_context_state_space(&proof_data);
+        assert_eq!(
+            space,
+            size_of::<ProofContextState<ZeroCiphertextProofContext>>()
+        );
+
+        // The request overrides the rent, which otherwise comes from the cluster, or from the
+        // default rent without an RPC endpoint
+        let state = AppState::default();
+        let rpc_state = test_util::mock_rpc_state(vec![]).await;
+        assert_eq!(
+            state.rent(None, space).await.unwrap(),
+            Rent::default().minimum_balance(space)
+        );
+        assert_eq!(
+            rpc_state.rent(None, space).await.unwrap(),
+            space as u64 * test_util::MOCK_RENT_PER_BYTE
+        );
+        assert_eq!(
+            rpc_state
+                .rent(Some(&"42".to_string()), space)
+                .await
+                .unwrap(),
+            42
+        );
+        assert!(state.rent(Some(&"lots".to_string()), space).await.is_err());
+
+        let (create_account_ix, _) = get_zk_proof_context_state_account_creation_instructions(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &proof_data,
+            42,
+        )
+        .unwrap();
+        assert_eq!(
+            bincode::deserialize::<SystemInstruction>(&create_account_ix.data).unwrap(),
+            SystemInstruction::CreateAccount {
+                lamports: 42,
+                space: space as u64,
+                owner: solana_zk_sdk::zk_elgamal_proof_program::id(),
+            }
+        );
+    }
+}
@@ -2,24 +2,28 @@ use {
     crate::{
         errors::AppError,
         models::{MultiTransactionResponse, WithdrawCbRequest},
-        routes::util::parse_latest_blockhash,
+        routes::{
+            bundle::{check_bundle_size, tip_instruction},
+            nonce::TransactionLifetime,
+            packer::{fits_in_transaction, pack_transactions},
+            priority_fee::PriorityFee,
+            proof_accounts::{
+                check_withdraw_proof_context, flow_nonce, ExistingProofAccounts, ProofAccount,
+            },
+            util::{
+                check_sender_balance, get_zk_proof_context_state_account_creation_instructions,
+                get_zk_proof_record_verification_instructions, missing_signers, parse_fee_payer,
+                parse_token_account_authority, proof_context_state_space, proof_record_space,
+                with_memo,
+            },
+        },
+        state::AppState,
     },
-    axum::extract::Json,
+    axum::extract::{Json, State},
     base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
     bincode,
-    solana_sdk::{
-        message::{v0, VersionedMessage},
-        pubkey::Pubkey,
-        signature::{Keypair, NullSigner, Signature},
-        signer::Signer,
-        system_instruction,
-        transaction::VersionedTransaction,
-    },
-    solana_zk_sdk::zk_elgamal_proof_program::{
-        instruction::{close_context_state, ContextStateInfo},
-        proof_data::ZkProofData,
-        state::ProofContextState,
-    },
+    solana_sdk::{signature::Signature, signer::Signer},
+    solana_zk_sdk::zk_elgamal_proof_program::instruction::{close_context_state, ContextStateInfo},
     spl_associated_token_account::get_associated_token_address_with_program_id,
     spl_token_2022::{
         error::TokenError,
@@ -32,54 +36,53 @@ use {
         },
         solana_zk_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
     },
-    spl_token_confidential_transfer_proof_extraction::instruction::ProofLocation,
+    spl_token_confidential_transfer_proof_extraction::instruction::{ProofData, ProofLocation},
     spl_token_confidential_transfer_proof_generation::withdraw::WithdrawProofData,
-    std::str::FromStr,
+    std::{num::NonZeroI8, str::FromStr},
 };
 
 /// Handler for the withdraw-cb endpoint
 ///
 /// This endpoint creates a transaction to withdraw tokens from a confidential token account
 pub async fn withdraw_cb(
+    State(state): State<AppState>,
     Json(request): Json<WithdrawCbRequest>,
 ) -> Result<Json<MultiTransactionResponse>, AppError> {
     println!("📝 Processing withdraw-cb request");
 
     // Decode recipient token account info
     let recipient_token_account_info = {
-        let recipient_token_account_data =
-            BASE64_STANDARD.decode(&request.recipient_token_account)?;
+        let recipient_token_account_data = state
+            .account_data(
+                request.recipient_token_account.as_ref(),
+                request.recipient_token_account_address.as_ref(),
+                "recipient_token_account",
+            )
+            .await?;
         StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(
             recipient_token_account_data,
         )?
     };
 
-    // Parse rent values for proof account creation
-    let equality_proof_rent = request
-        .equality_proof_rent
-        .parse::<u64>()
-        .map_err(|_| AppError::SerializationError)?;
-    let range_proof_rent = request
-        .range_proof_rent
-        .parse::<u64>()
-        .map_err(|_| AppError::SerializationError)?;
-
     // Decode mint account info
     let mint_account_info = {
-        let mint_account_data = BASE64_STANDARD.decode(&request.mint_account_info)?;
+        let mint_account_data = state
+            .mint_data(
+                request.mint_account_info.as_ref(),
+                &recipient_token_account_info.base.mint,
+                "mint_account_info",
+            )
+            .await?;
         StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(mint_account_data)?
     };
 
-    // Decode client blockhash
-    let client_blockhash = parse_latest_blockhash(&request.latest_blockhash)?;
-
     // Decode withdraw amount
     let withdraw_amount = u64::from_str(&request.withdraw_amount_lamports)
         .map_err(|_| AppError::SerializationError)?;
 
     // Create the ElGamal keypair and AES key for the sender token account
     // Create the sender's ElGamal keypair in a temporary scope
-    let receiver_elgamal_keypair = {
+    let elgamal_signature = {
         println!(
             "🔐 Decoding ElGamal signature: {}",
             request.elgamal_signature
@@ -95,9 +98,10 @@ pub async fn withdraw_cb(
             .map_err(|_| AppError::SerializationError)?;
         println!("✅ ElGamal signature created successfully");
 
-        ElGamalKeypair::new_from_signature(&elgamal_signature)
-            .map_err(|_| AppError::SerializationError)?
+        elgamal_signature
     };
+    let receiver_elgamal_keypair = ElGamalKeypair::new_from_signature(&elgamal_signature)
+        .map_err(|_| AppError::SerializationError)?;
     println!("✅ ElGamal keypair created successfully");
 
     // Create the sender's AES key in a temporary scope
@@ -118,6 +122,14 @@ pub async fn withdraw_cb(
     };
     println!("✅ AES key created successfully");
 
+    // Fail with the balances instead of a generic proof generation error
+    check_sender_balance(
+        recipient_token_account_info.get_extension::<ConfidentialTransferAccount>()?,
+        &receiver_elgamal_keypair,
+        &receiver_aes_key,
+        withdraw_amount,
+    )?;
+
     // Unpack the ConfidentialTransferAccount extension portion of the token account data
     let extension_data =
         recipient_token_account_info.get_extension::<ConfidentialTransferAccount>()?;
@@ -125,8 +137,32 @@ pub async fn withdraw_cb(
     // Confidential Transfer extension information needed to construct a `Withdraw` instruction.
     let withdraw_account_info = WithdrawAccountInfo::new(extension_data);
 
+    // The token account owner may be an SPL multisig, whose first signer then pays the fees
+    let authority = parse_token_account_authority(
+        &recipient_token_account_info.base.owner,
+        request.multisig.as_ref(),
+        request.multisig_signers.as_ref(),
+    )?;
+    let fee_payer = parse_fee_payer(request.fee_payer.as_ref(), authority.fee_payer())?;
+
     // Authority for the withdraw proof account (to close the account)
-    let context_state_authority = &recipient_token_account_info.base.owner;
+    let context_state_authority = &fee_payer;
+
+    // Transactions with room for it advance their own durable nonce if nonce accounts are given,
+    // so that slow approvals cannot expire the flow halfway through
+    let mut lifetime = TransactionLifetime::from_request(
+        &state,
+        request.latest_blockhash.as_ref(),
+        request.nonce_accounts.as_ref(),
+        request.nonce_account_data.as_ref(),
+        request.nonce_authority.as_ref(),
+        fee_payer,
+    )
+    .await?;
+    let lookup_tables = state
+        .lookup_tables(request.address_lookup_tables.as_ref())
+        .await?;
+    let priority_fee = PriorityFee::from_request(request.priority_fee.as_ref())?;
 
     // Create a withdraw proof data
     let WithdrawProofData {
@@ -138,8 +174,74 @@ pub async fn withdraw_cb(
         &receiver_aes_key,
     )?;
 
-    let equality_proof_context_state_keypair = Keypair::new();
-    let range_proof_context_state_keypair = Keypair::new();
+    // Use the proof account rents from the request, or the rent-exempt minimums
+    let range_proof_rent = state
+        .rent(
+            request.range_proof_rent.as_ref(),
+            proof_context_state_space(&range_proof_data),
+        )
+        .await?;
+    let equality_proof_rent = state
+        .rent(
+            request.equality_proof_rent.as_ref(),
+            proof_context_state_space(&equality_proof_data),
+        )
+        .await?;
+
+    // Derive the addresses of the proof accounts, so that those of an abandoned flow can be
+    // recovered
+    let recipient_token_account = get_associated_token_address_with_program_id(
+        &recipient_token_account_info.base.owner,
+        &recipient_token_account_info.base.mint,
+        &spl_token_2022::id(),
+    );
+    let flow_nonce = flow_nonce(request.flow_nonce, extension_data);
+    let equality_proof_context_state_keypair = ProofAccount::WithdrawEquality.keypair(
+        &elgamal_signature,
+        &recipient_token_account,
+        flow_nonce,
+    );
+    let range_proof_context_state_keypair = ProofAccount::WithdrawRange.keypair(
+        &elgamal_signature,
+        &recipient_token_account,
+        flow_nonce,
+    );
+    let range_proof_record_keypair = ProofAccount::WithdrawRangeRecord.keypair(
+        &elgamal_signature,
+        &recipient_token_account,
+        flow_nonce,
+    );
+    let equality_proof_context_state_address = equality_proof_context_state_keypair.pubkey();
+    let range_proof_context_state_address = range_proof_context_state_keypair.pubkey();
+    let range_proof_record_address = range_proof_record_keypair.pubkey();
+
+    // Proof accounts of an interrupted attempt, to return only its missing transactions
+    let mut existing_proof_accounts = ExistingProofAccounts::from_request(
+        &state,
+        request.resume.as_ref(),
+        &[
+            equality_proof_context_state_address,
+            range_proof_context_state_address,
+            range_proof_record_address,
+        ],
+    )
+    .await?;
+    let discarded_proof_accounts = existing_proof_accounts.discard_partially_verified(&[
+        &equality_proof_context_state_address,
+        &range_proof_context_state_address,
+    ]);
+    if let Some(proof_context) = existing_proof_accounts.withdraw_proof_context(
+        &equality_proof_context_state_address,
+        &range_proof_context_state_address,
+        context_state_authority,
+    )? {
+        check_withdraw_proof_context(
+            &proof_context,
+            extension_data,
+            &receiver_elgamal_keypair,
+            withdraw_amount,
+        )?;
+    }
 
     // Range Proof Instructions------------------------------------------------------------------------------
     let (range_create_ix, range_verify_ix) =
@@ -150,6 +252,31 @@ pub async fn withdraw_cb(
             &range_proof_data,
             range_proof_rent,
         )?;
+    // With durable nonces, the range proof is verified from a record account instead
+    let range_proof_record =
+        lifetime.is_durable() || existing_proof_accounts.exists(&range_proof_record_address);
+    let range_verify_groups = if range_proof_record {
+        let range_proof_record_rent = if existing_proof_accounts.exists(&range_proof_record_address)
+        {
+            None
+        } else {
+            Some(
+                state
+                    .rent(None, proof_record_space(&range_proof_data))
+                    .await?,
+            )
+        };
+        get_zk_proof_record_verification_instructions(
+            &context_state_authority,
+            &range_proof_record_address,
+            &range_proof_context_state_address,
+            &context_state_authority,
+            &range_proof_data,
+            range_proof_record_rent,
+        )?
+    } else {
+        vec![vec![range_verify_ix]]
+    };
 
     // Equality Proof Instructions---------------------------------------------------------------------------
     let (equality_create_ix, equality_verify_ix) =
@@ -160,54 +287,27 @@ pub async fn withdraw_cb(
             &equality_proof_data,
             equality_proof_rent,
         )?;
-
-    let tx1 = {
-        let message = v0::Message::try_compile(
-            &context_state_authority,
-            &[equality_create_ix, equality_verify_ix, range_create_ix],
-            &[],
-            client_blockhash,
-        )?;
-
-        let versioned_message = VersionedMessage::V0(message.clone());
-        VersionedTransaction::try_new(
-            versioned_message,
-            &[
-                &NullSigner::new(&context_state_authority) as &dyn Signer,
-                &range_proof_context_state_keypair,
-                &equality_proof_context_state_keypair,
-            ],
-        )?
-    };
-
-    let tx2 = {
-        let message = v0::Message::try_compile(
-            &context_state_authority,
-            &[range_verify_ix],
-            &[],
-            client_blockhash,
-        )?;
-
-        let versioned_message = VersionedMessage::V0(message.clone());
-        VersionedTransaction::try_new(
-            versioned_message,
-            &[&NullSigner::new(&context_state_authority) as &dyn Signer],
-        )?
-    };
-
-    let tx3 = {
-        let new_decryptable_available_balance = withdraw_account_info
-            .new_decryptable_available_balance(withdraw_amount, &receiver_aes_key)
-            .map_err(|_| TokenError::AccountDecryption)?
-            .into();
-
-        let recipient_token_account = get_associated_token_address_with_program_id(
-            &recipient_token_account_info.base.owner,
-            &recipient_token_account_info.base.mint,
-            &spl_token_2022::id(),
-        );
-
-        let instructions = withdraw(
+    let equality_verify_groups = vec![vec![equality_verify_ix]];
+
+    let new_decryptable_available_balance = withdraw_account_info
+        .new_decryptable_available_balance(withdraw_amount, &receiver_aes_key)
+        .map_err(|_| TokenError::AccountDecryption)?
+        .into();
+
+    // The equality proof is verified in the withdraw transaction (at an instruction offset) if it
+    // fits there, and pre-verified into a context state account otherwise. The range proof never
+    // fits, and a proof account left by an interrupted attempt is always used.
+    let withdraw_instructions = |inline_equality_proof: bool| {
+        let equality_proof_location = if inline_equality_proof {
+            ProofLocation::InstructionOffset(
+                NonZeroI8::new(1).unwrap(),
+                ProofData::InstructionData(&equality_proof_data),
+            )
+        } else {
+            ProofLocation::ContextStateAccount(&equality_proof_context_state_address)
+        };
+
+        withdraw(
             &spl_token_2022::id(),
             &recipient_token_account,
             &recipient_token_account_info.base.mint,
@@ -215,64 +315,124 @@ pub async fn withdraw_cb(
             mint_account_info.base.decimals,
             &new_decryptable_available_balance,
             &recipient_token_account_info.base.owner,
-            &vec![],
-            ProofLocation::ContextStateAccount(&equality_proof_context_state_keypair.pubkey()),
-            ProofLocation::ContextStateAccount(&range_proof_context_state_keypair.pubkey()),
-        )?;
-
-        let message = v0::Message::try_compile(
-            &recipient_token_account_info.base.owner,
-            &instructions,
-            &[],
-            client_blockhash,
-        )?;
-
-        VersionedTransaction {
-            signatures: vec![Signature::default()],
-            message: VersionedMessage::V0(message),
-        }
+            &authority.signers(),
+            equality_proof_location,
+            ProofLocation::ContextStateAccount(&range_proof_context_state_address),
+        )
+        .map(|instructions| with_memo(request.memo.as_ref(), instructions))
     };
+    let inline_equality_proof = !existing_proof_accounts
+        .exists(&equality_proof_context_state_address)
+        && fits_in_transaction(
+            &fee_payer,
+            &withdraw_instructions(true)?,
+            &lifetime,
+            &lookup_tables,
+            priority_fee.as_ref(),
+        )?;
+    println!(
+        "✅ Equality proof verified in the withdraw transaction: {}",
+        inline_equality_proof
+    );
 
-    let tx4 = {
-        // Lamports from the closed proof accounts will be sent to this account
-        let destination_account = &context_state_authority;
-
-        // Close the equality proof account
-        let close_equality_proof_instruction = close_context_state(
-            ContextStateInfo {
-                context_state_account: &equality_proof_context_state_keypair.pubkey(),
-                context_state_authority: &context_state_authority,
-            },
-            &destination_account,
-        );
+    // Create the context state accounts for the other proofs, in the order they are verified
+    let mut context_state_proofs = vec![(
+        &range_proof_context_state_keypair,
+        range_create_ix,
+        range_verify_groups,
+    )];
+    if !inline_equality_proof {
+        context_state_proofs.push((
+            &equality_proof_context_state_keypair,
+            equality_create_ix,
+            equality_verify_groups,
+        ));
+    }
+
+    // Close the proof accounts an interrupted attempt only partially verified, allocate the proof
+    // accounts, then verify the proofs into them, skipping the steps that attempt already took
+    let mut groups = discarded_proof_accounts
+        .iter()
+        .map(|context_state_account| {
+            vec![close_context_state(
+                ContextStateInfo {
+                    context_state_account,
+                    context_state_authority,
+                },
+                context_state_authority,
+            )]
+        })
+        .collect::<Vec<_>>();
+    groups.extend(
+        context_state_proofs
+            .iter()
+            .filter(|(keypair, _, _)| !existing_proof_accounts.exists(&keypair.pubkey()))
+            .map(|(_, create_ix, _)| vec![create_ix.clone()]),
+    );
+    groups.extend(
+        context_state_proofs
+            .iter()
+            .filter(|(keypair, _, _)| !existing_proof_accounts.is_verified(&keypair.pubkey()))
+            .flat_map(|(_, _, verify_groups)| verify_groups.clone()),
+    );
 
-        // Close the range proof account
-        let close_range_proof_instruction = close_context_state(
-            ContextStateInfo {
-                context_state_account: &range_proof_context_state_keypair.pubkey(),
-                context_state_authority: &context_state_authority,
-            },
-            &destination_account,
-        );
+    // Execute the withdraw along with its inline proof
+    groups.push(withdraw_instructions(inline_equality_proof)?);
+
+    // Lamports from the closed proof accounts will be sent to this account
+    let destination_account = &context_state_authority;
+    groups.extend(
+        context_state_proofs
+            .iter()
+            .map(|(context_state_keypair, _, _)| {
+                vec![close_context_state(
+                    ContextStateInfo {
+                        context_state_account: &context_state_keypair.pubkey(),
+                        context_state_authority,
+                    },
+                    destination_account,
+                )]
+            }),
+    );
 
-        let message = v0::Message::try_compile(
-            &context_state_authority,
-            &[
-                close_equality_proof_instruction,
-                close_range_proof_instruction,
-            ],
-            &[],
-            client_blockhash,
-        )?;
+    // In bundle mode, the last transaction tips the block engine to land the flow atomically
+    if let Some(tip_instruction) = tip_instruction(
+        &state,
+        &fee_payer,
+        &authority.fee_payer(),
+        request.bundle.as_ref(),
+    )
+    .await?
+    {
+        groups.push(vec![tip_instruction]);
+    }
+
+    // Fill as few transactions as possible
+    let mut proof_account_keypairs = context_state_proofs
+        .iter()
+        .map(|(context_state_keypair, _, _)| *context_state_keypair)
+        .collect::<Vec<_>>();
+    if range_proof_record {
+        proof_account_keypairs.push(&range_proof_record_keypair);
+    }
+    let mut transactions = pack_transactions(
+        &fee_payer,
+        groups,
+        &proof_account_keypairs,
+        &mut lifetime,
+        &lookup_tables,
+        priority_fee.as_ref(),
+    )?;
+    if request.bundle.is_some() {
+        check_bundle_size(transactions.len())?;
+    }
 
-        VersionedTransaction {
-            signatures: vec![Signature::default()],
-            message: VersionedMessage::V0(message),
-        }
-    };
+    // Sign as fee payer if the transactions are sponsored by the server
+    state
+        .co_sign("withdraw-cb", withdraw_amount, &mut transactions)
+        .await?;
 
-    // Return all transactions
-    let transactions = vec![tx1, tx2, tx3, tx4];
+    let required_signers = transactions.iter().map(missing_signers).collect();
     let response = MultiTransactionResponse {
         transactions: transactions
             .into_iter()
@@ -287,61 +447,573 @@ pub async fn withdraw_cb(
                 Ok(serialized_transaction)
             })
             .collect::<Result<Vec<String>, AppError>>()?,
+        required_signers,
+        flow_nonce: Some(flow_nonce),
+        flow_nonce_count: Some(1),
         message: "MultiTransaction for confidential transfer created successfully".to_string(),
     };
 
     Ok(Json(response))
 }
 
-/// Refactored version of spl_token_client::token::Token::confidential_transfer_create_context_state_account().
-/// Instead of sending transactions internally or calculating rent via RPC, this function now accepts
-/// the rent value from the caller and returns the instructions to be used externally.
-fn get_zk_proof_context_state_account_creation_instructions<
-    ZK: bytemuck::Pod + ZkProofData<U>,
-    U: bytemuck::Pod,
->(
-    fee_payer_pubkey: &Pubkey,
-    context_state_account_pubkey: &Pubkey,
-    context_state_authority_pubkey: &Pubkey,
-    proof_data: &ZK,
-    rent: u64,
-) -> Result<
-    (
-        solana_sdk::instruction::Instruction,
-        solana_sdk::instruction::Instruction,
-    ),
-    AppError,
-> {
-    use spl_token_confidential_transfer_proof_extraction::instruction::zk_proof_type_to_instruction;
-    use std::mem::size_of;
-
-    let space = size_of::<ProofContextState<U>>();
-    println!("📊 Context state account space required: {} bytes", space);
-    println!(
-        "💰 Using provided rent for context state account: {} lamports",
-        rent
-    );
-
-    let context_state_info = ContextStateInfo {
-        context_state_account: context_state_account_pubkey,
-        context_state_authority: context_state_authority_pubkey,
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            models::{PriorityFeeRequest, ProofAccountRequest, ResumeRequest},
+            routes::test_util,
+        },
+        solana_sdk::{
+            compute_budget::{self, ComputeBudgetInstruction},
+            hash::Hash,
+            nonce::state::DurableNonce,
+            packet::PACKET_DATA_SIZE,
+            pubkey::Pubkey,
+            system_instruction::SystemInstruction,
+            transaction::VersionedTransaction,
+        },
+        solana_zk_sdk::zk_elgamal_proof_program::{
+            instruction::ProofInstruction,
+            proof_data::{
+                BatchedRangeProofContext, BatchedRangeProofU64Data, ProofType, ZkProofData,
+            },
+            state::ProofContextState,
+        },
     };
 
-    let instruction_type = zk_proof_type_to_instruction(ZK::PROOF_TYPE)?;
-
-    println!("🔧 Creating context state account with inputs: fee_payer={}, context_state_account={}, rent={}, space={}",
-        fee_payer_pubkey, context_state_account_pubkey, rent, space);
-    let create_account_ix = system_instruction::create_account(
-        fee_payer_pubkey,
-        context_state_account_pubkey,
-        rent,
-        space as u64,
-        &solana_zk_sdk::zk_elgamal_proof_program::id(),
-    );
-
-    let verify_proof_ix =
-        instruction_type.encode_verify_proof(Some(context_state_info), proof_data);
+    #[tokio::test]
+    async fn test_withdraw_over_rpc_funds_proof_accounts_with_cluster_rent() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let state = test_util::mock_rpc_state(vec![
+            (
+                token_account,
+                test_util::confidential_token_account_data(&owner, &mint, 1_000, 0, 0),
+            ),
+            (mint, test_util::confidential_mint_data(&owner, false)),
+        ])
+        .await;
+
+        let Json(response) = withdraw_cb(
+            State(state),
+            Json(WithdrawCbRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                aes_signature: test_util::aes_signature(),
+                recipient_token_account: None,
+                recipient_token_account_address: Some(token_account.to_string()),
+                mint_account_info: None,
+                withdraw_amount_lamports: "100".to_string(),
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: None,
+                latest_blockhash: None,
+                address_lookup_tables: None,
+                nonce_accounts: None,
+                nonce_account_data: None,
+                nonce_authority: None,
+                equality_proof_rent: None,
+                range_proof_rent: None,
+                priority_fee: None,
+                bundle: None,
+                flow_nonce: None,
+                resume: None,
+                memo: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        // Every proof account is funded with the rent-exempt minimum reported by the RPC node
+        let mut proof_accounts = 0;
+        for transaction in response.transactions {
+            let transaction: VersionedTransaction =
+                bincode::deserialize(&BASE64_STANDARD.decode(transaction).unwrap()).unwrap();
+            assert_eq!(
+                *transaction.message.recent_blockhash(),
+                test_util::mock_blockhash()
+            );
+
+            let account_keys = transaction.message.static_account_keys();
+            for instruction in transaction.message.instructions() {
+                // Only the system program has the all-zero address
+                if account_keys[instruction.program_id_index as usize] != Pubkey::default() {
+                    continue;
+                }
+                let SystemInstruction::CreateAccount {
+                    lamports, space, ..
+                } = bincode::deserialize(&instruction.data).unwrap()
+                else {
+                    panic!("unexpected system instruction");
+                };
+                assert_eq!(lamports, space * test_util::MOCK_RENT_PER_BYTE);
+                proof_accounts += 1;
+            }
+        }
+        assert_eq!(proof_accounts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_resumes_from_verified_proof_accounts() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account_data =
+            test_util::confidential_token_account_data(&owner, &mint, 1_000, 0, 0);
+        let token_account =
+            get_associated_token_address_with_program_id(&owner, &mint, &spl_token_2022::id());
+        let elgamal_signature = Signature::try_from(
+            BASE64_STANDARD
+                .decode(test_util::elgamal_signature())
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        let equality_proof_account = ProofAccount::WithdrawEquality
+            .keypair(&elgamal_signature, &token_account, 1)
+            .pubkey();
+        let range_proof_account = ProofAccount::WithdrawRange
+            .keypair(&elgamal_signature, &token_account, 1)
+            .pubkey();
+
+        // The interrupted attempt verified both proofs of a withdrawal of 100
+        let WithdrawProofData {
+            equality_proof_data,
+            range_proof_data,
+        } = {
+            let token_account_info =
+                StateWithExtensionsOwned::<spl_token_2022::state::Account>::unpack(
+                    BASE64_STANDARD.decode(&token_account_data).unwrap(),
+                )
+                .unwrap();
+            WithdrawAccountInfo::new(
+                token_account_info
+                    .get_extension::<ConfidentialTransferAccount>()
+                    .unwrap(),
+            )
+            .generate_proof_data(100, &test_util::elgamal_keypair(), &test_util::aes_key())
+            .unwrap()
+        };
+        let resume = Some(ResumeRequest {
+            proof_accounts: vec![
+                ProofAccountRequest {
+                    address: equality_proof_account.to_string(),
+                    data: Some(BASE64_STANDARD.encode(ProofContextState::encode(
+                        &owner,
+                        ProofType::CiphertextCommitmentEquality,
+                        equality_proof_data.context_data(),
+                    ))),
+                },
+                ProofAccountRequest {
+                    address: range_proof_account.to_string(),
+                    data: Some(BASE64_STANDARD.encode(ProofContextState::encode(
+                        &owner,
+                        ProofType::BatchedRangeProofU64,
+                        range_proof_data.context_data(),
+                    ))),
+                },
+            ],
+        });
+
+        let withdraw = |withdraw_amount: &str, resume: Option<ResumeRequest>| {
+            withdraw_cb(
+                State(AppState::default()),
+                Json(WithdrawCbRequest {
+                    elgamal_signature: test_util::elgamal_signature(),
+                    aes_signature: test_util::aes_signature(),
+                    recipient_token_account: Some(token_account_data.clone()),
+                    recipient_token_account_address: None,
+                    mint_account_info: Some(test_util::confidential_mint_data(&owner, false)),
+                    withdraw_amount_lamports: withdraw_amount.to_string(),
+                    multisig: None,
+                    multisig_signers: None,
+                    fee_payer: None,
+                    latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                    address_lookup_tables: None,
+                    nonce_accounts: None,
+                    nonce_account_data: None,
+                    nonce_authority: None,
+                    equality_proof_rent: None,
+                    range_proof_rent: None,
+                    priority_fee: None,
+                    bundle: None,
+                    flow_nonce: Some(1),
+                    resume,
+                    memo: None,
+                }),
+            )
+        };
+
+        // Only the withdrawal and the closing of both proof accounts are left
+        let Json(response) = withdraw("100", resume.clone()).await.unwrap();
+        assert_eq!(response.transactions.len(), 1);
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&BASE64_STANDARD.decode(&response.transactions[0]).unwrap())
+                .unwrap();
+        assert_eq!(transaction.message.instructions().len(), 3);
+        assert!(transaction
+            .message
+            .static_account_keys()
+            .contains(&range_proof_account));
+
+        // The proofs do not cover another amount
+        assert!(matches!(
+            withdraw("200", resume).await,
+            Err(AppError::StaleProofAccounts)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_resumes_from_its_own_range_proof_account() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account_data =
+            test_util::confidential_token_account_data(&owner, &mint, 1_000, 0, 0);
+        let token_account =
+            get_associated_token_address_with_program_id(&owner, &mint, &spl_token_2022::id());
+        let elgamal_signature = Signature::try_from(
+            BASE64_STANDARD
+                .decode(test_util::elgamal_signature())
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        let equality_proof_account = ProofAccount::WithdrawEquality
+            .keypair(&elgamal_signature, &token_account, 1)
+            .pubkey();
+        let range_proof_account = ProofAccount::WithdrawRange
+            .keypair(&elgamal_signature, &token_account, 1)
+            .pubkey();
+
+        let withdraw = |resume: Option<ResumeRequest>| {
+            withdraw_cb(
+                State(AppState::default()),
+                Json(WithdrawCbRequest {
+                    elgamal_signature: test_util::elgamal_signature(),
+                    aes_signature: test_util::aes_signature(),
+                    recipient_token_account: Some(token_account_data.clone()),
+                    recipient_token_account_address: None,
+                    mint_account_info: Some(test_util::confidential_mint_data(&owner, false)),
+                    withdraw_amount_lamports: "100".to_string(),
+                    multisig: None,
+                    multisig_signers: None,
+                    fee_payer: None,
+                    latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                    address_lookup_tables: None,
+                    nonce_accounts: None,
+                    nonce_account_data: None,
+                    nonce_authority: None,
+                    equality_proof_rent: None,
+                    range_proof_rent: None,
+                    priority_fee: None,
+                    bundle: None,
+                    flow_nonce: Some(1),
+                    resume,
+                    memo: None,
+                }),
+            )
+        };
+        let instructions = |response: &MultiTransactionResponse| {
+            response
+                .transactions
+                .iter()
+                .flat_map(|transaction| {
+                    let transaction: VersionedTransaction =
+                        bincode::deserialize(&BASE64_STANDARD.decode(transaction).unwrap())
+                            .unwrap();
+                    let account_keys = transaction.message.static_account_keys().to_vec();
+                    transaction
+                        .message
+                        .instructions()
+                        .iter()
+                        .map(|instruction| {
+                            (
+                                account_keys[instruction.program_id_index as usize],
+                                instruction.data.clone(),
+                                instruction
+                                    .accounts
+                                    .iter()
+                                    .map(|index| account_keys[*index as usize])
+                                    .collect::<Vec<_>>(),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // The equality proof is verified inline, so only the range proof account is allocated
+        let Json(response) = withdraw(None).await.unwrap();
+        let instructions_of_flow = instructions(&response);
+        assert!(!instructions_of_flow
+            .iter()
+            .any(|(_, _, accounts)| accounts.contains(&equality_proof_account)));
+        let range_proof_data = instructions_of_flow
+            .iter()
+            .find_map(|(program_id, data, _)| {
+                (*program_id == solana_zk_sdk::zk_elgamal_proof_program::id()).then_some(())?;
+                ProofInstruction::proof_data::<BatchedRangeProofU64Data, BatchedRangeProofContext>(
+                    data,
+                )
+            })
+            .unwrap();
+
+        // The range proof was verified, but the withdraw transaction expired
+        let Json(response) = withdraw(Some(ResumeRequest {
+            proof_accounts: vec![ProofAccountRequest {
+                address: range_proof_account.to_string(),
+                data: Some(BASE64_STANDARD.encode(ProofContextState::encode(
+                    &owner,
+                    ProofType::BatchedRangeProofU64,
+                    range_proof_data.context_data(),
+                ))),
+            }],
+        }))
+        .await
+        .unwrap();
+
+        // Its proof cannot be paired with a fresh equality proof, so the account is closed and the
+        // flow starts over
+        let instructions_of_resumed_flow = instructions(&response);
+        let position = |program_id: Pubkey, instruction_type: Option<ProofInstruction>| {
+            instructions_of_resumed_flow
+                .iter()
+                .position(|(instruction_program_id, data, accounts)| {
+                    *instruction_program_id == program_id
+                        && accounts.contains(&range_proof_account)
+                        && instruction_type.is_none_or(|instruction_type| {
+                            ProofInstruction::instruction_type(data) == Some(instruction_type)
+                        })
+                })
+                .unwrap()
+        };
+        let close = position(
+            solana_zk_sdk::zk_elgamal_proof_program::id(),
+            Some(ProofInstruction::CloseContextState),
+        );
+        let create = position(solana_sdk::system_program::id(), None);
+        let verify = position(
+            solana_zk_sdk::zk_elgamal_proof_program::id(),
+            Some(ProofInstruction::VerifyBatchedRangeProofU64),
+        );
+        let withdraw = position(spl_token_2022::id(), None);
+        assert!(close < create && create < verify && verify < withdraw);
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_with_durable_nonces() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let nonce_accounts = (0..3).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let nonces = (0..3u8)
+            .map(|i| Hash::new_from_array([i + 10; 32]))
+            .collect::<Vec<_>>();
+
+        let Json(response) = withdraw_cb(
+            State(AppState::default()),
+            Json(WithdrawCbRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                aes_signature: test_util::aes_signature(),
+                recipient_token_account: Some(test_util::confidential_token_account_data(
+                    &owner, &mint, 1_000, 0, 0,
+                )),
+                recipient_token_account_address: None,
+                mint_account_info: Some(test_util::confidential_mint_data(&owner, false)),
+                withdraw_amount_lamports: "100".to_string(),
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: None,
+                latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                address_lookup_tables: None,
+                nonce_accounts: Some(nonce_accounts.iter().map(Pubkey::to_string).collect()),
+                nonce_account_data: Some(
+                    nonces
+                        .iter()
+                        .map(|nonce| test_util::nonce_account_data(&owner, nonce))
+                        .collect(),
+                ),
+                nonce_authority: None,
+                equality_proof_rent: None,
+                range_proof_rent: None,
+                priority_fee: None,
+                bundle: None,
+                flow_nonce: None,
+                resume: None,
+                memo: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        // Every transaction advances its own nonce first and uses the nonce as its blockhash
+        let transactions = response
+            .transactions
+            .iter()
+            .map(|transaction| {
+                let bytes = BASE64_STANDARD.decode(transaction).unwrap();
+                assert!(bytes.len() <= PACKET_DATA_SIZE);
+                bincode::deserialize::<VersionedTransaction>(&bytes).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(transactions.len(), nonce_accounts.len());
+        for (index, transaction) in transactions.iter().enumerate() {
+            assert_eq!(
+                transaction.message.recent_blockhash(),
+                DurableNonce::from_blockhash(&nonces[index]).as_hash()
+            );
+
+            let account_keys = transaction.message.static_account_keys();
+            let advance_nonce = &transaction.message.instructions()[0];
+            assert_eq!(
+                bincode::deserialize::<SystemInstruction>(&advance_nonce.data).unwrap(),
+                SystemInstruction::AdvanceNonceAccount
+            );
+            assert_eq!(
+                account_keys[advance_nonce.accounts[0] as usize],
+                nonce_accounts[index]
+            );
+            assert_eq!(account_keys[advance_nonce.accounts[2] as usize], owner);
+        }
 
-    // Return a tuple containing the create account instruction and verify proof instruction.
-    Ok((create_account_ix, verify_proof_ix))
+        // The range proof is verified from the record account its chunks were written to
+        let instructions = transactions
+            .iter()
+            .flat_map(|transaction| {
+                let account_keys = transaction.message.static_account_keys();
+                transaction
+                    .message
+                    .instructions()
+                    .iter()
+                    .filter_map(|instruction| {
+                        Some((
+                            account_keys[instruction.program_id_index as usize],
+                            instruction.data.len(),
+                            account_keys[*instruction.accounts.first()? as usize],
+                        ))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let (_, _, record_account) = instructions
+            .iter()
+            .find(|(program_id, data_len, _)| {
+                *program_id == solana_zk_sdk::zk_elgamal_proof_program::id() && *data_len == 5
+            })
+            .unwrap();
+        assert!(instructions
+            .iter()
+            .any(|(program_id, _, account)| *program_id == spl_record::id()
+                && account == record_account));
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_sets_priority_fee_on_every_transaction() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let Json(response) = withdraw_cb(
+            State(AppState::default()),
+            Json(WithdrawCbRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                aes_signature: test_util::aes_signature(),
+                recipient_token_account: Some(test_util::confidential_token_account_data(
+                    &owner, &mint, 1_000, 0, 0,
+                )),
+                recipient_token_account_address: None,
+                mint_account_info: Some(test_util::confidential_mint_data(&owner, false)),
+                withdraw_amount_lamports: "100".to_string(),
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: None,
+                latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                address_lookup_tables: None,
+                nonce_accounts: None,
+                nonce_account_data: None,
+                nonce_authority: None,
+                equality_proof_rent: None,
+                range_proof_rent: None,
+                priority_fee: Some(PriorityFeeRequest {
+                    micro_lamports_per_compute_unit: Some("1000".to_string()),
+                    total_lamports: None,
+                }),
+                bundle: None,
+                flow_nonce: None,
+                resume: None,
+                memo: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        // Every transaction, the range proof verification included, starts with its compute
+        // budget instructions
+        let mut compute_unit_limits = Vec::new();
+        for transaction in response.transactions {
+            let bytes = BASE64_STANDARD.decode(transaction).unwrap();
+            assert!(bytes.len() <= PACKET_DATA_SIZE);
+            let transaction = bincode::deserialize::<VersionedTransaction>(&bytes).unwrap();
+
+            let account_keys = transaction.message.static_account_keys();
+            let instructions = transaction.message.instructions();
+            for instruction in &instructions[..2] {
+                assert_eq!(
+                    account_keys[instruction.program_id_index as usize],
+                    compute_budget::id()
+                );
+            }
+            assert_eq!(
+                ComputeBudgetInstruction::set_compute_unit_price(1000).data,
+                instructions[1].data
+            );
+            compute_unit_limits.push(instructions[0].data.clone());
+        }
+        assert_eq!(
+            compute_unit_limits[1],
+            ComputeBudgetInstruction::set_compute_unit_limit(111_300).data
+        );
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_rejects_nonce_accounts_of_another_authority() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let result = withdraw_cb(
+            State(AppState::default()),
+            Json(WithdrawCbRequest {
+                elgamal_signature: test_util::elgamal_signature(),
+                aes_signature: test_util::aes_signature(),
+                recipient_token_account: Some(test_util::confidential_token_account_data(
+                    &owner, &mint, 1_000, 0, 0,
+                )),
+                recipient_token_account_address: None,
+                mint_account_info: Some(test_util::confidential_mint_data(&owner, false)),
+                withdraw_amount_lamports: "100".to_string(),
+                multisig: None,
+                multisig_signers: None,
+                fee_payer: None,
+                latest_blockhash: Some(test_util::mock_blockhash().to_string()),
+                address_lookup_tables: None,
+                nonce_accounts: Some((0..3).map(|_| Pubkey::new_unique().to_string()).collect()),
+                nonce_account_data: Some(vec![
+                    test_util::nonce_account_data(
+                        &Pubkey::new_unique(),
+                        &Hash::new_unique()
+                    );
+                    3
+                ]),
+                nonce_authority: None,
+                equality_proof_rent: None,
+                range_proof_rent: None,
+                priority_fee: None,
+                bundle: None,
+                flow_nonce: None,
+                resume: None,
+                memo: None,
+            }),
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::NonceAuthorityMismatch)));
+    }
 }
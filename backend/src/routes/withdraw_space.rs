@@ -1,12 +1,14 @@
 use {
-    crate::{errors::AppError, models::WithdrawCbSpaceResponse},
-    axum::extract::Json,
+    crate::{errors::AppError, models::WithdrawCbSpaceResponse, state::AppState},
+    axum::extract::{Json, State},
     solana_zk_sdk::zk_elgamal_proof_program,
     std::mem::size_of,
 };
 
-/// GET handler to provide space requirements for withdraw-cb operation
-pub async fn withdraw_cb_space() -> Result<Json<WithdrawCbSpaceResponse>, AppError> {
+/// GET handler to provide space and rent requirements for withdraw-cb operation
+pub async fn withdraw_cb_space(
+    State(state): State<AppState>,
+) -> Result<Json<WithdrawCbSpaceResponse>, AppError> {
     println!("📊 Processing withdraw-cb-space request");
 
     let equality_proof_space = size_of::<zk_elgamal_proof_program::state::ProofContextState<
@@ -14,12 +16,14 @@ pub async fn withdraw_cb_space() -> Result<Json<WithdrawCbSpaceResponse>, AppErr
     >>();
 
     let range_proof_space = size_of::<zk_elgamal_proof_program::state::ProofContextState<
-        solana_zk_sdk::zk_elgamal_proof_program::proof_data::batched_range_proof::batched_range_proof_u128::BatchedRangeProofU128Data
+        solana_zk_sdk::zk_elgamal_proof_program::proof_data::batched_range_proof::BatchedRangeProofContext
     >>();
 
     Ok(Json(WithdrawCbSpaceResponse {
         equality_proof_space,
         range_proof_space,
-        message: "Space requirements for withdraw-cb proofs".to_string(),
+        equality_proof_rent: state.rent(None, equality_proof_space).await?,
+        range_proof_rent: state.rent(None, range_proof_space).await?,
+        message: "Space and rent requirements for withdraw-cb proofs".to_string(),
     }))
 }
@@ -0,0 +1,91 @@
+//! Optional RPC-backed mode.
+//!
+//! With `RPC_URL` set, requests can leave out account data, lookup table contents, mint decimals,
+//! the epoch, the slot, the latest blockhash and leftover proof accounts: the server fetches them
+//! from the RPC node instead. Rents are also read from the cluster rather than computed from the
+//! default rent.
+//! Values sent in a request always take precedence, so clients that supply everything
+//! themselves keep working unchanged.
+
+use {
+    crate::errors::AppError,
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{hash::Hash, pubkey::Pubkey},
+};
+
+/// RPC node the server reads chain state from
+pub struct Rpc {
+    client: RpcClient,
+}
+
+impl Rpc {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: RpcClient::new(url),
+        }
+    }
+
+    /// Configures the RPC node from `RPC_URL`. Returns `None` if it is not set.
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("RPC_URL").ok()?;
+        println!(
+            "✅ Fetching account data, rent and blockhashes from {}",
+            url
+        );
+
+        Some(Self::new(url))
+    }
+
+    pub async fn account_data(&self, address: &Pubkey) -> Result<Vec<u8>, AppError> {
+        println!("🌐 Fetching account data for {}", address);
+        let data = self.client.get_account_data(address).await?;
+        println!("✅ Fetched {} bytes of account data", data.len());
+
+        Ok(data)
+    }
+
+    /// Data of the accounts, `None` for those that don't exist
+    pub async fn multiple_account_data(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<Vec<u8>>>, AppError> {
+        println!("🌐 Looking up {} accounts", addresses.len());
+        let accounts = self.client.get_multiple_accounts(addresses).await?;
+
+        Ok(accounts
+            .into_iter()
+            .map(|account| account.map(|account| account.data))
+            .collect())
+    }
+
+    pub async fn latest_blockhash(&self) -> Result<Hash, AppError> {
+        let blockhash = self.client.get_latest_blockhash().await?;
+        println!("✅ Fetched latest blockhash: {}", blockhash);
+
+        Ok(blockhash)
+    }
+
+    pub async fn minimum_balance_for_rent_exemption(&self, space: usize) -> Result<u64, AppError> {
+        let rent = self
+            .client
+            .get_minimum_balance_for_rent_exemption(space)
+            .await?;
+        println!("✅ Fetched rent for {} bytes: {} lamports", space, rent);
+
+        Ok(rent)
+    }
+
+    pub async fn slot(&self) -> Result<u64, AppError> {
+        let slot = self.client.get_slot().await?;
+        println!("✅ Fetched current slot: {}", slot);
+
+        Ok(slot)
+    }
+
+    pub async fn epoch(&self) -> Result<u64, AppError> {
+        let epoch = self.client.get_epoch_info().await?.epoch;
+        println!("✅ Fetched current epoch: {}", epoch);
+
+        Ok(epoch)
+    }
+}
@@ -0,0 +1,228 @@
+use {
+    super::{SignFuture, SignerProvider},
+    crate::errors::AppError,
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    google_cloud_kms::{
+        client::{Client, ClientConfig},
+        grpc::kms::v1::{AsymmetricSignRequest, GetPublicKeyRequest},
+    },
+    solana_sdk::{
+        pubkey::{Pubkey, PUBKEY_BYTES},
+        signature::Signature,
+        signer::SignerError,
+    },
+    std::{future::Future, pin::Pin},
+};
+
+/// Future returned by the `KmsClient` calls
+pub type KmsFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// The subset of the Cloud KMS API used to sign with an `EC_SIGN_ED25519` key version
+pub trait KmsClient: Send + Sync {
+    /// Returns the PEM encoded public key of the key version
+    fn get_public_key<'a>(&'a self, key_name: &'a str) -> KmsFuture<'a, String>;
+
+    /// Signs raw data (Ed25519 keys sign the message itself, not a digest)
+    fn asymmetric_sign<'a>(&'a self, key_name: &'a str, data: &'a [u8]) -> KmsFuture<'a, Vec<u8>>;
+}
+
+impl KmsClient for Client {
+    fn get_public_key<'a>(&'a self, key_name: &'a str) -> KmsFuture<'a, String> {
+        Box::pin(async move {
+            let request = GetPublicKeyRequest {
+                name: key_name.to_string(),
+            };
+            Client::get_public_key(self, request, None)
+                .await
+                .map(|public_key| public_key.pem)
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    fn asymmetric_sign<'a>(&'a self, key_name: &'a str, data: &'a [u8]) -> KmsFuture<'a, Vec<u8>> {
+        Box::pin(async move {
+            let request = AsymmetricSignRequest {
+                name: key_name.to_string(),
+                data: data.to_vec(),
+                ..Default::default()
+            };
+            Client::asymmetric_sign(self, request, None)
+                .await
+                .map(|response| response.signature)
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
+/// Signs with an Ed25519 key version held in Google Cloud KMS
+pub struct GcpKmsSigner<C: KmsClient> {
+    client: C,
+    key_name: String,
+    pubkey: Pubkey,
+}
+
+impl GcpKmsSigner<Client> {
+    /// Connects with the default Google credentials of the environment
+    pub async fn from_env(key_name: String) -> Result<Self, AppError> {
+        let config = ClientConfig::default()
+            .with_auth()
+            .await
+            .map_err(|e| kms_error(e.to_string()))?;
+        let client = Client::new(config)
+            .await
+            .map_err(|e| kms_error(e.to_string()))?;
+
+        Self::new(client, key_name).await
+    }
+}
+
+impl<C: KmsClient> GcpKmsSigner<C> {
+    /// Fetches the public key of the key version once, so it doesn't cost a KMS call per request
+    pub async fn new(client: C, key_name: String) -> Result<Self, AppError> {
+        let pem = client.get_public_key(&key_name).await.map_err(kms_error)?;
+        let pubkey = pubkey_from_pem(&pem)?;
+        println!("✅ Loaded KMS key {} with pubkey {}", key_name, pubkey);
+
+        Ok(Self {
+            client,
+            key_name,
+            pubkey,
+        })
+    }
+}
+
+impl<C: KmsClient> SignerProvider for GcpKmsSigner<C> {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    fn sign_message<'a>(&'a self, message: &'a [u8]) -> SignFuture<'a> {
+        Box::pin(async move {
+            let signature = self
+                .client
+                .asymmetric_sign(&self.key_name, message)
+                .await
+                .map_err(kms_error)?;
+
+            Signature::try_from(signature.as_slice())
+                .map_err(|_| kms_error(format!("unexpected signature length {}", signature.len())))
+        })
+    }
+}
+
+fn kms_error(message: String) -> AppError {
+    println!("⛔️ Cloud KMS error: {}", message);
+    AppError::SignerError(SignerError::Custom(format!("Cloud KMS: {}", message)))
+}
+
+// DER prefix of an Ed25519 SubjectPublicKeyInfo, which is followed by the raw 32 byte key
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+// Rejects any other key type, which would otherwise yield an unrelated fee payer pubkey
+fn pubkey_from_pem(pem: &str) -> Result<Pubkey, AppError> {
+    let der = BASE64_STANDARD.decode(
+        pem.lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>(),
+    )?;
+    match der.strip_prefix(&ED25519_SPKI_PREFIX[..]) {
+        Some(key) if key.len() == PUBKEY_BYTES => Ok(Pubkey::try_from(key).unwrap()),
+        _ => Err(kms_error("public key is not an Ed25519 key".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::signature::{Keypair, Signer},
+    };
+
+    // DER prefix of an EC P-256 SubjectPublicKeyInfo, followed by a 65 byte uncompressed point
+    const P256_SPKI_PREFIX: [u8; 26] = [
+        0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+    ];
+
+    /// In-process stand-in for Cloud KMS holding a single key version, Ed25519 unless its DER
+    /// public key is overridden
+    struct FakeKms {
+        key_name: String,
+        keypair: Keypair,
+        public_key_der: Option<Vec<u8>>,
+    }
+
+    impl KmsClient for FakeKms {
+        fn get_public_key<'a>(&'a self, key_name: &'a str) -> KmsFuture<'a, String> {
+            Box::pin(async move {
+                if key_name != self.key_name {
+                    return Err(format!("key {} not found", key_name));
+                }
+                let der = self.public_key_der.clone().unwrap_or_else(|| {
+                    [&ED25519_SPKI_PREFIX[..], self.keypair.pubkey().as_ref()].concat()
+                });
+                Ok(format!(
+                    "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+                    BASE64_STANDARD.encode(der)
+                ))
+            })
+        }
+
+        fn asymmetric_sign<'a>(
+            &'a self,
+            key_name: &'a str,
+            data: &'a [u8],
+        ) -> KmsFuture<'a, Vec<u8>> {
+            Box::pin(async move {
+                if key_name != self.key_name {
+                    return Err(format!("key {} not found", key_name));
+                }
+                Ok(self.keypair.sign_message(data).as_ref().to_vec())
+            })
+        }
+    }
+
+    fn fake_kms() -> FakeKms {
+        FakeKms {
+            key_name: "projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1"
+                .to_string(),
+            keypair: Keypair::new(),
+            public_key_der: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_with_fake_kms() {
+        let kms = fake_kms();
+        let key_name = kms.key_name.clone();
+        let expected_pubkey = kms.keypair.pubkey();
+
+        let signer = GcpKmsSigner::new(kms, key_name).await.unwrap();
+        assert_eq!(signer.pubkey(), expected_pubkey);
+
+        let signature = signer.sign_message(b"message").await.unwrap();
+        assert!(signature.verify(expected_pubkey.as_ref(), b"message"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_kms_key() {
+        assert!(matches!(
+            GcpKmsSigner::new(fake_kms(), "unknown".to_string()).await,
+            Err(AppError::SignerError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_non_ed25519_kms_key() {
+        let mut kms = fake_kms();
+        let key_name = kms.key_name.clone();
+        kms.public_key_der = Some([&P256_SPKI_PREFIX[..], &[4; 65]].concat());
+
+        assert!(matches!(
+            GcpKmsSigner::new(kms, key_name).await,
+            Err(AppError::SignerError(_))
+        ));
+    }
+}
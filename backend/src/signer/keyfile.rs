@@ -0,0 +1,61 @@
+use {
+    super::{SignFuture, SignerProvider},
+    crate::errors::AppError,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{read_keypair_file, Keypair},
+        signer::{Signer, SignerError},
+    },
+};
+
+/// Signs with a keypair loaded from a local Solana keypair file (JSON byte array)
+pub struct LocalKeyfileSigner {
+    keypair: Keypair,
+}
+
+impl LocalKeyfileSigner {
+    pub fn new(keypair: Keypair) -> Self {
+        Self { keypair }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, AppError> {
+        let keypair = read_keypair_file(path).map_err(|e| {
+            println!("⛔️ Failed to read keypair file {}: {}", path, e);
+            AppError::SignerError(SignerError::Custom(format!(
+                "failed to read keypair file: {}",
+                e
+            )))
+        })?;
+
+        Ok(Self::new(keypair))
+    }
+}
+
+impl SignerProvider for LocalKeyfileSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    fn sign_message<'a>(&'a self, message: &'a [u8]) -> SignFuture<'a> {
+        Box::pin(async move { Ok(self.keypair.try_sign_message(message)?) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::signature::write_keypair_file};
+
+    #[tokio::test]
+    async fn test_sign_with_keyfile() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir().join(format!("sponsor-{}.json", keypair.pubkey()));
+        write_keypair_file(&keypair, &path).unwrap();
+
+        let signer = LocalKeyfileSigner::from_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+        let signature = signer.sign_message(b"message").await.unwrap();
+        assert!(signature.verify(keypair.pubkey().as_ref(), b"message"));
+    }
+}
@@ -0,0 +1,309 @@
+//! Server-side signing for sponsored transactions.
+//!
+//! When a request sets `fee_payer` to the sponsor key, the server co-signs the fee payer slot of
+//! the generated transactions (if the sponsorship policy allows it), so users without SOL can
+//! still submit them. The key itself lives behind a `SignerProvider`: a local keypair file or a
+//! Google Cloud KMS key.
+
+mod gcp_kms;
+mod keyfile;
+mod policy;
+
+pub use {gcp_kms::GcpKmsSigner, keyfile::LocalKeyfileSigner, policy::SponsorshipPolicy};
+
+use {
+    crate::errors::AppError,
+    solana_sdk::{
+        borsh1::try_from_slice_unchecked,
+        compute_budget::{self, ComputeBudgetInstruction},
+        pubkey::Pubkey,
+        signature::Signature,
+        system_instruction::SystemInstruction,
+        transaction::VersionedTransaction,
+    },
+    solana_sdk_ids::system_program,
+    std::{future::Future, pin::Pin},
+};
+
+/// Base fee charged per signature
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// Compute units given to each instruction when no limit is set
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+/// Maximum compute units of a transaction
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+
+/// Future returned by `SignerProvider::sign_message`
+pub type SignFuture<'a> = Pin<Box<dyn Future<Output = Result<Signature, AppError>> + Send + 'a>>;
+
+/// A key the server can sign transaction messages with
+pub trait SignerProvider: Send + Sync {
+    fn pubkey(&self) -> Pubkey;
+
+    /// Signs the serialized message of a transaction
+    fn sign_message<'a>(&'a self, message: &'a [u8]) -> SignFuture<'a>;
+}
+
+/// Fee payer key held by the server, together with the policy of what it sponsors
+pub struct Sponsor {
+    provider: Box<dyn SignerProvider>,
+    policy: SponsorshipPolicy,
+}
+
+impl Sponsor {
+    pub fn new(provider: Box<dyn SignerProvider>, policy: SponsorshipPolicy) -> Self {
+        Self { provider, policy }
+    }
+
+    /// Configures the sponsor from the environment:
+    /// `SPONSOR_KEYPAIR_PATH` (local keypair file) or `SPONSOR_KMS_KEY` (Cloud KMS key version),
+    /// plus the `SPONSOR_ROUTES`, `SPONSOR_MAX_AMOUNT` and `SPONSOR_MAX_LAMPORTS` policy. Returns `None` if no key is set.
+    pub async fn from_env() -> Result<Option<Self>, AppError> {
+        let provider: Box<dyn SignerProvider> =
+            if let Ok(keypair_path) = std::env::var("SPONSOR_KEYPAIR_PATH") {
+                Box::new(LocalKeyfileSigner::from_file(&keypair_path)?)
+            } else if let Ok(key_name) = std::env::var("SPONSOR_KMS_KEY") {
+                Box::new(GcpKmsSigner::from_env(key_name).await?)
+            } else {
+                return Ok(None);
+            };
+
+        let policy = SponsorshipPolicy::from_env()?;
+        println!(
+            "✅ Sponsoring fees with {} for routes {:?}",
+            provider.pubkey(),
+            policy.routes()
+        );
+
+        Ok(Some(Self::new(provider, policy)))
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.provider.pubkey()
+    }
+
+    pub fn policy(&self) -> &SponsorshipPolicy {
+        &self.policy
+    }
+
+    /// Signs the fee payer slot of every transaction paid by the sponsor key.
+    /// Transactions paid by anyone else are left untouched.
+    pub async fn co_sign(
+        &self,
+        route: &str,
+        amount: u64,
+        transactions: &mut [VersionedTransaction],
+    ) -> Result<(), AppError> {
+        let sponsor = self.pubkey();
+        let sponsored = transactions
+            .iter()
+            .enumerate()
+            .filter(|(_, transaction)| {
+                transaction.message.static_account_keys().first() == Some(&sponsor)
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        if sponsored.is_empty() {
+            return Ok(());
+        }
+
+        let lamports = sponsored
+            .iter()
+            .map(|index| lamports_spent(&sponsor, &transactions[*index]))
+            .sum();
+        self.policy.check(route, amount, lamports)?;
+
+        for index in sponsored {
+            let transaction = &mut transactions[index];
+            let signature = self
+                .provider
+                .sign_message(&transaction.message.serialize())
+                .await?;
+            // The fee payer is always the first signer
+            transaction.signatures[0] = signature;
+        }
+        println!(
+            "✅ Co-signed {} transactions as fee payer {}",
+            route, sponsor
+        );
+
+        Ok(())
+    }
+}
+
+/// Lamports a transaction costs its fee payer: signature fees, the priority fee, and the
+/// transfers and account creations it funds
+fn lamports_spent(fee_payer: &Pubkey, transaction: &VersionedTransaction) -> u64 {
+    let message = &transaction.message;
+    let account_keys = message.static_account_keys();
+    let mut compute_unit_limit = None;
+    let mut compute_unit_price = 0u64;
+    let mut instruction_count = 0u64;
+    let mut lamports = 0u64;
+
+    for instruction in message.instructions() {
+        let program_id = account_keys.get(instruction.program_id_index as usize);
+        if program_id == Some(&compute_budget::id()) {
+            match try_from_slice_unchecked::<ComputeBudgetInstruction>(&instruction.data) {
+                Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => {
+                    compute_unit_limit = Some(limit as u64)
+                }
+                Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => {
+                    compute_unit_price = price
+                }
+                _ => {}
+            }
+            continue;
+        }
+        instruction_count += 1;
+
+        // Only lamports moved out of the fee payer, always the first account key
+        if program_id != Some(&system_program::id())
+            || instruction
+                .accounts
+                .first()
+                .map(|index| &account_keys[*index as usize])
+                != Some(fee_payer)
+        {
+            continue;
+        }
+        match bincode::deserialize::<SystemInstruction>(&instruction.data) {
+            Ok(SystemInstruction::Transfer { lamports: amount })
+            | Ok(SystemInstruction::CreateAccount {
+                lamports: amount, ..
+            })
+            | Ok(SystemInstruction::CreateAccountWithSeed {
+                lamports: amount, ..
+            }) => lamports = lamports.saturating_add(amount),
+            _ => {}
+        }
+    }
+
+    let compute_unit_limit = compute_unit_limit.unwrap_or_else(|| {
+        (instruction_count * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT).min(MAX_COMPUTE_UNIT_LIMIT)
+    });
+    let priority_fee =
+        (compute_unit_price as u128 * compute_unit_limit as u128).div_ceil(1_000_000);
+    let signature_fee = message.header().num_required_signatures as u64 * LAMPORTS_PER_SIGNATURE;
+
+    lamports
+        .saturating_add(signature_fee)
+        .saturating_add(priority_fee.min(u64::MAX as u128) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{
+            hash::Hash,
+            instruction::Instruction,
+            message::{v0, VersionedMessage},
+            signature::Keypair,
+            signer::Signer,
+            system_instruction,
+        },
+        spl_memo::build_memo,
+    };
+
+    fn transaction(fee_payer: &Pubkey) -> VersionedTransaction {
+        transaction_with(fee_payer, &[build_memo(b"sponsored", &[])])
+    }
+
+    fn transaction_with(fee_payer: &Pubkey, instructions: &[Instruction]) -> VersionedTransaction {
+        let message =
+            v0::Message::try_compile(fee_payer, instructions, &[], Hash::default()).unwrap();
+        VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::V0(message),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_co_sign_sponsored_transactions() {
+        let keypair = Keypair::new();
+        let sponsor = Sponsor::new(
+            Box::new(LocalKeyfileSigner::new(keypair.insecure_clone())),
+            SponsorshipPolicy::new(vec!["deposit-cb".to_string()], Some(1_000), None),
+        );
+
+        let mut transactions = [
+            transaction(&keypair.pubkey()),
+            transaction(&Pubkey::new_unique()),
+        ];
+        sponsor
+            .co_sign("deposit-cb", 100, &mut transactions)
+            .await
+            .unwrap();
+
+        assert!(transactions[0].signatures[0].verify(
+            keypair.pubkey().as_ref(),
+            &transactions[0].message.serialize()
+        ));
+        assert_eq!(transactions[1].signatures[0], Signature::default());
+
+        assert!(matches!(
+            sponsor.co_sign("transfer-cb", 100, &mut transactions).await,
+            Err(AppError::SponsorshipDenied)
+        ));
+        assert!(matches!(
+            sponsor
+                .co_sign("deposit-cb", 5_000, &mut transactions)
+                .await,
+            Err(AppError::SponsorshipDenied)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_co_sign_caps_sponsor_lamports() {
+        let keypair = Keypair::new();
+        let sponsor = Sponsor::new(
+            Box::new(LocalKeyfileSigner::new(keypair.insecure_clone())),
+            SponsorshipPolicy::new(vec!["transfer-cb".to_string()], None, Some(100_000)),
+        );
+        let fee_payer = keypair.pubkey();
+
+        // Signature fee plus 10_000 micro-lamports for 300_000 compute units
+        let mut transactions = [transaction_with(
+            &fee_payer,
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+                ComputeBudgetInstruction::set_compute_unit_price(10_000),
+                build_memo(b"sponsored", &[]),
+            ],
+        )];
+        assert_eq!(lamports_spent(&fee_payer, &transactions[0]), 5_000 + 3_000);
+        sponsor
+            .co_sign("transfer-cb", 0, &mut transactions)
+            .await
+            .unwrap();
+
+        // A tip paid by the sponsor counts towards the cap
+        let mut transactions = [transaction_with(
+            &fee_payer,
+            &[system_instruction::transfer(
+                &fee_payer,
+                &Pubkey::new_unique(),
+                1_000_000,
+            )],
+        )];
+        assert_eq!(lamports_spent(&fee_payer, &transactions[0]), 1_005_000);
+        assert!(matches!(
+            sponsor.co_sign("transfer-cb", 0, &mut transactions).await,
+            Err(AppError::SponsorshipDenied)
+        ));
+
+        // So does a priority fee without a compute unit limit
+        let mut transactions = [transaction_with(
+            &fee_payer,
+            &[
+                ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+                build_memo(b"sponsored", &[]),
+            ],
+        )];
+        assert_eq!(lamports_spent(&fee_payer, &transactions[0]), 205_000);
+        assert!(matches!(
+            sponsor.co_sign("transfer-cb", 0, &mut transactions).await,
+            Err(AppError::SponsorshipDenied)
+        ));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::errors::AppError;
+
+/// Which routes the sponsor pays fees for, up to which token amount, and how many lamports it
+/// spends at most per request (fees, priority fees, tips and rent)
+pub struct SponsorshipPolicy {
+    routes: Vec<String>,
+    max_amount: Option<u64>,
+    max_lamports: Option<u64>,
+}
+
+impl SponsorshipPolicy {
+    pub fn new(routes: Vec<String>, max_amount: Option<u64>, max_lamports: Option<u64>) -> Self {
+        Self {
+            routes,
+            max_amount,
+            max_lamports,
+        }
+    }
+
+    /// Reads `SPONSOR_ROUTES` (comma separated route names, e.g. `transfer-cb,withdraw-cb`) and
+    /// the optional `SPONSOR_MAX_AMOUNT` and `SPONSOR_MAX_LAMPORTS`. Nothing is sponsored unless
+    /// routes are listed.
+    pub fn from_env() -> Result<Self, AppError> {
+        let routes = std::env::var("SPONSOR_ROUTES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|route| route.trim().trim_start_matches('/').to_string())
+            .filter(|route| !route.is_empty())
+            .collect::<Vec<_>>();
+        if routes.is_empty() {
+            println!("⚠️ SPONSOR_ROUTES is empty, no route will be sponsored");
+        }
+
+        let max_amount = std::env::var("SPONSOR_MAX_AMOUNT")
+            .ok()
+            .map(|max_amount| max_amount.parse::<u64>())
+            .transpose()
+            .map_err(|_| AppError::InvalidAmount)?;
+
+        let max_lamports = std::env::var("SPONSOR_MAX_LAMPORTS")
+            .ok()
+            .map(|max_lamports| max_lamports.parse::<u64>())
+            .transpose()
+            .map_err(|_| AppError::InvalidAmount)?;
+
+        Ok(Self::new(routes, max_amount, max_lamports))
+    }
+
+    pub fn routes(&self) -> &[String] {
+        &self.routes
+    }
+
+    pub fn max_amount(&self) -> Option<u64> {
+        self.max_amount
+    }
+
+    pub fn max_lamports(&self) -> Option<u64> {
+        self.max_lamports
+    }
+
+    /// Checks a request on `route` moving `amount` tokens and costing the sponsor `lamports`
+    pub fn check(&self, route: &str, amount: u64, lamports: u64) -> Result<(), AppError> {
+        if !self.routes.iter().any(|allowed| allowed == route) {
+            println!("⛔️ Route {} is not sponsored", route);
+            return Err(AppError::SponsorshipDenied);
+        }
+
+        if let Some(max_amount) = self.max_amount {
+            if amount > max_amount {
+                println!(
+                    "⛔️ Amount {} exceeds the sponsored maximum of {}",
+                    amount, max_amount
+                );
+                return Err(AppError::SponsorshipDenied);
+            }
+        }
+
+        if let Some(max_lamports) = self.max_lamports {
+            if lamports > max_lamports {
+                println!(
+                    "⛔️ Spending {} lamports exceeds the sponsored maximum of {}",
+                    lamports, max_lamports
+                );
+                return Err(AppError::SponsorshipDenied);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use {
+    crate::{
+        block_engine::BlockEngine,
+        errors::AppError,
+        models::{AddressLookupTableRequest, ProofAccountRequest},
+        routes::util::{parse_base58_pubkey, parse_latest_blockhash, parse_rent_override},
+        rpc::Rpc,
+        signer::Sponsor,
+    },
+    base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _},
+    solana_address_lookup_table_interface::state::AddressLookupTable,
+    solana_message::AddressLookupTableAccount,
+    solana_sdk::{hash::Hash, pubkey::Pubkey, rent::Rent, transaction::VersionedTransaction},
+    spl_token_2022::{extension::StateWithExtensionsOwned, state::Mint},
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// State shared by the route handlers
+#[derive(Clone, Default)]
+pub struct AppState {
+    pub sponsor: Option<Arc<Sponsor>>,
+    pub rpc: Option<Arc<Rpc>>,
+    pub block_engine: Option<Arc<BlockEngine>>,
+}
+
+impl AppState {
+    /// Co-signs the transactions paid by the server's sponsor key, if one is configured
+    pub async fn co_sign(
+        &self,
+        route: &str,
+        amount: u64,
+        transactions: &mut [VersionedTransaction],
+    ) -> Result<(), AppError> {
+        match &self.sponsor {
+            Some(sponsor) => sponsor.co_sign(route, amount, transactions).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `pubkey` is the server's sponsor key
+    pub fn is_sponsor(&self, pubkey: &Pubkey) -> bool {
+        self.sponsor
+            .as_ref()
+            .is_some_and(|sponsor| sponsor.pubkey() == *pubkey)
+    }
+
+    fn rpc(&self, field: &'static str) -> Result<&Rpc, AppError> {
+        self.rpc.as_deref().ok_or_else(|| {
+            println!("⛔️ {} is missing and no RPC endpoint is configured", field);
+            AppError::MissingRequestField(field)
+        })
+    }
+
+    /// Returns the base64 account data from the request, or fetches the account at `address`
+    pub async fn account_data(
+        &self,
+        data: Option<&String>,
+        address: Option<&String>,
+        field: &'static str,
+    ) -> Result<Vec<u8>, AppError> {
+        if let Some(data) = data {
+            return Ok(BASE64_STANDARD.decode(data)?);
+        }
+
+        let rpc = self.rpc(field)?;
+        let address = parse_base58_pubkey(address.ok_or(AppError::MissingRequestField(field))?)?;
+        rpc.account_data(&address).await
+    }
+
+    /// Returns the block engine bundles are submitted to
+    pub fn block_engine(&self) -> Result<&BlockEngine, AppError> {
+        self.block_engine.as_deref().ok_or_else(|| {
+            println!("⛔️ No block engine is configured");
+            AppError::BlockEngineNotConfigured
+        })
+    }
+
+    /// Returns the tip account from the request, or a random one of the block engine.
+    /// Only the block engine's own tip accounts are accepted.
+    pub async fn tip_account(&self, tip_account: Option<&String>) -> Result<Pubkey, AppError> {
+        let block_engine = self.block_engine()?;
+        match tip_account {
+            Some(tip_account) => {
+                let tip_account = parse_base58_pubkey(tip_account)?;
+                if !block_engine.tip_accounts().await?.contains(&tip_account) {
+                    println!(
+                        "⛔️ {} is not a tip account of the block engine",
+                        tip_account
+                    );
+                    return Err(AppError::InvalidTipAccount);
+                }
+                Ok(tip_account)
+            }
+            None => block_engine.tip_account().await,
+        }
+    }
+
+    /// Returns the mint data from the request, or fetches the mint account
+    pub async fn mint_data(
+        &self,
+        data: Option<&String>,
+        mint: &Pubkey,
+        field: &'static str,
+    ) -> Result<Vec<u8>, AppError> {
+        match data {
+            Some(data) => Ok(BASE64_STANDARD.decode(data)?),
+            None => self.rpc(field)?.account_data(mint).await,
+        }
+    }
+
+    /// Returns the mint decimals from the request, or reads them from the mint account
+    pub async fn mint_decimals(&self, decimals: Option<u8>, mint: &Pubkey) -> Result<u8, AppError> {
+        if let Some(decimals) = decimals {
+            return Ok(decimals);
+        }
+
+        let mint_data = self.rpc("mint_decimals")?.account_data(mint).await?;
+        Ok(StateWithExtensionsOwned::<Mint>::unpack(mint_data)?
+            .base
+            .decimals)
+    }
+
+    /// Returns the data of the accounts among `candidates` listed in the request, or of those
+    /// that exist according to the RPC node
+    pub async fn existing_accounts(
+        &self,
+        accounts: Option<&Vec<ProofAccountRequest>>,
+        candidates: &[Pubkey],
+        field: &'static str,
+    ) -> Result<HashMap<Pubkey, Vec<u8>>, AppError> {
+        let Some(accounts) = accounts else {
+            let data = self.rpc(field)?.multiple_account_data(candidates).await?;
+            return Ok(candidates
+                .iter()
+                .zip(data)
+                .filter_map(|(candidate, data)| Some((*candidate, data?)))
+                .collect());
+        };
+
+        let mut existing = HashMap::new();
+        for account in accounts {
+            let address = parse_base58_pubkey(&account.address)?;
+            if !candidates.contains(&address) {
+                println!("⛔️ {} is not among the expected accounts", address);
+                return Err(AppError::UnknownProofAccount);
+            }
+
+            let data = self
+                .account_data(account.data.as_ref(), Some(&account.address), field)
+                .await?;
+            existing.insert(address, data);
+        }
+
+        Ok(existing)
+    }
+
+    /// Returns the blockhash from the request, or the latest one from the RPC node
+    pub async fn latest_blockhash(
+        &self,
+        latest_blockhash: Option<&String>,
+    ) -> Result<Hash, AppError> {
+        match latest_blockhash {
+            Some(latest_blockhash) => parse_latest_blockhash(latest_blockhash),
+            None => self.rpc("latest_blockhash")?.latest_blockhash().await,
+        }
+    }
+
+    /// Returns the lookup tables of the request, fetching the contents of those sent without them
+    pub async fn lookup_tables(
+        &self,
+        lookup_tables: Option<&Vec<AddressLookupTableRequest>>,
+    ) -> Result<Vec<AddressLookupTableAccount>, AppError> {
+        let mut accounts = Vec::new();
+        for lookup_table in lookup_tables.into_iter().flatten() {
+            let key = parse_base58_pubkey(&lookup_table.address)?;
+            let addresses = match &lookup_table.addresses {
+                Some(addresses) => addresses
+                    .iter()
+                    .map(|address| parse_base58_pubkey(address))
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => {
+                    let data = self
+                        .rpc("address_lookup_tables.addresses")?
+                        .account_data(&key)
+                        .await?;
+                    AddressLookupTable::deserialize(&data)
+                        .map_err(|_| AppError::InvalidAddressLookupTable)?
+                        .addresses
+                        .to_vec()
+                }
+            };
+            println!("✅ Lookup table {} with {} addresses", key, addresses.len());
+
+            accounts.push(AddressLookupTableAccount { key, addresses });
+        }
+
+        Ok(accounts)
+    }
+
+    /// Returns the slot from the request, or the current one from the RPC node
+    pub async fn slot(&self, slot: Option<u64>) -> Result<u64, AppError> {
+        match slot {
+            Some(slot) => Ok(slot),
+            None => self.rpc("recent_slot")?.slot().await,
+        }
+    }
+
+    /// Returns the rent from the request, or the rent-exempt minimum for `space` bytes, fetched
+    /// from the RPC node if one is configured
+    pub async fn rent(&self, rent: Option<&String>, space: usize) -> Result<u64, AppError> {
+        if let Some(rent) = parse_rent_override(rent)? {
+            return Ok(rent);
+        }
+
+        match &self.rpc {
+            Some(rpc) => rpc.minimum_balance_for_rent_exemption(space).await,
+            None => Ok(Rent::default().minimum_balance(space)),
+        }
+    }
+
+    /// Returns the epoch from the request, or the current one from the RPC node
+    pub async fn epoch(&self, epoch: Option<u64>) -> Result<u64, AppError> {
+        match epoch {
+            Some(epoch) => Ok(epoch),
+            None => self.rpc("epoch")?.epoch().await,
+        }
+    }
+}